@@ -11,15 +11,25 @@
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
-    // on the linker search path.
+    // on the linker search path. When building for the bootloader,
+    // we use the layout of `memory-dfu.x` instead, and with the
+    // softdevice the one of `memory-ble.x`, which leaves it its RAM.
+    let memory: &[u8] = if env::var_os("CARGO_FEATURE_BLE").is_some() {
+        include_bytes!("memory-ble.x")
+    } else if env::var_os("CARGO_FEATURE_DFU").is_some() {
+        include_bytes!("memory-dfu.x")
+    } else {
+        include_bytes!("memory.x")
+    };
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(memory)
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
@@ -28,8 +38,48 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=memory-dfu.x");
+    println!("cargo:rerun-if-changed=memory-ble.x");
 
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
     println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
+
+    // Make the git revision available to the firmware, for the device information service.
+    let git_hash = git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+
+    // The hash changes with a checkout (HEAD), a commit to the current branch (its ref file), or
+    // when refs get packed. Staging files doesn't change it, so the index isn't watched. Missing files would rerun the build script every time, so they are
+    // skipped. A packed branch gets its ref file back with the next commit, in its directory.
+    let mut watched: Vec<PathBuf> = ["HEAD", "packed-refs"]
+        .into_iter()
+        .filter_map(git_path)
+        .collect();
+    if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]).and_then(|r| git_path(&r)) {
+        if branch.exists() {
+            watched.push(branch);
+        } else {
+            watched.extend(branch.parent().map(Path::to_path_buf));
+        }
+    }
+    for path in watched.iter().filter(|path| path.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+/// The path of a file in the git directory.
+fn git_path(file: &str) -> Option<PathBuf> {
+    git(&["rev-parse", "--git-path", file]).map(PathBuf::from)
+}
+
+/// Run a git command, returning its trimmed output.
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
 }
@@ -0,0 +1,24 @@
+MEMORY
+{
+  /* NOTE 1 K = 1 KiBi = 1024 bytes */
+  /* The layout of memory-dfu.x, leaving the RAM below 0x20020000 to the softdevice S140 7.x */
+  MBR                               : ORIGIN = 0x00000000, LENGTH = 4K
+  SOFTDEVICE                        : ORIGIN = 0x00001000, LENGTH = 155648
+  FLASH                             : ORIGIN = 0x00027000, LENGTH = 421888
+  DFU                               : ORIGIN = 0x0008F000, LENGTH = 425984
+  STORAGE                           : ORIGIN = 0x000F8000, LENGTH = 4K
+  BOOTLOADER_STATE                  : ORIGIN = 0x000ff000, LENGTH = 4K
+  RAM                         (rwx) : ORIGIN = 0x20020000, LENGTH = 128K
+}
+
+__bootloader_active_start = ORIGIN(FLASH);
+__bootloader_active_end = ORIGIN(FLASH) + LENGTH(FLASH);
+
+__bootloader_state_start = ORIGIN(BOOTLOADER_STATE);
+__bootloader_state_end = ORIGIN(BOOTLOADER_STATE) + LENGTH(BOOTLOADER_STATE);
+
+__bootloader_dfu_start = ORIGIN(DFU);
+__bootloader_dfu_end = ORIGIN(DFU) + LENGTH(DFU);
+
+__storage_start = ORIGIN(STORAGE);
+__storage_end = ORIGIN(STORAGE) + LENGTH(STORAGE);
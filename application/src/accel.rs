@@ -0,0 +1,61 @@
+//! Accelerometer input, detecting tilt, shake and tap gestures.
+//!
+//! The accelerometer is mounted with its x axis along the strip. Tilting the strip moves the
+//! liquid of the liquid mode, shaking it sparkles, and tapping it twice switches to the next mode.
+
+use crate::runner::Msg;
+use ector::{Actor, Address, Inbox};
+use embassy_time::{Duration, Timer};
+use neopixel_logic::accel::{Gesture, Gestures};
+
+pub use neopixel_logic::accel::{Accelerometer, Lis3dh, LIS3DH_ADDRESS};
+
+/// Interval of sampling the accelerometer, the thresholds of the gestures count samples
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The message for the runner, following a gesture.
+fn to_msg(gesture: Gesture) -> Msg {
+    match gesture {
+        Gesture::Tilt(tilt) => Msg::Tilt(tilt),
+        Gesture::Shake => Msg::Sparkle,
+        Gesture::DoubleTap => Msg::Next,
+    }
+}
+
+pub struct AccelInput<A: Accelerometer + 'static> {
+    accel: A,
+    runner: Address<Msg>,
+    gestures: Gestures,
+}
+
+impl<A: Accelerometer + 'static> AccelInput<A> {
+    pub fn new(accel: A, runner: Address<Msg>) -> Self {
+        Self {
+            accel,
+            runner,
+            gestures: Gestures::new(),
+        }
+    }
+}
+
+#[ector::actor]
+impl<A: Accelerometer + 'static> Actor for AccelInput<A> {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        loop {
+            match self.gestures.poll(&mut self.accel) {
+                Ok(Some(gesture)) => {
+                    defmt::debug!("Gesture: {}", defmt::Debug2Format(&gesture));
+                    self.runner.try_notify(to_msg(gesture)).ok();
+                }
+                Ok(None) => {}
+                Err(_) => defmt::warn!("Failed to read the accelerometer"),
+            }
+            Timer::after(SAMPLE_INTERVAL).await;
+        }
+    }
+}
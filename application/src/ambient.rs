@@ -0,0 +1,53 @@
+//! Automatic brightness, following the ambient light.
+//!
+//! The light level is measured by a phototransistor (like the TEMT6000) with a load resistor,
+//! sampled by the SAADC. The readings are smoothed, and mapped to a brightness along a curve, in the
+//! `neopixel-logic` crate.
+
+use crate::runner::Msg;
+use ector::{Actor, Address, Inbox};
+use embassy_nrf::saadc::Saadc;
+use embassy_time::{Duration, Timer};
+use neopixel_logic::ambient::{to_lux, AutoBrightness};
+
+pub use neopixel_logic::ambient::Curve;
+
+/// Interval of sampling the light level
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct AmbientSensor {
+    saadc: Saadc<'static, 1>,
+    runner: Address<Msg>,
+    auto: AutoBrightness,
+}
+
+impl AmbientSensor {
+    pub fn new(saadc: Saadc<'static, 1>, runner: Address<Msg>, curve: Curve) -> Self {
+        Self {
+            saadc,
+            runner,
+            auto: AutoBrightness::new(curve),
+        }
+    }
+}
+
+#[ector::actor]
+impl Actor for AmbientSensor {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        let mut buf = [0; 1];
+        loop {
+            self.saadc.sample(&mut buf).await;
+            let lux = to_lux(buf[0]);
+            if let Some(brightness) = self.auto.update(lux) {
+                defmt::info!("Ambient light: {} lux, brightness: {}", lux, brightness);
+                self.runner.try_notify(Msg::Ambient(brightness)).ok();
+            }
+            Timer::after(SAMPLE_INTERVAL).await;
+        }
+    }
+}
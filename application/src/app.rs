@@ -1,7 +1,9 @@
+use ector::Address;
 use embassy_executor::Spawner;
-use nrf_softdevice::{raw, Softdevice};
+use nrf_softdevice::{raw, Flash, Softdevice};
 
 use crate::board::*;
+use crate::dfu;
 use crate::gatt::*;
 
 pub struct App {
@@ -12,11 +14,20 @@ pub struct App {
 impl App {
     pub fn enable(s: Spawner, device_name: &'static str) -> App {
         let config = nrf_softdevice::Config {
+            #[cfg(feature = "lfxo")]
+            clock: Some(raw::nrf_clock_lf_cfg_t {
+                source: raw::NRF_CLOCK_LF_SRC_XTAL as u8,
+                rc_ctiv: 0,
+                rc_temp_ctiv: 0,
+                accuracy: raw::NRF_CLOCK_LF_ACCURACY_20_PPM as u8,
+            }),
+            // the RC oscillator is calibrated by the softdevice, checking the temperature every second
+            #[cfg(not(feature = "lfxo"))]
             clock: Some(raw::nrf_clock_lf_cfg_t {
                 source: raw::NRF_CLOCK_LF_SRC_RC as u8,
                 rc_ctiv: 4,
                 rc_temp_ctiv: 2,
-                accuracy: 7,
+                accuracy: raw::NRF_CLOCK_LF_ACCURACY_500_PPM as u8,
             }),
             conn_gap: Some(raw::ble_gap_conn_cfg_t {
                 conn_count: 2,
@@ -43,6 +54,11 @@ impl App {
             ..Default::default()
         };
         let sd = Softdevice::enable(&config);
+        // the softdevice owns the clock now, keep the crystal oscillator running for USB
+        #[cfg(feature = "usb")]
+        unsafe {
+            raw::sd_clock_hfclk_request();
+        }
         s.spawn(softdevice_task(sd)).unwrap();
 
         Self {
@@ -51,12 +67,22 @@ impl App {
         }
     }
 
-    pub fn mount(&'static self, s: Spawner, p: &BoardActors) {
-        self.app.mount(s, self.sd, p);
+    /// Access to the flash, which is scheduled by the softdevice. This can be taken once.
+    pub fn flash(&self) -> Flash {
+        Flash::take(self.sd)
+    }
+
+    /// The softdevice, for peripherals it reserves, like the temperature sensor.
+    pub fn softdevice(&self) -> &'static Softdevice {
+        self.sd
+    }
+
+    pub fn mount(&'static self, s: Spawner, p: &BoardActors, firmware: Address<dfu::Msg>) {
+        self.app.mount(s, self.sd, p, firmware);
     }
 }
 
-#[embassy::task]
+#[embassy_executor::task]
 async fn softdevice_task(sd: &'static Softdevice) {
     sd.run().await;
 }
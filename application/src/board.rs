@@ -7,13 +7,88 @@ use embassy_nrf::{
     peripherals::PWM0,
 };
 
+#[cfg(feature = "ambient")]
+use crate::ambient;
+#[cfg(feature = "dmx")]
+use crate::dmx;
+#[cfg(feature = "thermal")]
+use crate::thermal::{self, celsius};
+#[cfg(feature = "triggers")]
+use crate::{
+    pattern::ModeDiscriminants,
+    triggers::{self, Action, Sense},
+};
 use crate::{runner, Runner, NUM_LEDS};
+#[cfg(feature = "triggers")]
+use neopixel_logic::rgb::Rgb8;
 
 //pub type UserLed = Led<Output<'static, AnyPin>>;
 pub type MyNeoPixel<const N: usize> = NeoPixelRgb<'static, PWM0, N>;
 pub type MyRunner = Runner<NUM_LEDS>;
 pub type MyControlButtons = ControlButtons<runner::Msg>;
 
+/// Model name reported by the device information service
+pub const MODEL_NUMBER: &str = "BurrBoard Neopixel";
+/// Hardware revision of the board
+pub const HARDWARE_REVISION: &str = "1.0";
+/// Manufacturer name reported by the device information service
+pub const MANUFACTURER_NAME: &str = "Drogue IoT";
+
+/// Brightness for the ambient light level (lux)
+#[cfg(feature = "ambient")]
+pub const AMBIENT_CURVE: ambient::Curve =
+    ambient::Curve::new(&[(0, 4), (10, 8), (100, 32), (1000, 255)]);
+
+/// Derating of the brightness, when getting hot
+#[cfg(feature = "thermal")]
+pub const THERMAL_LIMITS: thermal::Limits = thermal::Limits {
+    start: celsius(50),
+    full: celsius(65),
+    min_level: 64,
+    critical: celsius(75),
+    hysteresis: celsius(3),
+};
+
+/// The row of the cellular automata wraps around, instead of dead cells past the ends
+pub const AUTOMATON_WRAP: bool = true;
+/// The cellular automata are seeded randomly again, once they die out or get stuck
+pub const AUTOMATON_RESEED: bool = true;
+
+/// Pixel at twelve o'clock of the clock
+pub const CLOCK_OFFSET: usize = 0;
+/// The hands of the clock move along the strip, otherwise backwards
+pub const CLOCK_CLOCKWISE: bool = true;
+
+/// Start address and footprint of the DMX512 receiver
+#[cfg(feature = "dmx")]
+pub const DMX_PATCH: dmx::Patch = dmx::Patch {
+    start: 1,
+    footprint: dmx::Footprint::Personality,
+};
+
+/// Trigger inputs on `P0.02`, `P0.03` and `P1.01`, pulled up
+#[cfg(feature = "triggers")]
+pub const TRIGGERS: [triggers::Config; 3] = [
+    // doorbell button, pulling the input low
+    triggers::Config {
+        sense: Sense::Falling,
+        action: Action::Notify(Rgb8::new(0xFF, 0x80, 0x00)),
+        release: None,
+    },
+    // door contact, opening when the door opens
+    triggers::Config {
+        sense: Sense::High,
+        action: Action::On,
+        release: Some(Action::Off),
+    },
+    // relay output of the home automation, closing when the alarm goes off
+    triggers::Config {
+        sense: Sense::Low,
+        action: Action::Mode(ModeDiscriminants::Fire),
+        release: None,
+    },
+];
+
 pub struct BurrBoard {
     runner: ActorContext<MyRunner, 5>,
     control: ActorContext<MyControlButtons>,
@@ -33,6 +108,9 @@ pub struct BoardPeripherals {
     ),
 
     pub neopixel: MyNeoPixel<NUM_LEDS>,
+
+    /// the seed for random effects
+    pub seed: u32,
 }
 
 impl BurrBoard {
@@ -44,7 +122,7 @@ impl BurrBoard {
     }
 
     pub fn mount(&'static self, s: Spawner, p: BoardPeripherals) -> BoardActors {
-        let runner = self.runner.mount(s, Runner::new(p.neopixel));
+        let runner = self.runner.mount(s, Runner::new(p.neopixel, p.seed));
 
         let control = self
             .control
@@ -0,0 +1,293 @@
+//! The command console, on the USB serial port or the UART.
+
+use crate::{
+    pattern::ModeDiscriminants,
+    realtime, runner,
+    shell::{self, Command},
+    status, wallclock, NUM_LEDS,
+};
+use core::fmt::Write;
+use ector::Address;
+#[cfg(feature = "uart")]
+use embassy_nrf::{
+    peripherals::{TIMER1, UARTE1},
+    uarte::{self, UarteWithIdle},
+};
+use embassy_time::{Duration, Timer};
+#[cfg(feature = "usb")]
+use embassy_usb::driver::EndpointError;
+#[cfg(feature = "usb")]
+use embassy_usb_serial::CdcAcmClass;
+use neopixel_console::{
+    command, format,
+    frames::{Received, Receiver},
+    tpm2, Edit, LineEditor,
+};
+use neopixel_logic::rgb::Rgb8;
+use strum::IntoEnumIterator;
+
+#[cfg(feature = "usb")]
+use crate::usb::MyDriver;
+
+/// Size of a USB packet, and of the reads from the UART.
+const PACKET_SIZE: usize = 64;
+const PROMPT: &str = "> ";
+
+/// Time to flush the response, before resetting the device.
+const RESET_DELAY: Duration = Duration::from_millis(100);
+
+/// The serial port the console runs on.
+pub enum Port<'a> {
+    #[cfg(feature = "usb")]
+    Usb(&'a mut CdcAcmClass<'static, MyDriver>),
+    #[cfg(feature = "uart")]
+    Uart(&'a mut UarteWithIdle<'static, UARTE1, TIMER1>),
+}
+
+pub enum Error {
+    #[cfg(feature = "usb")]
+    Usb(EndpointError),
+    #[cfg(feature = "uart")]
+    Uart(uarte::Error),
+}
+
+#[cfg(feature = "usb")]
+impl From<EndpointError> for Error {
+    fn from(err: EndpointError) -> Self {
+        Error::Usb(err)
+    }
+}
+
+#[cfg(feature = "uart")]
+impl From<uarte::Error> for Error {
+    fn from(err: uarte::Error) -> Self {
+        Error::Uart(err)
+    }
+}
+
+impl<'a> Port<'a> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match self {
+            #[cfg(feature = "usb")]
+            Port::Usb(class) => Ok(class.read_packet(buf).await?),
+            #[cfg(feature = "uart")]
+            Port::Uart(uart) => Ok(uart.read_until_idle(buf).await?),
+        }
+    }
+
+    /// Write the data, which must be in RAM for the UART.
+    async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        match self {
+            #[cfg(feature = "usb")]
+            Port::Usb(class) => {
+                for chunk in data.chunks(PACKET_SIZE) {
+                    class.write_packet(chunk).await?;
+                }
+                // a full packet doesn't end the transfer, the host waits for more
+                if !data.is_empty() && data.len() % PACKET_SIZE == 0 {
+                    class.write_packet(&[]).await?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "uart")]
+            Port::Uart(uart) => {
+                if !data.is_empty() {
+                    uart.write(data).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run the console on the UART, restarting it after errors.
+#[cfg(feature = "uart")]
+#[embassy_executor::task]
+pub async fn uart_task(
+    mut uart: UarteWithIdle<'static, UARTE1, TIMER1>,
+    runner: Address<runner::Msg>,
+) {
+    loop {
+        let mut console = Console::new(&runner);
+        if console.run(&mut Port::Uart(&mut uart)).await.is_err() {
+            defmt::warn!("UART console error");
+        }
+    }
+}
+
+/// The output of the console, flushed to the host once full.
+type Output = heapless::String<512>;
+
+/// The command console on the serial port, which also accepts Adalight and TPM2 frames.
+pub struct Console<'a> {
+    runner: &'a Address<runner::Msg>,
+    receiver: Receiver<NUM_LEDS>,
+    editor: LineEditor<64>,
+    output: Output,
+    reset: bool,
+}
+
+impl<'a> Console<'a> {
+    pub fn new(runner: &'a Address<runner::Msg>) -> Self {
+        Self {
+            runner,
+            receiver: Receiver::new(),
+            editor: LineEditor::new(),
+            output: Output::new(),
+            reset: false,
+        }
+    }
+
+    pub async fn run(&mut self, port: &mut Port<'_>) -> Result<(), Error> {
+        let mut buf = [0; PACKET_SIZE];
+
+        self.print(port, |w| w.write_str(PROMPT)).await?;
+        self.flush(port).await?;
+
+        loop {
+            let n = port.read(&mut buf).await?;
+            for b in &buf[..n] {
+                self.receive(port, *b).await?;
+            }
+            self.flush(port).await?;
+        }
+    }
+
+    /// Dispatch a received byte to the frame decoders, or the console.
+    async fn receive(&mut self, port: &mut Port<'_>, b: u8) -> Result<(), Error> {
+        match self.receiver.push(b) {
+            Received::Console(b) => self.input(port, b).await,
+            Received::Consumed => Ok(()),
+            Received::Frame => {
+                let frame = self.receiver.frame().map(|[r, g, b]| Rgb8::new(r, g, b));
+                realtime::update(&frame);
+                self.runner.try_notify(runner::Msg::Frame).ok();
+                Ok(())
+            }
+            Received::Command => {
+                // the response must not be interleaved with console output, and must be in RAM
+                self.flush(port).await?;
+                let response = tpm2::RESPONSE;
+                port.write(&response).await
+            }
+            Received::Replay(replay) => {
+                for b in replay.bytes() {
+                    self.input(port, *b).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn input(&mut self, port: &mut Port<'_>, b: u8) -> Result<(), Error> {
+        match self.editor.push(b) {
+            Edit::None => {}
+            Edit::Echo(b) => {
+                self.print(port, |w| w.write_char(b as char)).await?;
+            }
+            Edit::Erase => {
+                self.print(port, |w| w.write_str("\x08 \x08")).await?;
+            }
+            Edit::Clear(n) => {
+                for _ in 0..n {
+                    self.print(port, |w| w.write_str("\x08 \x08")).await?;
+                }
+            }
+            Edit::Line => {
+                self.print(port, |w| w.write_str("\r\n")).await?;
+                self.execute(port).await?;
+                self.print(port, |w| w.write_str(PROMPT)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn execute(&mut self, port: &mut Port<'_>) -> Result<(), Error> {
+        let state = status::current();
+        let command = match Command::parse(self.editor.line()) {
+            Ok(command) => command,
+            Err(err) => {
+                return self.print(port, |w| format::write_error(w, err)).await;
+            }
+        };
+
+        if let Some(msg) = shell::to_msg(&command, &state) {
+            self.runner.try_notify(msg).ok();
+        }
+
+        match command {
+            Command::Status => {
+                #[cfg(feature = "thermal")]
+                if let Some(thermal) = crate::thermal::current() {
+                    self.print(port, |w| {
+                        format::write_thermal(w, thermal.temperature, thermal.level)
+                    })
+                    .await?;
+                }
+                let mode = shell::mode_name(state.mode);
+                self.print(port, |w| format::write_status(w, mode, &state))
+                    .await
+            }
+            Command::Modes => {
+                for mode in ModeDiscriminants::iter() {
+                    self.print(port, |w| shell::write_mode(w, mode, &state))
+                        .await?;
+                }
+                Ok(())
+            }
+            Command::Time => {
+                let time = wallclock::now();
+                self.print(port, |w| format::write_time(w, time)).await
+            }
+            Command::SetTime(time) => {
+                wallclock::set(time);
+                self.print(port, |w| w.write_str("ok\r\n")).await
+            }
+            Command::Help => {
+                self.print(port, command::write_help).await?;
+                #[cfg(feature = "ir")]
+                self.print(port, shell::write_learn_help).await?;
+                Ok(())
+            }
+            #[cfg(feature = "ir")]
+            Command::Learn(action) => {
+                crate::ir::learn(action);
+                self.print(port, |w| w.write_str("press a key on the remote\r\n"))
+                    .await
+            }
+            Command::Reset => {
+                self.reset = true;
+                self.print(port, |w| w.write_str("resetting\r\n")).await
+            }
+            _ => self.print(port, |w| w.write_str("ok\r\n")).await,
+        }
+    }
+
+    /// Append to the output. If it doesn't fit, the output is flushed first, so nothing gets
+    /// truncated as long as a single piece fits into the buffer.
+    async fn print(
+        &mut self,
+        port: &mut Port<'_>,
+        mut f: impl FnMut(&mut Output) -> core::fmt::Result,
+    ) -> Result<(), Error> {
+        let len = self.output.len();
+        if f(&mut self.output).is_err() {
+            self.output.truncate(len);
+            self.flush(port).await?;
+            f(&mut self.output).ok();
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self, port: &mut Port<'_>) -> Result<(), Error> {
+        port.write(self.output.as_bytes()).await?;
+        self.output.clear();
+
+        if self.reset {
+            Timer::after(RESET_DELAY).await;
+            cortex_m::peripheral::SCB::sys_reset();
+        }
+
+        Ok(())
+    }
+}
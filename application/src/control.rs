@@ -6,6 +6,7 @@ use embassy_time::{Duration, Timer};
 const DEBOUNCE_DELAY: Duration = Duration::from_millis(50);
 const REPEAT_DELAY: Duration = Duration::from_millis(250);
 const RESET_DELAY: Duration = Duration::from_millis(250);
+const HOLD_DELAY: Duration = Duration::from_millis(750);
 
 #[derive(Clone, Copy, Debug, defmt::Format)]
 pub enum Action {
@@ -22,6 +23,10 @@ pub enum Event {
     Increase,
     Decrease,
     Reset,
+    /// Pressed and released, without increasing or decreasing
+    Click,
+    /// Held, without increasing or decreasing, repeated until released
+    Hold,
 }
 
 #[derive(Clone, Copy, Debug, defmt::Format)]
@@ -105,7 +110,7 @@ where
             defmt::debug!("Start {}", action);
             self.send((action, Event::Start));
 
-            match action {
+            let adjusted = match action {
                 Action::A => {
                     run_action(
                         &mut self.handler,
@@ -114,7 +119,7 @@ where
                         &mut self.buttons.3,
                         [&mut self.buttons.1, &mut self.buttons.2],
                     )
-                    .await;
+                    .await
                 }
                 Action::B => {
                     run_action(
@@ -124,7 +129,7 @@ where
                         &mut self.buttons.2,
                         [&mut self.buttons.3, &mut self.buttons.0],
                     )
-                    .await;
+                    .await
                 }
                 Action::C => {
                     run_action(
@@ -134,7 +139,7 @@ where
                         &mut self.buttons.1,
                         [&mut self.buttons.3, &mut self.buttons.0],
                     )
-                    .await;
+                    .await
                 }
                 Action::D => {
                     run_action(
@@ -144,12 +149,15 @@ where
                         &mut self.buttons.0,
                         [&mut self.buttons.1, &mut self.buttons.2],
                     )
-                    .await;
+                    .await
                 }
-            }
+            };
 
             defmt::debug!("Stop {}", action);
             self.send((action, Event::Stop));
+            if !adjusted {
+                self.send((action, Event::Click));
+            }
         }
     }
 }
@@ -194,36 +202,53 @@ async fn run_action<H>(
     activator: &mut Input<'static, AnyPin>,
     increment: &mut Input<'static, AnyPin>,
     decrement: [&mut Input<'static, AnyPin>; 2],
-) where
+) -> bool
+where
     H: TryFrom<ControlEvent> + 'static,
 {
     let [d1, d2] = decrement;
+    let mut adjusted = false;
+    // the time until the next hold event, none after increasing or decreasing
+    let mut hold = Some(HOLD_DELAY);
 
     loop {
-        match select4(
+        let buttons = select4(
             stopped(activator),
             pushed(increment),
             pushed(d1),
             pushed(d2),
-        )
-        .await
-        {
-            Either4::First(_) => {
+        );
+        let held = async move {
+            match hold {
+                Some(delay) => Timer::after(delay).await,
+                None => core::future::pending().await,
+            }
+        };
+
+        let event = match select(buttons, held).await {
+            Either::First(Either4::First(_)) => {
                 // Stopped
-                return;
+                return adjusted;
             }
-            Either4::Second(_) => {
+            Either::First(Either4::Second(_)) => {
                 // Increment
-                if let Ok(event) = H::try_from(ControlEvent::from((action, Event::Increase))) {
-                    address.try_notify(event).ok();
-                }
+                hold = None;
+                Event::Increase
             }
-            Either4::Third(_) | Either4::Fourth(_) => {
+            Either::First(Either4::Third(_) | Either4::Fourth(_)) => {
                 // Decrement
-                if let Ok(event) = H::try_from(ControlEvent::from((action, Event::Decrease))) {
-                    address.try_notify(event).ok();
-                }
+                hold = None;
+                Event::Decrease
             }
+            Either::Second(_) => {
+                hold = Some(REPEAT_DELAY);
+                Event::Hold
+            }
+        };
+
+        adjusted = true;
+        if let Ok(event) = H::try_from(ControlEvent::from((action, event))) {
+            address.try_notify(event).ok();
         }
     }
 }
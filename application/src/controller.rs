@@ -1,39 +1,109 @@
-use crate::pattern::{Context, Mode, ModeDiscriminants};
+use crate::easing::Easing;
+use crate::flash::{Flashes, Trigger};
+use crate::pattern::{palettes::Palette, Context, Glitter, IntoPixel, Mode, ModeDiscriminants};
+use crate::rng::Rng;
+use crate::wallclock;
 use crate::MyNeoPixel;
-use drogue_device::drivers::led::neopixel::{
-    filter::Brightness,
-    rgb::{Rgb8, BLACK},
-};
+use drogue_device::drivers::led::neopixel::{filter::Brightness, rgb as neopixel_rgb};
 use embassy_time::{Duration, Instant};
+use neopixel_logic::occupancy::{self, Occupancy};
+use neopixel_logic::rgb::{Rgb8, BLACK};
+use neopixel_protocol::{Color, LightState};
 use num::{cast, traits::Float, NumCast};
+use palette::Hsv;
+use strum::EnumCount;
 
 pub struct Controller<const N: usize> {
     pixels: [Rgb8; N],
     mode: Mode<N>,
+    /// the mode to return to, when switching on again
+    resume: ModeDiscriminants,
+    /// the selected colors, kept in the form they were set in
+    colors: [Color; 2],
+    /// the palettes selected for the modes, `None` for the default of the mode
+    palettes: [Option<Palette>; ModeDiscriminants::COUNT],
+    easing: Easing,
     sleep: Option<Sleep<u8>>,
+    /// the manual brightness
     brightness: u8,
+    /// the brightness following the ambient light, if available
+    ambient: Option<u8>,
+    /// use the ambient brightness, with manual adjustments as an offset
+    auto_brightness: bool,
+    offset: i16,
     speed: u8,
     last_run: Instant,
+    flashes: Flashes,
+    /// glitter on top of the mode, if enabled
+    glitter: Option<Glitter<N, GLITTER_DENSITY, GLITTER_FADE_MS>>,
+    occupancy: Occupancy,
+    fade: Option<Fade>,
+    /// the level the brightness is derated to, when getting hot
+    derating: u8,
+    tilt: i8,
+    rng: Rng,
+}
+
+/// Fading the lights in or out, when switched by the occupancy.
+enum Fade {
+    In(Sleep<u8>),
+    Out(Sleep<u8>),
 }
 
 const INITIAL_BRIGHTNESS: u8 = 16;
 pub const DEFAULT_SPEED: u8 = u8::MAX / 2;
+const FADE_IN: Duration = Duration::from_secs(1);
+const FADE_OUT: Duration = Duration::from_secs(10);
+/// Number of flashes, when sparkling
+const SPARKLES: usize = 8;
+/// Sparkles of the glitter, per second
+const GLITTER_DENSITY: u8 = 20;
+const GLITTER_FADE_MS: u16 = 150;
+const DEFAULT_COLORS: [Color; 2] = [
+    Color::WHITE,
+    Color::Hsv {
+        hue: 240,
+        saturation: u8::MAX,
+        value: u8::MAX,
+    },
+];
+/// Step of the hue, in degrees, when picking a color
+const HUE_STEP: u16 = 30;
+/// Step of the saturation, when picking a color
+const SATURATION_STEP: u8 = 16;
 
 impl<const N: usize> Controller<N> {
-    pub fn new() -> Self {
+    pub fn new(seed: u32) -> Self {
         let mut result = Self {
             mode: Mode::Off,
+            resume: ModeDiscriminants::Off.next(),
+            colors: DEFAULT_COLORS,
+            palettes: [None; ModeDiscriminants::COUNT],
+            easing: Easing::default(),
             pixels: [BLACK; N],
             sleep: None,
             brightness: INITIAL_BRIGHTNESS,
+            ambient: None,
+            auto_brightness: true,
+            offset: 0,
             speed: DEFAULT_SPEED,
             last_run: Instant::now(),
+            flashes: Flashes::new(),
+            glitter: None,
+            occupancy: Occupancy::new(occupancy::DEFAULT_TIMEOUT),
+            fade: None,
+            derating: u8::MAX,
+            tilt: 0,
+            rng: Rng::new(seed),
         };
         result.next();
         result
     }
 
     pub fn mode(&mut self, mode: ModeDiscriminants) {
+        if mode != ModeDiscriminants::Off {
+            self.resume = mode;
+        }
         self.mode = mode.new(&mut self.pixels);
         defmt::info!("Mode: {}", Into::<&'static str>::into(&self.mode))
     }
@@ -46,24 +116,245 @@ impl<const N: usize> Controller<N> {
         self.mode(ModeDiscriminants::from(&self.mode).prev());
     }
 
-    pub async fn tick(&mut self, neopixel: &mut MyNeoPixel<N>) {
-        let mut f = if let Some(sleep) = &self.sleep {
-            Brightness(sleep.remaining_now())
+    /// Switch off, keeping the mode to continue with.
+    pub fn off(&mut self) {
+        self.mode(ModeDiscriminants::Off);
+    }
+
+    /// Switch on again, continuing with the last mode.
+    pub fn on(&mut self) {
+        if self.is_off() {
+            self.mode(self.resume);
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if self.is_off() {
+            self.on();
+        } else {
+            self.off();
+        }
+    }
+
+    fn is_off(&self) -> bool {
+        matches!(self.mode, Mode::Off)
+    }
+
+    /// The selected colors, to keep them in storage.
+    #[cfg(feature = "dfu")]
+    pub fn colors(&self) -> [Color; 2] {
+        self.colors
+    }
+
+    /// Restore the selected colors from storage, without changing the mode.
+    #[cfg(feature = "dfu")]
+    pub fn restore_colors(&mut self, colors: [Color; 2]) {
+        self.colors = colors;
+    }
+
+    /// Select the color, showing it if the mode doesn't use it.
+    pub fn color(&mut self, color: Color) {
+        self.colors[0] = color;
+        if !ModeDiscriminants::from(&self.mode).uses_color() {
+            self.mode(ModeDiscriminants::Solid);
+        }
+    }
+
+    /// Select the second color, showing the gradient.
+    pub fn second_color(&mut self, color: Color) {
+        self.colors[1] = color;
+        if !matches!(self.mode, Mode::Gradient(_)) {
+            self.mode(ModeDiscriminants::Gradient);
+        }
+    }
+
+    /// Turn the hue of the color being picked: the second color of the gradient, or the color.
+    pub fn next_hue(&mut self) {
+        self.pick(next_hue);
+    }
+
+    /// Lower the saturation of the color being picked.
+    pub fn next_saturation(&mut self) {
+        self.pick(next_saturation);
+    }
+
+    fn pick(&mut self, f: fn(Color) -> Color) {
+        let (hue, saturation, value) = match self.mode {
+            Mode::Gradient(_) => {
+                self.second_color(f(self.colors[1]));
+                self.colors[1].hsv()
+            }
+            _ => {
+                self.color(f(self.colors[0]));
+                self.colors[0].hsv()
+            }
+        };
+        defmt::info!("Color: hsv {},{},{}", hue, saturation, value);
+    }
+
+    /// The palette of the current mode.
+    pub fn palette(&self) -> Palette {
+        let mode = ModeDiscriminants::from(&self.mode);
+        self.palettes[mode as usize].unwrap_or_else(|| mode.palette())
+    }
+
+    /// Select the palette for the current mode.
+    pub fn set_palette(&mut self, palette: Palette) {
+        let mode = ModeDiscriminants::from(&self.mode);
+        self.palettes[mode as usize] = Some(palette);
+        defmt::info!("Palette: {}", Into::<&'static str>::into(palette));
+    }
+
+    /// Cycle through the palettes, for the current mode.
+    pub fn next_palette(&mut self) {
+        self.set_palette(self.palette().next());
+    }
+
+    /// Select the easing curve, of the modes using one.
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = easing;
+        defmt::info!("Easing: {}", Into::<&'static str>::into(easing));
+    }
+
+    /// Motion was detected.
+    pub fn motion(&mut self) {
+        let action = self.occupancy.motion(Instant::now());
+        self.occupancy_action(action);
+    }
+
+    /// The lights were controlled manually, overriding the motion control.
+    pub fn manual(&mut self) {
+        self.occupancy.manual(Instant::now());
+        self.fade = None;
+    }
+
+    /// Change the time without motion, after which the lights are switched off.
+    pub fn motion_timeout(&mut self, timeout: Option<Duration>) {
+        self.occupancy.set_timeout(timeout);
+    }
+
+    fn occupancy_action(&mut self, action: Option<occupancy::Action>) {
+        match action {
+            Some(occupancy::Action::FadeIn) => {
+                if self.is_off() || matches!(self.fade, Some(Fade::Out(_))) {
+                    defmt::info!("Motion, switching on");
+                    self.on();
+                    self.fade = Some(Fade::In(Sleep::now(0, u8::MAX, FADE_IN)));
+                }
+            }
+            Some(occupancy::Action::FadeOut) => {
+                if !self.is_off() {
+                    defmt::info!("No motion, switching off");
+                    self.fade = Some(Fade::Out(Sleep::now(0, u8::MAX, FADE_OUT)));
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn update_fade(&mut self) {
+        match &self.fade {
+            Some(Fade::In(fade)) if fade.remaining_ms() <= 0.0 => {
+                self.fade = None;
+            }
+            Some(Fade::Out(fade)) if fade.remaining_ms() <= 0.0 => {
+                self.off();
+                self.fade = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn brightness_filter(&self) -> Brightness {
+        let brightness = if let Some(sleep) = &self.sleep {
+            sleep.remaining_now()
         } else {
-            Brightness(self.brightness)
+            self.brightness()
+        };
+
+        let fade = match &self.fade {
+            Some(Fade::In(fade)) => fade.elapsed_now(),
+            Some(Fade::Out(fade)) => fade.remaining_now(),
+            None => u8::MAX,
         };
 
+        Brightness(scale(scale(brightness, fade), self.derating))
+    }
+
+    pub async fn tick(&mut self, neopixel: &mut MyNeoPixel<N>) {
         let now = Instant::now();
+        let action = self.occupancy.poll(now);
+        self.occupancy_action(action);
+        self.update_fade();
+
+        let mut f = self.brightness_filter();
+
         let delta = now - self.last_run;
         self.last_run = now;
         let ctx = Context {
             speed: self.speed,
             delta,
+            color: self.colors[0].into_pixel(),
+            second_color: self.colors[1].into_pixel(),
+            tilt: self.tilt,
+            palette: self.palette(),
+            easing: self.easing,
+            rng: self.rng.fork(),
+            time_of_day: wallclock::millis(),
         };
 
-        self.mode
-            .tick(&mut self.pixels, neopixel, ctx, &mut f)
-            .await;
+        self.flashes.advance(delta);
+        self.mode.tick(&mut self.pixels, ctx);
+
+        // the pixels of the previous mode are still around, when off
+        let mut base = [BLACK; N];
+        if !self.is_off() {
+            base = self.pixels;
+            if let Some(glitter) = &mut self.glitter {
+                glitter.advance(ctx.time_step(), &mut self.rng);
+                glitter.render(&mut base);
+            }
+        }
+
+        let mut frame = [BLACK; N];
+        self.flashes.render(&base, &mut frame);
+        write(neopixel, &frame, &mut f).await;
+    }
+
+    /// Flash a color, on top of the current mode.
+    pub fn flash(&mut self, trigger: Trigger) {
+        self.flashes.trigger(trigger);
+    }
+
+    /// Flash random pixels in random colors, on top of the current mode.
+    pub fn sparkle(&mut self) {
+        for _ in 0..SPARKLES {
+            let hue = self.rng.below(360) as f32;
+            self.flashes.trigger(Trigger {
+                position: self.rng.next_u8(),
+                color: Hsv::new(hue, 1.0, 1.0).into_pixel(),
+            });
+        }
+    }
+
+    /// Sparkle on top of the current mode, or stop.
+    pub fn glitter(&mut self, enabled: bool) {
+        self.glitter = enabled.then(Glitter::new);
+    }
+
+    pub fn tilt(&mut self, tilt: i8) {
+        self.tilt = tilt;
+    }
+
+    /// Blink the whole strip, on top of the current mode.
+    pub fn notify(&mut self, color: Rgb8) {
+        self.flashes.notify(color);
+    }
+
+    /// Render a frame from an external source, instead of the mode.
+    pub async fn render(&mut self, neopixel: &mut MyNeoPixel<N>, frame: &[Rgb8; N]) {
+        let mut f = self.brightness_filter();
+        write(neopixel, frame, &mut f).await;
     }
 
     pub fn start_sleep(&mut self, duration: Duration) {
@@ -78,23 +369,69 @@ impl<const N: usize> Controller<N> {
         self.sleep.as_ref().map(|s| s.remaining_ms())
     }
 
+    /// The effective brightness, following the ambient light if enabled.
+    pub fn brightness(&self) -> u8 {
+        match self.auto() {
+            Some(ambient) => (ambient as i16 + self.offset).clamp(0, u8::MAX as i16) as u8,
+            None => self.brightness,
+        }
+    }
+
+    fn auto(&self) -> Option<u8> {
+        self.ambient.filter(|_| self.auto_brightness)
+    }
+
     pub fn lighter(&mut self) {
-        if self.brightness < u8::MAX {
-            self.brightness += 1;
+        if self.brightness() < u8::MAX {
+            match self.auto() {
+                Some(_) => self.offset += 1,
+                None => self.brightness += 1,
+            }
         }
-        defmt::info!("Brightness: {}", self.brightness);
+        defmt::info!("Brightness: {}", self.brightness());
     }
 
     pub fn darker(&mut self) {
-        if self.brightness > u8::MIN {
-            self.brightness -= 1;
+        if self.brightness() > u8::MIN {
+            match self.auto() {
+                Some(_) => self.offset -= 1,
+                None => self.brightness -= 1,
+            }
         }
-        defmt::info!("Brightness: {}", self.brightness);
+        defmt::info!("Brightness: {}", self.brightness());
     }
 
     pub fn reset_brightness(&mut self) {
-        self.brightness = INITIAL_BRIGHTNESS;
-        defmt::info!("Brightness: {}", self.brightness);
+        match self.auto() {
+            Some(_) => self.offset = 0,
+            None => self.brightness = INITIAL_BRIGHTNESS,
+        }
+        defmt::info!("Brightness: {}", self.brightness());
+    }
+
+    pub fn set_brightness(&mut self, brightness: u8) {
+        match self.auto() {
+            Some(ambient) => self.offset = brightness as i16 - ambient as i16,
+            None => self.brightness = brightness,
+        }
+        defmt::info!("Brightness: {}", self.brightness());
+    }
+
+    /// Derate the brightness, due to the temperature.
+    pub fn thermal(&mut self, level: u8) {
+        self.derating = level;
+    }
+
+    /// Update the brightness following the ambient light.
+    pub fn ambient(&mut self, brightness: u8) {
+        self.ambient = Some(brightness);
+    }
+
+    /// Enable or disable following the ambient light, dropping manual adjustments.
+    pub fn auto_brightness(&mut self, enabled: bool) {
+        self.auto_brightness = enabled;
+        self.offset = 0;
+        defmt::info!("Auto brightness: {}", enabled);
     }
 
     pub fn faster(&mut self) {
@@ -108,6 +445,65 @@ impl<const N: usize> Controller<N> {
     pub fn reset_speed(&mut self) {
         self.speed = DEFAULT_SPEED;
     }
+
+    pub fn set_speed(&mut self, speed: u8) {
+        self.speed = speed;
+    }
+
+    /// Get the current state, as reported to others.
+    pub fn state(&self) -> LightState {
+        let mode = ModeDiscriminants::from(&self.mode);
+        LightState {
+            mode: mode.id() as u8,
+            brightness: self.brightness(),
+            speed: self.speed,
+            on: mode != ModeDiscriminants::Off,
+            sleeping: self.sleep.is_some(),
+        }
+    }
+}
+
+/// Turn the hue, starting with a saturated color if there is no hue.
+fn next_hue(color: Color) -> Color {
+    let (hue, saturation, value) = color.hsv();
+    let (hue, saturation) = match saturation {
+        0 => (hue, u8::MAX),
+        _ => ((hue + HUE_STEP) % 360, saturation),
+    };
+    Color::Hsv {
+        hue,
+        saturation,
+        value,
+    }
+}
+
+/// Lower the saturation, wrapping around to full saturation after white.
+fn next_saturation(color: Color) -> Color {
+    let (hue, saturation, value) = color.hsv();
+    let saturation = match saturation {
+        0 => u8::MAX,
+        saturation => saturation.saturating_sub(SATURATION_STEP),
+    };
+    Color::Hsv {
+        hue,
+        saturation,
+        value,
+    }
+}
+
+/// Scale a value by a level, with `255` keeping the value.
+fn scale(value: u8, level: u8) -> u8 {
+    (value as u16 * level as u16 / u8::MAX as u16) as u8
+}
+
+/// Write the pixels to the strip, in the colors of the driver.
+async fn write<const N: usize>(
+    neopixel: &mut MyNeoPixel<N>,
+    pixels: &[Rgb8; N],
+    filter: &mut Brightness,
+) {
+    let frame = pixels.map(|p| neopixel_rgb::Rgb8::new(p.r, p.g, p.b));
+    neopixel.set_with_filter(&frame, filter).await.ok();
 }
 
 pub struct Sleep<T>
@@ -156,6 +552,17 @@ where
         self.remaining(Instant::now())
     }
 
+    /// The opposite of [`Self::remaining`], rising from `min` to `max`.
+    pub fn elapsed(&self, now: Instant) -> T {
+        let value = |v: T| v.to_f64().unwrap_or_default();
+        let elapsed = value(self.max) + value(self.min) - value(self.remaining(now));
+        cast(elapsed).unwrap_or(self.max)
+    }
+
+    pub fn elapsed_now(&self) -> T {
+        self.elapsed(Instant::now())
+    }
+
     /// Get the remaining time in ms
     pub fn remaining_ms(&self) -> f64 {
         let now = Instant::now();
@@ -189,4 +596,27 @@ mod test {
         assert_eq!(sleep.remaining(Instant::from_secs(300)), 0);
         assert_eq!(sleep.remaining(Instant::from_secs(350)), 0);
     }
+
+    #[test]
+    fn pick_color() {
+        let hsv = |hue, saturation, value| Color::Hsv {
+            hue,
+            saturation,
+            value,
+        };
+
+        // starting with red, from white
+        assert_eq!(next_hue(Color::WHITE), hsv(0, 255, 255));
+        assert_eq!(next_hue(hsv(0, 255, 255)), hsv(30, 255, 255));
+        assert_eq!(next_hue(hsv(330, 128, 64)), hsv(0, 128, 64));
+
+        // down to white, keeping the hue
+        let mut color = hsv(90, 255, 255);
+        for _ in 0..16 {
+            color = next_saturation(color);
+        }
+        assert_eq!(color, hsv(90, 0, 255));
+        assert_eq!(next_saturation(color), hsv(90, 255, 255));
+        assert_eq!(next_hue(color), hsv(90, 255, 255));
+    }
 }
@@ -0,0 +1,458 @@
+use core::future::Future;
+use core::sync::atomic::{AtomicU32, Ordering};
+use ector::{Actor, Address, Inbox};
+use embassy_boot_nrf::{FirmwareUpdater, State};
+use embassy_nrf::nvmc::Nvmc;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex, signal::Signal};
+use embassy_time::{Duration, Ticker, Timer};
+use futures::StreamExt;
+use heapless::Vec;
+
+pub use neopixel_logic::dfu::*;
+
+/// Time between checks of the running firmware.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time between reporting the completed update and resetting the device.
+const RESET_DELAY: Duration = Duration::from_secs(1);
+
+/// Latest status of the firmware update, picked up by the transport to report back.
+///
+/// Only the latest status matters, a newer one replaces a status not picked up yet.
+pub static STATUS: Signal<CriticalSectionRawMutex, Status> = Signal::new();
+
+/// Number of times the lights were updated, showing the firmware is running.
+static HEARTBEATS: AtomicU32 = AtomicU32::new(0);
+
+/// Report the lights were updated, for the health check of the running firmware.
+pub fn heartbeat() {
+    HEARTBEATS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The maximum size of a firmware image, which is the size of the active partition.
+///
+/// This is taken from the linker script (see `memory-dfu.x`), so it follows the memory layout.
+pub fn max_firmware_size() -> u32 {
+    extern "C" {
+        static __bootloader_active_start: u32;
+        static __bootloader_active_end: u32;
+    }
+    unsafe {
+        let start = &__bootloader_active_start as *const u32 as u32;
+        let end = &__bootloader_active_end as *const u32 as u32;
+        end - start
+    }
+}
+
+/// The flash holding the DFU partition.
+///
+/// While the softdevice is enabled, it owns the flash, so writes must be scheduled through it.
+pub enum Partition {
+    Nvmc(Nvmc<'static>),
+    #[cfg(feature = "ble")]
+    Softdevice(nrf_softdevice::Flash),
+}
+
+/// The flash, shared by the firmware updates and the settings in storage.
+pub type SharedPartition = Mutex<CriticalSectionRawMutex, Partition>;
+
+#[derive(Debug)]
+pub enum PartitionError {
+    Nvmc(embassy_nrf::nvmc::Error),
+    #[cfg(feature = "ble")]
+    Softdevice(nrf_softdevice::FlashError),
+}
+
+/// The DFU partition, written through the embassy-boot firmware updater.
+pub struct NrfDfuFlash {
+    updater: FirmwareUpdater,
+    partition: &'static SharedPartition,
+}
+
+impl NrfDfuFlash {
+    pub fn new(partition: &'static SharedPartition) -> Self {
+        Self {
+            updater: FirmwareUpdater::default(),
+            partition,
+        }
+    }
+
+    /// Confirm the running firmware, in case it was just swapped in.
+    pub async fn mark_booted(&mut self) -> Result<(), PartitionError> {
+        let mut magic = [0; 4];
+        let updater = &mut self.updater;
+        match &mut *self.partition.lock().await {
+            Partition::Nvmc(flash) => {
+                let state = updater.get_state_blocking(flash, &mut magic);
+                if let State::Swap = state.map_err(PartitionError::Nvmc)? {
+                    defmt::info!("Confirming updated firmware");
+                    updater
+                        .mark_booted_blocking(flash, &mut magic)
+                        .map_err(PartitionError::Nvmc)?;
+                }
+            }
+            #[cfg(feature = "ble")]
+            Partition::Softdevice(flash) => {
+                let state = updater.get_state(flash, &mut magic).await;
+                if let State::Swap = state.map_err(PartitionError::Softdevice)? {
+                    defmt::info!("Confirming updated firmware");
+                    updater
+                        .mark_booted(flash, &mut magic)
+                        .await
+                        .map_err(PartitionError::Softdevice)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DfuFlash for NrfDfuFlash {
+    type Error = PartitionError;
+
+    type WritePageFuture<'m> = impl Future<Output = Result<(), Self::Error>> + 'm
+    where
+        Self: 'm;
+
+    fn write_page<'m>(
+        &'m mut self,
+        offset: usize,
+        page: &'m [u8; PAGE_SIZE],
+    ) -> Self::WritePageFuture<'m> {
+        async move {
+            let updater = &mut self.updater;
+            match &mut *self.partition.lock().await {
+                Partition::Nvmc(flash) => updater
+                    .write_firmware_blocking(offset, page, flash, PAGE_SIZE)
+                    .map_err(PartitionError::Nvmc),
+                #[cfg(feature = "ble")]
+                Partition::Softdevice(flash) => updater
+                    .write_firmware(offset, page, flash, PAGE_SIZE)
+                    .await
+                    .map_err(PartitionError::Softdevice),
+            }
+        }
+    }
+
+    type MarkUpdatedFuture<'m> = impl Future<Output = Result<(), Self::Error>> + 'm
+    where
+        Self: 'm;
+
+    fn mark_updated(&mut self) -> Self::MarkUpdatedFuture<'_> {
+        async move {
+            let mut magic = [0; 4];
+            let updater = &mut self.updater;
+            match &mut *self.partition.lock().await {
+                Partition::Nvmc(flash) => updater
+                    .mark_updated_blocking(flash, &mut magic)
+                    .map_err(PartitionError::Nvmc),
+                #[cfg(feature = "ble")]
+                Partition::Softdevice(flash) => updater
+                    .mark_updated(flash, &mut magic)
+                    .await
+                    .map_err(PartitionError::Softdevice),
+            }
+        }
+    }
+}
+
+pub enum Msg {
+    Control(Vec<u8, CONTROL_LEN>),
+    Chunk(Vec<u8, CHUNK_LEN>),
+}
+
+/// Confirms the running firmware, and receives new firmware images.
+pub struct FirmwareManager {
+    flash: NrfDfuFlash,
+    receiver: DfuReceiver,
+}
+
+impl FirmwareManager {
+    pub fn new(partition: &'static SharedPartition) -> Self {
+        Self {
+            flash: NrfDfuFlash::new(partition),
+            receiver: DfuReceiver::new(max_firmware_size()),
+        }
+    }
+}
+
+#[ector::actor]
+impl Actor for FirmwareManager {
+    type Message<'m> = Msg;
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, mut inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        // only accept updates once the running firmware is confirmed
+        let mut health = HealthCheck::new();
+        let mut ticker = Ticker::every(HEALTH_CHECK_INTERVAL);
+        while !health.check(HEARTBEATS.load(Ordering::Relaxed)) {
+            ticker.next().await;
+        }
+        if let Err(err) = self.flash.mark_booted().await {
+            defmt::warn!("Failed to confirm firmware: {}", defmt::Debug2Format(&err));
+        }
+
+        loop {
+            let status = match inbox.next().await {
+                Msg::Control(data) => match Command::parse(&data) {
+                    Ok(command) => {
+                        defmt::info!("DFU command: {}", defmt::Debug2Format(&command));
+                        self.receiver.command(&mut self.flash, command).await
+                    }
+                    Err(err) => Status::Failed(err),
+                },
+                Msg::Chunk(data) => self.receiver.chunk(&mut self.flash, &data).await,
+            };
+
+            STATUS.signal(status);
+
+            if let Status::Complete = status {
+                defmt::info!("Firmware update complete, resetting");
+                Timer::after(RESET_DELAY).await;
+                cortex_m::peripheral::SCB::sys_reset();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    const MAX_SIZE: u32 = 3 * PAGE_SIZE as u32;
+
+    /// Run a future, which completes right away with the memory flash.
+    fn run<F: Future>(future: F) -> F::Output {
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+        const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+        let waker = unsafe { Waker::from_raw(RAW) };
+        futures::pin_mut!(future);
+        match future.poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("blocked on the memory flash"),
+        }
+    }
+
+    struct MemoryFlash {
+        data: [u8; 3 * PAGE_SIZE],
+        writes: usize,
+        updated: bool,
+    }
+
+    impl MemoryFlash {
+        fn new() -> Self {
+            Self {
+                data: [0; 3 * PAGE_SIZE],
+                writes: 0,
+                updated: false,
+            }
+        }
+    }
+
+    impl DfuFlash for MemoryFlash {
+        type Error = ();
+
+        type WritePageFuture<'m> = core::future::Ready<Result<(), ()>>;
+
+        fn write_page<'m>(
+            &'m mut self,
+            offset: usize,
+            page: &'m [u8; PAGE_SIZE],
+        ) -> Self::WritePageFuture<'m> {
+            assert_eq!(offset % PAGE_SIZE, 0);
+            self.data[offset..offset + PAGE_SIZE].copy_from_slice(page);
+            self.writes += 1;
+            core::future::ready(Ok(()))
+        }
+
+        type MarkUpdatedFuture<'m> = core::future::Ready<Result<(), ()>>;
+
+        fn mark_updated(&mut self) -> Self::MarkUpdatedFuture<'_> {
+            self.updated = true;
+            core::future::ready(Ok(()))
+        }
+    }
+
+    fn image() -> [u8; 5000] {
+        let mut image = [0u8; 5000];
+        for (i, b) in image.iter_mut().enumerate() {
+            *b = (i * 7 % 251) as u8;
+        }
+        image
+    }
+
+    fn crc(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.finish()
+    }
+
+    fn chunk(offset: usize, data: &[u8]) -> Vec<u8, CHUNK_LEN> {
+        let mut result = Vec::new();
+        result
+            .extend_from_slice(&(offset as u32).to_le_bytes())
+            .unwrap();
+        result.extend_from_slice(data).unwrap();
+        result
+    }
+
+    fn start(image: &[u8]) -> Command {
+        Command::Start {
+            size: image.len() as u32,
+            crc: crc(image),
+        }
+    }
+
+    #[test]
+    fn crc32() {
+        assert_eq!(crc(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc(b""), 0);
+    }
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(
+            Command::parse(&[0x01, 0x10, 0x00, 0x00, 0x00, 0x04, 0x03, 0x02, 0x01]),
+            Ok(Command::Start {
+                size: 16,
+                crc: 0x01020304
+            })
+        );
+        assert_eq!(Command::parse(&[0x02]), Ok(Command::Finish));
+        assert_eq!(Command::parse(&[0x03]), Ok(Command::Abort));
+        assert_eq!(Command::parse(&[0x01, 0x10]), Err(Error::InvalidCommand));
+        assert_eq!(Command::parse(&[]), Err(Error::InvalidCommand));
+    }
+
+    #[test]
+    fn update() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        assert_eq!(
+            run(receiver.command(&mut flash, start(&image))),
+            Status::Receiving(0)
+        );
+
+        for (i, data) in image.chunks(128).enumerate() {
+            let status = run(receiver.chunk(&mut flash, &chunk(i * 128, data)));
+            assert_eq!(status, Status::Receiving((i * 128 + data.len()) as u32));
+        }
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Complete
+        );
+        assert_eq!(flash.writes, 2);
+        assert!(flash.updated);
+        assert_eq!(&flash.data[..image.len()], &image[..]);
+        // remainder of the last page is padded
+        assert!(flash.data[image.len()..2 * PAGE_SIZE]
+            .iter()
+            .all(|b| *b == 0xFF));
+    }
+
+    #[test]
+    fn resend_after_unexpected_offset() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        run(receiver.command(&mut flash, start(&image)));
+        run(receiver.chunk(&mut flash, &chunk(0, &image[..128])));
+
+        // a lost chunk is reported, and the update continues with the expected offset
+        assert_eq!(
+            run(receiver.chunk(&mut flash, &chunk(256, &image[256..384]))),
+            Status::Receiving(128)
+        );
+        for (i, data) in image[128..].chunks(128).enumerate() {
+            run(receiver.chunk(&mut flash, &chunk(128 + i * 128, data)));
+        }
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Complete
+        );
+        assert_eq!(&flash.data[..image.len()], &image[..]);
+    }
+
+    #[test]
+    fn reject_checksum_mismatch() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        run(receiver.command(
+            &mut flash,
+            Command::Start {
+                size: image.len() as u32,
+                crc: crc(&image) ^ 1,
+            },
+        ));
+        for (i, data) in image.chunks(128).enumerate() {
+            run(receiver.chunk(&mut flash, &chunk(i * 128, data)));
+        }
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Failed(Error::ChecksumMismatch)
+        );
+        assert!(!flash.updated);
+    }
+
+    #[test]
+    fn reject_incomplete() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        run(receiver.command(&mut flash, start(&image)));
+        run(receiver.chunk(&mut flash, &chunk(0, &image[..128])));
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Failed(Error::UnexpectedOffset)
+        );
+        assert!(!flash.updated);
+    }
+
+    #[test]
+    fn reject_not_started() {
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        assert_eq!(
+            run(receiver.chunk(&mut flash, &chunk(0, &[1, 2, 3]))),
+            Status::Failed(Error::NotStarted)
+        );
+        assert_eq!(
+            run(receiver.command(
+                &mut flash,
+                Command::Start {
+                    size: MAX_SIZE + 1,
+                    crc: 0
+                }
+            )),
+            Status::Failed(Error::TooLarge)
+        );
+    }
+
+    #[test]
+    fn health_check() {
+        let mut health = HealthCheck::new();
+        for beats in 1..HEALTH_CHECKS {
+            assert!(!health.check(beats));
+        }
+        // stalled, starting over
+        assert!(!health.check(HEALTH_CHECKS - 1));
+        for beats in HEALTH_CHECKS..2 * HEALTH_CHECKS - 1 {
+            assert!(!health.check(beats));
+        }
+        assert!(health.check(2 * HEALTH_CHECKS - 1));
+    }
+}
@@ -0,0 +1,457 @@
+//! DMX512 receiver, and the mapping of the universe onto the controller.
+
+use crate::{pattern::ModeDiscriminants, realtime, runner::Msg};
+use ector::{Actor, Address, Inbox};
+use embassy_nrf::{
+    pac,
+    peripherals::{PPI_CH4, TIMER0, UARTE0},
+    ppi::{Event, Ppi, Task},
+    uarte::UarteWithIdle,
+};
+use heapless::Vec;
+use neopixel_logic::rgb::{Rgb8, BLACK};
+
+/// Number of slots in a universe.
+pub const SLOTS: usize = 512;
+
+/// Start code of packets carrying dimmer data. Other packets (like RDM) are ignored.
+const START_CODE_DIMMER: u8 = 0x00;
+
+/// Values of the mode channel selecting the same mode, from its identifier times the range.
+pub const MODE_RANGE: u8 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Waiting for the first break
+    Break,
+    StartCode,
+    Slots,
+    /// Ignore the packet, until the next break
+    Ignore,
+}
+
+/// Parses the DMX512 byte stream into universes.
+///
+/// The break, which separates packets, is detected by the UART and signaled using
+/// [`Parser::on_break`]. Packets may span several reads, the parser keeps its state in between.
+pub struct Parser {
+    state: State,
+    slots: [u8; SLOTS],
+    len: usize,
+}
+
+impl Parser {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Break,
+            slots: [0; SLOTS],
+            len: 0,
+        }
+    }
+
+    /// A break was detected. Returns `true` if this completed a universe.
+    pub fn on_break(&mut self) -> bool {
+        let complete = self.state == State::Slots && self.len > 0;
+        // a full universe completed before the break, and is kept until the next packet
+        let full = self.state == State::Ignore && self.len == SLOTS;
+        self.state = State::StartCode;
+        if !complete && !full {
+            self.len = 0;
+        }
+        complete
+    }
+
+    /// Push a received byte. Returns `true` if this completed a universe.
+    pub fn push(&mut self, b: u8) -> bool {
+        match self.state {
+            State::Break | State::Ignore => false,
+            State::StartCode => {
+                self.len = 0;
+                self.state = if b == START_CODE_DIMMER {
+                    State::Slots
+                } else {
+                    State::Ignore
+                };
+                false
+            }
+            State::Slots => {
+                self.slots[self.len] = b;
+                self.len += 1;
+                if self.len == SLOTS {
+                    // nothing more to receive, until the next break
+                    self.state = State::Ignore;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Push the data of a read. A read with a framing error ended with the break, received as
+    /// zero byte. Returns `true` if this completed a universe.
+    pub fn read(&mut self, data: &[u8], framing_error: bool) -> bool {
+        let (data, brk) = match data {
+            [data @ .., 0] if framing_error => (data, true),
+            data => (data, false),
+        };
+
+        let mut complete = false;
+        for b in data {
+            complete |= self.push(*b);
+        }
+        if brk {
+            complete |= self.on_break();
+        }
+        complete
+    }
+
+    /// The slots of the last universe, starting with slot one.
+    pub fn slots(&self) -> &[u8] {
+        &self.slots[..self.len]
+    }
+}
+
+/// The channels the device uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Footprint {
+    /// Three channels (red, green, blue) per pixel
+    Rgb { pixels: usize },
+    /// Three channels: dimmer, mode and speed
+    Personality,
+}
+
+/// The start address and footprint of the device in the universe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Patch {
+    /// The start address, starting with one
+    pub start: usize,
+    pub footprint: Footprint,
+}
+
+impl Patch {
+    /// Get the channels of the patch, if the universe contains all of them.
+    fn channels<'s>(&self, slots: &'s [u8], len: usize) -> Option<&'s [u8]> {
+        let start = self.start.max(1) - 1;
+        slots.get(start..start + len)
+    }
+
+    /// Get the pixels. Pixels missing from the universe are black.
+    pub fn pixels(&self, slots: &[u8], pixels: &mut [Rgb8]) {
+        let start = self.start.max(1) - 1;
+        let channels = slots.get(start..).unwrap_or_default();
+        let len = match self.footprint {
+            Footprint::Rgb { pixels } => pixels,
+            Footprint::Personality => 0,
+        };
+
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = match channels.get(i * 3..i * 3 + 3) {
+                Some([r, g, b]) if i < len => Rgb8::new(*r, *g, *b),
+                _ => BLACK,
+            };
+        }
+    }
+
+    /// Get the personality channels.
+    pub fn personality(&self, slots: &[u8]) -> Option<Personality> {
+        match self.channels(slots, 3)? {
+            [dimmer, mode, speed] => Some(Personality {
+                dimmer: *dimmer,
+                mode: *mode,
+                speed: *speed,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Personality {
+    pub dimmer: u8,
+    pub mode: u8,
+    pub speed: u8,
+}
+
+impl Personality {
+    /// The mode, by its identifier, with a range of [`MODE_RANGE`] values each.
+    pub fn mode(&self) -> ModeDiscriminants {
+        ModeDiscriminants::from_id(self.mode / MODE_RANGE).unwrap_or(ModeDiscriminants::Off)
+    }
+}
+
+/// Maps the personality onto the controller, sending only changes.
+pub struct PersonalityMapper {
+    last: Option<Personality>,
+}
+
+impl PersonalityMapper {
+    pub const fn new() -> Self {
+        Self { last: None }
+    }
+
+    pub fn update(&mut self, personality: Personality) -> Vec<Msg, 3> {
+        let mut result = Vec::new();
+        let last = self.last.replace(personality);
+
+        if last.map(|l| l.dimmer) != Some(personality.dimmer) {
+            result.push(Msg::SetBrightness(personality.dimmer)).ok();
+        }
+        if last.map(|l| l.mode()) != Some(personality.mode()) {
+            result.push(Msg::StreamMode(personality.mode())).ok();
+        }
+        if last.map(|l| l.speed) != Some(personality.speed) {
+            result.push(Msg::SetSpeed(personality.speed)).ok();
+        }
+
+        result
+    }
+}
+
+/// Receives DMX512 from a RS-485 transceiver, connected to the UART.
+///
+/// The break is reported by the UART as framing error, and received as zero byte. The error stops
+/// the reception, so that each read ends with the break, if any.
+pub struct DmxReceiver<const N: usize> {
+    uart: UarteWithIdle<'static, UARTE0, TIMER0>,
+    _break_ppi: Ppi<'static, PPI_CH4, 1, 1>,
+    runner: Address<Msg>,
+    patch: Patch,
+    parser: Parser,
+    mapper: PersonalityMapper,
+    pixels: [Rgb8; N],
+}
+
+impl<const N: usize> DmxReceiver<N> {
+    pub fn new(
+        uart: UarteWithIdle<'static, UARTE0, TIMER0>,
+        ppi_ch: PPI_CH4,
+        runner: Address<Msg>,
+        patch: Patch,
+    ) -> Self {
+        let uarte = unsafe { &*pac::UARTE0::ptr() };
+        let mut break_ppi = Ppi::new_one_to_one(
+            ppi_ch,
+            Event::from_reg(&uarte.events_error),
+            Task::from_reg(&uarte.tasks_stoprx),
+        );
+        break_ppi.enable();
+
+        Self {
+            uart,
+            _break_ppi: break_ppi,
+            runner,
+            patch,
+            parser: Parser::new(),
+            mapper: PersonalityMapper::new(),
+            pixels: [BLACK; N],
+        }
+    }
+
+    /// Check, and clear, the break condition of the UART, after each read.
+    fn take_break() -> bool {
+        let uarte = unsafe { &*pac::UARTE0::ptr() };
+        let errors = uarte.errorsrc.read();
+        let detected = errors.break_().bit_is_set() || errors.framing().bit_is_set();
+        uarte.errorsrc.write(|w| unsafe { w.bits(errors.bits()) });
+        detected
+    }
+
+    fn universe(&mut self) {
+        let slots = self.parser.slots();
+        match self.patch.footprint {
+            Footprint::Rgb { .. } => {
+                self.patch.pixels(slots, &mut self.pixels);
+                realtime::update(&self.pixels);
+                self.runner.try_notify(Msg::Frame).ok();
+            }
+            Footprint::Personality => {
+                if let Some(personality) = self.patch.personality(slots) {
+                    for msg in self.mapper.update(personality) {
+                        self.runner.try_notify(msg).ok();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[ector::actor]
+impl<const N: usize> Actor for DmxReceiver<N> {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        // start code, slots and the break
+        let mut buf = [0; SLOTS + 2];
+        loop {
+            let n = match self.uart.read_until_idle(&mut buf).await {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+
+            if self.parser.read(&buf[..n], Self::take_break()) {
+                self.universe();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn receive(parser: &mut Parser, packet: &[u8]) -> bool {
+        let mut complete = parser.on_break();
+        for b in packet {
+            complete |= parser.push(*b);
+        }
+        complete
+    }
+
+    #[test]
+    fn parse_universe() {
+        let mut parser = Parser::new();
+
+        // data before the first break is ignored
+        assert!(!parser.push(0x00));
+        assert!(!parser.push(0x42));
+
+        assert!(!receive(&mut parser, &[0x00, 1, 2, 3]));
+        // the packet is complete with the next break
+        assert!(parser.on_break());
+        assert_eq!(parser.slots(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn parse_full_universe() {
+        let mut parser = Parser::new();
+        let mut packet = [0x7Fu8; SLOTS + 1];
+        packet[0] = START_CODE_DIMMER;
+
+        // a full universe is complete without waiting for the break
+        assert!(receive(&mut parser, &packet));
+        assert_eq!(parser.slots().len(), SLOTS);
+        // and kept with the break
+        assert!(!parser.on_break());
+        assert_eq!(parser.slots(), &packet[1..]);
+
+        // also within a read ending with the break
+        packet[SLOTS] = 0x01;
+        let mut read = [0u8; SLOTS + 2];
+        read[..SLOTS + 1].copy_from_slice(&packet);
+        assert!(parser.read(&read, true));
+        assert_eq!(parser.slots(), &packet[1..]);
+    }
+
+    #[test]
+    fn reads_spanning_packets() {
+        let mut parser = Parser::new();
+
+        // the first read ends with a break
+        assert!(!parser.read(&[0x42, 0x00], true));
+        // the packet is split across reads, and the break ends the last one
+        assert!(!parser.read(&[0x00, 1, 2], false));
+        assert!(!parser.read(&[3, 0], false));
+        assert!(parser.read(&[4, 0x00], true));
+        assert_eq!(parser.slots(), &[1, 2, 3, 0, 4]);
+
+        // a zero slot without framing error is no break
+        assert!(!parser.read(&[0x00, 5, 0x00], false));
+        assert!(parser.read(&[0x00], true));
+        assert_eq!(parser.slots(), &[5, 0]);
+    }
+
+    #[test]
+    fn ignore_alternate_start_code() {
+        let mut parser = Parser::new();
+
+        // RDM packet
+        assert!(!receive(&mut parser, &[0xCC, 1, 2, 3]));
+        assert!(!parser.on_break());
+        assert_eq!(parser.slots(), &[]);
+    }
+
+    #[test]
+    fn rgb_pixels() {
+        let patch = Patch {
+            start: 4,
+            footprint: Footprint::Rgb { pixels: 2 },
+        };
+        let slots = [9, 9, 9, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mut pixels = [Rgb8::new(1, 1, 1); 3];
+
+        patch.pixels(&slots, &mut pixels);
+        // the third pixel is outside of the footprint
+        assert_eq!(pixels, [Rgb8::new(1, 2, 3), Rgb8::new(4, 5, 6), BLACK]);
+
+        // a short universe leaves the remaining pixels black
+        patch.pixels(&slots[..7], &mut pixels);
+        assert_eq!(pixels, [Rgb8::new(1, 2, 3), BLACK, BLACK]);
+    }
+
+    #[test]
+    fn personality() {
+        let patch = Patch {
+            start: 2,
+            footprint: Footprint::Personality,
+        };
+
+        assert_eq!(
+            patch.personality(&[0, 128, 0, 200]),
+            Some(Personality {
+                dimmer: 128,
+                mode: 0,
+                speed: 200
+            })
+        );
+        assert_eq!(patch.personality(&[0, 128, 0]), None);
+    }
+
+    #[test]
+    fn personality_mode() {
+        let mode = |mode| {
+            Personality {
+                dimmer: 0,
+                mode,
+                speed: 0,
+            }
+            .mode()
+        };
+        assert_eq!(mode(0), ModeDiscriminants::Off);
+        assert_eq!(mode(3), ModeDiscriminants::Off);
+        assert_eq!(mode(4), ModeDiscriminants::Fire);
+        assert_eq!(mode(7), ModeDiscriminants::Fire);
+        assert_eq!(mode(u8::MAX), ModeDiscriminants::Off);
+    }
+
+    #[test]
+    fn map_changes() {
+        let mut mapper = PersonalityMapper::new();
+        let personality = Personality {
+            dimmer: 100,
+            mode: 0,
+            speed: 50,
+        };
+
+        // everything is sent initially
+        assert_eq!(mapper.update(personality).len(), 3);
+        // nothing changed
+        assert!(mapper.update(personality).is_empty());
+
+        let msgs = mapper.update(Personality {
+            dimmer: 101,
+            ..personality
+        });
+        assert!(matches!(msgs.as_slice(), [Msg::SetBrightness(101)]));
+
+        let msgs = mapper.update(Personality {
+            dimmer: 101,
+            mode: MODE_RANGE,
+            ..personality
+        });
+        assert!(matches!(msgs.as_slice(), [Msg::StreamMode(_)]));
+    }
+}
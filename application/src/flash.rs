@@ -0,0 +1,221 @@
+//! Short flashes of light, shown on top of the current mode.
+//!
+//! Flashes light up a few pixels around a position, and fade out. Notifications blink the whole
+//! strip a few times.
+
+use crate::pattern::{add, scale};
+use embassy_time::Duration;
+use heapless::Vec;
+use neopixel_logic::rgb::Rgb8;
+
+/// Maximum number of concurrent flashes. Further flashes replace the oldest one.
+const MAX_FLASHES: usize = 8;
+/// Time for a flash to fade out, in ms
+const FADE_MS: u64 = 500;
+/// Number of pixels lit on each side of the center
+const RADIUS: usize = 2;
+/// Number of times a notification blinks
+const BLINKS: u64 = 3;
+/// Time a notification is on, and off, while blinking, in ms
+const BLINK_MS: u64 = 200;
+
+/// A request to flash a color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trigger {
+    /// Position on the strip, from the first (`0`) to the last pixel (`u8::MAX`)
+    pub position: u8,
+    pub color: Rgb8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Flash {
+    trigger: Trigger,
+    /// Remaining time, in ms
+    remaining: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Notification {
+    color: Rgb8,
+    /// Elapsed time, in ms
+    elapsed: u64,
+}
+
+pub struct Flashes {
+    flashes: Vec<Flash, MAX_FLASHES>,
+    notification: Option<Notification>,
+}
+
+impl Flashes {
+    pub const fn new() -> Self {
+        Self {
+            flashes: Vec::new(),
+            notification: None,
+        }
+    }
+
+    /// Blink the whole strip, replacing an ongoing notification.
+    pub fn notify(&mut self, color: Rgb8) {
+        self.notification = Some(Notification { color, elapsed: 0 });
+    }
+
+    pub fn trigger(&mut self, trigger: Trigger) {
+        if self.flashes.is_full() {
+            self.flashes.remove(0);
+        }
+        self.flashes
+            .push(Flash {
+                trigger,
+                remaining: FADE_MS,
+            })
+            .ok();
+    }
+
+    /// Let the flashes fade out, and the notification blink.
+    ///
+    /// Returns `true` if the flashes need to be rendered. This includes the first call after
+    /// the last flash faded out, so that the strip gets restored.
+    pub fn advance(&mut self, delta: Duration) -> bool {
+        let active = !self.flashes.is_empty() || self.notification.is_some();
+        let delta = delta.as_millis();
+        self.flashes.retain_mut(|flash| {
+            flash.remaining = flash.remaining.saturating_sub(delta);
+            flash.remaining > 0
+        });
+        if let Some(notification) = &mut self.notification {
+            notification.elapsed += delta;
+            if notification.elapsed >= BLINKS * 2 * BLINK_MS {
+                self.notification = None;
+            }
+        }
+        active
+    }
+
+    /// Render the flashes on top of the base pixels.
+    pub fn render(&self, base: &[Rgb8], pixels: &mut [Rgb8]) {
+        pixels.copy_from_slice(base);
+        if pixels.is_empty() {
+            return;
+        }
+
+        let last = pixels.len() - 1;
+        for flash in &self.flashes {
+            let center = flash.trigger.position as usize * last / u8::MAX as usize;
+            let from = center.saturating_sub(RADIUS);
+            let to = (center + RADIUS).min(last);
+
+            for (i, pixel) in pixels.iter_mut().enumerate().take(to + 1).skip(from) {
+                // fade out over time, and towards the edges
+                let distance = center.abs_diff(i) as u64;
+                let level = (flash.remaining * (RADIUS as u64 + 1 - distance)) as f32
+                    / (FADE_MS * (RADIUS as u64 + 1)) as f32;
+                *pixel = add(*pixel, scale(flash.trigger.color, level));
+            }
+        }
+
+        if let Some(notification) = &self.notification {
+            if (notification.elapsed / BLINK_MS) % 2 == 0 {
+                pixels.fill(notification.color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use neopixel_logic::rgb::{BLACK, RED};
+
+    #[test]
+    fn render_flash() {
+        let mut flashes = Flashes::new();
+        flashes.trigger(Trigger {
+            position: 0,
+            color: RED,
+        });
+
+        let base = [Rgb8::new(0, 0, 10); 5];
+        let mut pixels = [BLACK; 5];
+        flashes.render(&base, &mut pixels);
+
+        assert_eq!(
+            pixels,
+            [
+                Rgb8::new(255, 0, 10),
+                Rgb8::new(170, 0, 10),
+                Rgb8::new(85, 0, 10),
+                Rgb8::new(0, 0, 10),
+                Rgb8::new(0, 0, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn position() {
+        let mut flashes = Flashes::new();
+        flashes.trigger(Trigger {
+            position: u8::MAX,
+            color: RED,
+        });
+
+        let mut pixels = [BLACK; 10];
+        flashes.render(&[BLACK; 10], &mut pixels);
+        assert_eq!(pixels[9], RED);
+        assert_eq!(pixels[6], BLACK);
+    }
+
+    #[test]
+    fn fade_out() {
+        let mut flashes = Flashes::new();
+        assert!(!flashes.advance(Duration::from_millis(10)));
+
+        flashes.trigger(Trigger {
+            position: 0,
+            color: RED,
+        });
+
+        assert!(flashes.advance(Duration::from_millis(FADE_MS / 2)));
+        let mut pixels = [BLACK; 1];
+        flashes.render(&[BLACK], &mut pixels);
+        assert_eq!(pixels, [Rgb8::new(127, 0, 0)]);
+
+        // rendered once more, after fading out
+        assert!(flashes.advance(Duration::from_millis(FADE_MS)));
+        assert!(!flashes.advance(Duration::from_millis(10)));
+        flashes.render(&[BLACK], &mut pixels);
+        assert_eq!(pixels, [BLACK]);
+    }
+
+    #[test]
+    fn notification() {
+        let mut flashes = Flashes::new();
+        flashes.notify(RED);
+
+        let mut blinks = heapless::Vec::<bool, 16>::new();
+        while flashes.advance(Duration::from_millis(BLINK_MS / 2)) {
+            let mut pixels = [BLACK; 3];
+            flashes.render(&[BLACK; 3], &mut pixels);
+            blinks.push(pixels == [RED; 3]).unwrap();
+        }
+
+        // on and off three times, and cleared after the last one
+        assert_eq!(
+            blinks,
+            [true, false, false, true, true, false, false, true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn replace_oldest() {
+        let mut flashes = Flashes::new();
+        for position in 0..=MAX_FLASHES as u8 {
+            flashes.trigger(Trigger {
+                position,
+                color: RED,
+            });
+        }
+
+        assert_eq!(flashes.flashes.len(), MAX_FLASHES);
+        assert_eq!(flashes.flashes[0].trigger.position, 1);
+    }
+}
@@ -1,22 +1,26 @@
 use ector::{Actor, ActorContext, Address, Inbox};
-use embassy::executor::Spawner;
+use embassy_executor::Spawner;
 use nrf_softdevice::ble::{gatt_server, peripheral, Connection};
 use nrf_softdevice::raw;
 use nrf_softdevice::Softdevice;
 
-use embassy::time::Duration;
+use embassy_time::Duration;
 
 use crate::board::*;
 use crate::pattern::ModeDiscriminants;
 use crate::runner;
-use embassy::time::Ticker;
-use futures::{future::select, future::Either, pin_mut, StreamExt};
+use crate::{dfu, info, status, wallclock};
+use embassy_futures::select::{select, select3, Either, Either3};
+use embassy_time::Ticker;
+use futures::StreamExt;
 use heapless::Vec;
+use neopixel_protocol::{Color, LightState, TimeOfDay};
 
 #[nrf_softdevice::gatt_server]
 pub struct BurrBoardServer {
     pub board: BurrBoardService,
     pub device_info: DeviceInformationService,
+    pub firmware: FirmwareUpdateService,
 }
 
 /// Gatt services for our module
@@ -32,6 +36,16 @@ pub struct BurrBoardService {
 
     #[characteristic(uuid = "1b25", read, write)]
     pub report_interval: u16,
+
+    /// The color, as RGB or HSV, encoded as a [`Color`]
+    #[characteristic(uuid = "00002001-b0cd-11ec-871f-d45ddf138840", write)]
+    pub color: Vec<u8, { Color::ENCODED_LEN }>,
+    /// The second color, for the end of the gradient
+    #[characteristic(uuid = "00002002-b0cd-11ec-871f-d45ddf138840", write)]
+    pub second_color: Vec<u8, { Color::ENCODED_LEN }>,
+    /// The time of day for the wall clock, encoded as a [`TimeOfDay`]
+    #[characteristic(uuid = "00002003-b0cd-11ec-871f-d45ddf138840", write)]
+    pub time: Vec<u8, { TimeOfDay::ENCODED_LEN }>,
 }
 
 #[nrf_softdevice::gatt_service(uuid = "180a")]
@@ -42,26 +56,76 @@ pub struct DeviceInformationService {
     pub serial_number: Vec<u8, 32>,
     #[characteristic(uuid = "2a27", read)]
     pub hardware_revision: Vec<u8, 4>,
+    #[characteristic(uuid = "2a26", read)]
+    pub firmware_revision: Vec<u8, 32>,
     #[characteristic(uuid = "2a29", read)]
     pub manufacturer_name: Vec<u8, 32>,
 }
 
+/// Firmware update over BLE.
+///
+/// Start an update by writing `0x01`, the image size and the CRC-32 of the image (both `u32`,
+/// little endian) to `control`. Then write the chunks to `data`, each prefixed with its offset
+/// (`u32`, little endian). Finish by writing `0x02` to `control`, or abort with `0x03`. The
+/// `status` reports the state and the offset the next chunk is expected at. Wait for it after each
+/// chunk, writes arriving while the update is busy are dropped.
+#[nrf_softdevice::gatt_service(uuid = "00001000-b0cd-11ec-871f-d45ddf138840")]
+pub struct FirmwareUpdateService {
+    #[characteristic(uuid = "00001001-b0cd-11ec-871f-d45ddf138840", read)]
+    pub version: Vec<u8, 32>,
+    #[characteristic(uuid = "00001002-b0cd-11ec-871f-d45ddf138840", write)]
+    pub control: Vec<u8, { dfu::CONTROL_LEN }>,
+    #[characteristic(uuid = "00001003-b0cd-11ec-871f-d45ddf138840", write)]
+    pub data: Vec<u8, { dfu::CHUNK_LEN }>,
+    #[characteristic(uuid = "00001004-b0cd-11ec-871f-d45ddf138840", read, notify)]
+    pub status: [u8; 5],
+}
+
+impl DeviceInformationService {
+    /// Fill the characteristics from the hardware and the build metadata.
+    pub fn populate(&self) {
+        self.model_number_set(to_vec(MODEL_NUMBER)).ok();
+        self.serial_number_set(to_vec(&info::serial_number())).ok();
+        self.hardware_revision_set(to_vec(HARDWARE_REVISION)).ok();
+        self.firmware_revision_set(to_vec(&info::firmware_revision()))
+            .ok();
+        self.manufacturer_name_set(to_vec(MANUFACTURER_NAME)).ok();
+    }
+}
+
+/// Convert a string into a characteristic value, truncating if necessary.
+fn to_vec<const N: usize>(value: &str) -> Vec<u8, N> {
+    let bytes = value.as_bytes();
+    Vec::from_slice(&bytes[..bytes.len().min(N)]).unwrap_or_default()
+}
+
 pub struct BurrBoardMonitor {
     ticker: Ticker,
     _service: &'static BurrBoardService,
+    firmware_service: &'static FirmwareUpdateService,
     runner: Address<runner::Msg>,
+    firmware: Address<dfu::Msg>,
     connections: Vec<Connection, 2>,
     _notifications: bool,
+    firmware_notifications: bool,
 }
 
 impl BurrBoardMonitor {
-    pub fn new(service: &'static BurrBoardService, runner: Address<runner::Msg>) -> Self {
+    pub fn new(
+        service: &'static BurrBoardService,
+        firmware_service: &'static FirmwareUpdateService,
+        runner: Address<runner::Msg>,
+        firmware: Address<dfu::Msg>,
+    ) -> Self {
         Self {
             _service: service,
+            firmware_service,
             connections: Vec::new(),
             ticker: Ticker::every(Duration::from_secs(1)),
             runner,
+            firmware,
             _notifications: false,
+            firmware_notifications: false,
         }
     }
 
@@ -101,16 +165,57 @@ impl BurrBoardMonitor {
 
             BurrBoardServiceEvent::DirectionWrite(val) => {
                 info!("Direction: {}", val);
-                let mode = match val {
-                    //0 => ModeDiscriminants::UA,
-                    //1 => ModeDiscriminants::DE,
-                    2 => ModeDiscriminants::Rainbow,
-                    3 => ModeDiscriminants::RainbowPart,
-                    _ => ModeDiscriminants::Off,
-                };
+                // the identifier of the mode, as in the advertised state
+                let mode = ModeDiscriminants::from_id(*val).unwrap_or(ModeDiscriminants::Off);
 
                 self.runner.try_notify(runner::Msg::SetMode(mode)).ok();
             }
+
+            BurrBoardServiceEvent::ColorWrite(data) => match Color::decode(data) {
+                Ok(color) => {
+                    self.runner.try_notify(runner::Msg::SetColor(color)).ok();
+                }
+                Err(_) => warn!("Invalid color"),
+            },
+
+            BurrBoardServiceEvent::SecondColorWrite(data) => match Color::decode(data) {
+                Ok(color) => {
+                    self.runner
+                        .try_notify(runner::Msg::SetSecondColor(color))
+                        .ok();
+                }
+                Err(_) => warn!("Invalid color"),
+            },
+
+            BurrBoardServiceEvent::TimeWrite(data) => match TimeOfDay::decode(data) {
+                Ok(time) => wallclock::set(time),
+                Err(_) => warn!("Invalid time"),
+            },
+        }
+    }
+
+    pub async fn handle_firmware_event(&mut self, event: FirmwareUpdateServiceEvent) {
+        match event {
+            FirmwareUpdateServiceEvent::ControlWrite(data) => {
+                self.firmware.notify(dfu::Msg::Control(data)).await;
+            }
+            FirmwareUpdateServiceEvent::DataWrite(data) => {
+                // wait for the chunk to be written, rather than dropping it
+                self.firmware.notify(dfu::Msg::Chunk(data)).await;
+            }
+            FirmwareUpdateServiceEvent::StatusCccdWrite { notifications } => {
+                self.firmware_notifications = notifications;
+            }
+        }
+    }
+
+    pub fn firmware_status(&mut self, status: dfu::Status) {
+        let status = status.to_bytes();
+        self.firmware_service.status_set(status).ok();
+        if self.firmware_notifications {
+            for c in self.connections.iter() {
+                self.firmware_service.status_notify(c, status).ok();
+            }
         }
     }
 }
@@ -119,6 +224,7 @@ pub enum MonitorEvent {
     Connected(Connection),
     Disconnected(Connection),
     Event(BurrBoardServiceEvent),
+    Firmware(FirmwareUpdateServiceEvent),
 }
 
 #[ector::actor]
@@ -133,14 +239,8 @@ impl Actor for BurrBoardMonitor {
         M: Inbox<Self::Message<'m>>,
     {
         loop {
-            let inbox_fut = inbox.next();
-            let ticker_fut = self.ticker.next();
-
-            pin_mut!(inbox_fut);
-            pin_mut!(ticker_fut);
-
-            match select(inbox_fut, ticker_fut).await {
-                Either::Left((m, _)) => match m {
+            match select3(inbox.next(), self.ticker.next(), dfu::STATUS.wait()).await {
+                Either3::First(m) => match m {
                     MonitorEvent::Connected(conn) => {
                         self.add_connection(&conn);
                     }
@@ -150,8 +250,14 @@ impl Actor for BurrBoardMonitor {
                     MonitorEvent::Event(event) => {
                         self.handle_event(&event);
                     }
+                    MonitorEvent::Firmware(event) => {
+                        self.handle_firmware_event(event).await;
+                    }
                 },
-                Either::Right((_, _)) => {
+                Either3::Third(status) => {
+                    self.firmware_status(status);
+                }
+                Either3::Second(_) => {
                     /*
                     let mut data: Vec<u8, 22> = Vec::new();
                     let analog = self.analog.request(AnalogRead).unwrap().await;
@@ -206,30 +312,35 @@ impl Actor for BurrBoardMonitor {
     }
 }
 
-#[embassy::task]
+#[embassy_executor::task]
 pub async fn bluetooth_task(
     sd: &'static Softdevice,
     server: &'static BurrBoardServer,
     monitor: Address<MonitorEvent>,
 ) {
-    #[rustfmt::skip]
-    let adv_data = &[
-        0x02, 0x01, raw::BLE_GAP_ADV_FLAGS_LE_ONLY_GENERAL_DISC_MODE as u8,
-        0x03, 0x03, 0x60, 0x18,
-        0x0a, 0x09, b'D', b'o', b'D', b'o', b'B', b'o', b'a', b'r', b'd',
-    ];
     #[rustfmt::skip]
     let scan_data = &[
         0x03, 0x03, 0x09, 0x18,
     ];
 
     loop {
+        let adv_data = adv_data(status::current());
         let config = peripheral::Config::default();
         let adv = peripheral::ConnectableAdvertisement::ScannableUndirected {
-            adv_data,
+            adv_data: &adv_data,
             scan_data,
         };
-        let conn = unwrap!(peripheral::advertise_connectable(sd, adv, &config).await);
+
+        // restart advertising when the state changes, to update the data
+        let conn = match select(
+            peripheral::advertise_connectable(sd, adv, &config),
+            status::CHANGED.recv(),
+        )
+        .await
+        {
+            Either::First(conn) => unwrap!(conn),
+            Either::Second(_) => continue,
+        };
 
         info!("advertising done!");
 
@@ -241,6 +352,12 @@ pub async fn bluetooth_task(
                 monitor.try_notify(MonitorEvent::Event(e)).ok();
             }
             BurrBoardServerEvent::DeviceInfo(_) => {}
+            BurrBoardServerEvent::Firmware(e) => {
+                // the next status reports where to continue
+                if monitor.try_notify(MonitorEvent::Firmware(e)).is_err() {
+                    warn!("Firmware update busy, dropping a write");
+                }
+            }
         })
         .await;
         monitor.try_notify(MonitorEvent::Disconnected(conn)).ok();
@@ -251,25 +368,56 @@ pub async fn bluetooth_task(
     }
 }
 
+/// Create the advertising data, including the current state as manufacturer specific data.
+fn adv_data(state: LightState) -> Vec<u8, 31> {
+    #[rustfmt::skip]
+    let prefix = &[
+        0x02, 0x01, raw::BLE_GAP_ADV_FLAGS_LE_ONLY_GENERAL_DISC_MODE as u8,
+        0x03, 0x03, 0x60, 0x18,
+        0x0a, 0x09, b'D', b'o', b'D', b'o', b'B', b'o', b'a', b'r', b'd',
+    ];
+
+    let mut data = Vec::new();
+    data.extend_from_slice(prefix).ok();
+    data.extend_from_slice(&state.encode_ad()).ok();
+    data
+}
+
 pub struct GattApp {
     server: BurrBoardServer,
 
-    monitor: ActorContext<BurrBoardMonitor>,
+    monitor: ActorContext<BurrBoardMonitor, 4>,
 }
 
 impl GattApp {
     pub fn enable(sd: &'static Softdevice) -> Self {
-        let server = gatt_server::register(sd).unwrap();
+        let server: BurrBoardServer = gatt_server::register(sd).unwrap();
+        server.device_info.populate();
+        server
+            .firmware
+            .version_set(to_vec(&info::firmware_revision()))
+            .ok();
         Self {
             server,
             monitor: ActorContext::new(),
         }
     }
 
-    pub fn mount(&'static self, s: Spawner, sd: &'static Softdevice, p: &BoardActors) {
+    pub fn mount(
+        &'static self,
+        s: Spawner,
+        sd: &'static Softdevice,
+        p: &BoardActors,
+        firmware: Address<dfu::Msg>,
+    ) {
         let monitor = self.monitor.mount(
             s,
-            BurrBoardMonitor::new(&self.server.board, p.runner.clone()),
+            BurrBoardMonitor::new(
+                &self.server.board,
+                &self.server.firmware,
+                p.runner.clone(),
+                firmware,
+            ),
         );
 
         s.spawn(bluetooth_task(sd, &self.server, monitor)).unwrap();
@@ -0,0 +1,29 @@
+use core::fmt::Write;
+use heapless::String;
+
+/// Firmware version, from the crate version
+pub const FIRMWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Git revision the firmware was built from, injected by `build.rs`
+pub const GIT_HASH: &str = env!("GIT_HASH");
+
+/// Get the 64 bit device ID, burned into the FICR by the factory.
+pub fn device_id() -> u64 {
+    let ficr = unsafe { &*embassy_nrf::pac::FICR::ptr() };
+    let low = ficr.deviceid[0].read().bits() as u64;
+    let high = ficr.deviceid[1].read().bits() as u64;
+    (high << 32) | low
+}
+
+/// The serial number, which is the device ID in hex.
+pub fn serial_number() -> String<16> {
+    let mut result = String::new();
+    write!(result, "{:016X}", device_id()).ok();
+    result
+}
+
+/// The firmware revision, combining version and git revision (e.g. `0.1.0-1a2b3c4`).
+pub fn firmware_revision() -> String<32> {
+    let mut result = String::new();
+    write!(result, "{}-{}", FIRMWARE_VERSION, GIT_HASH).ok();
+    result
+}
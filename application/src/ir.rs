@@ -0,0 +1,111 @@
+//! Receiver for infrared remote controls, using the NEC or RC5 protocol.
+//!
+//! The pulses are timed from the edges of an IR receiver module (like the TSOP38238), which
+//! demodulates the carrier. Its output is low while a carrier is received (a mark), and high
+//! otherwise (a space). The decoders and the keymap are in the `neopixel-logic` crate.
+
+use crate::{flash::Trigger, runner::Msg};
+use core::cell::Cell;
+use ector::{Actor, Address, Inbox};
+use embassy_nrf::gpio::{AnyPin, Input};
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::Instant;
+use neopixel_logic::ir::{Decoder, Event, Keymap, Pulse};
+use neopixel_logic::rgb::WHITE;
+
+pub use neopixel_logic::ir::Action;
+
+/// The message for the runner, to carry out an action.
+fn to_msg(action: Action) -> Msg {
+    match action {
+        Action::On => Msg::On,
+        Action::Off => Msg::Off,
+        Action::Toggle => Msg::Toggle,
+        Action::Lighter => Msg::Lighter,
+        Action::Darker => Msg::Darker,
+        Action::Faster => Msg::Faster,
+        Action::Slower => Msg::Slower,
+        Action::NextMode => Msg::Next,
+        Action::PrevMode => Msg::Prev,
+        Action::Color(color) => Msg::SetColor(color),
+    }
+}
+
+/// The action to bind to the next key, which is received.
+static LEARN: Mutex<CriticalSectionRawMutex, Cell<Option<Action>>> = Mutex::new(Cell::new(None));
+
+/// Bind the next key, which is received, to an action.
+pub fn learn(action: Action) {
+    LEARN.lock(|learn| learn.set(Some(action)));
+}
+
+pub struct IrReceiver {
+    pin: Input<'static, AnyPin>,
+    runner: Address<Msg>,
+    decoder: Decoder,
+    keymap: Keymap,
+}
+
+impl IrReceiver {
+    pub fn new(pin: Input<'static, AnyPin>, runner: Address<Msg>) -> Self {
+        Self {
+            pin,
+            runner,
+            decoder: Decoder::new(),
+            keymap: Keymap::new(),
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        if !event.repeat {
+            if let Some(action) = LEARN.lock(|learn| learn.take()) {
+                defmt::info!("Learned IR key: {}", defmt::Debug2Format(&event.key));
+                if self.keymap.bind(event.key, action) {
+                    // confirm
+                    let trigger = Trigger {
+                        position: u8::MAX / 2,
+                        color: WHITE,
+                    };
+                    self.runner.try_notify(Msg::Flash(trigger)).ok();
+                }
+                return;
+            }
+        }
+
+        match self.keymap.get(event.key) {
+            Some(action) if !event.repeat || action.repeats() => {
+                self.runner.try_notify(to_msg(action)).ok();
+            }
+            Some(_) => {}
+            None => {
+                defmt::info!("Unknown IR key: {}", defmt::Debug2Format(&event.key));
+            }
+        }
+    }
+}
+
+#[ector::actor]
+impl Actor for IrReceiver {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        let mut last = Instant::now();
+        loop {
+            self.pin.wait_for_any_edge().await;
+            let now = Instant::now();
+            let pulse = Pulse {
+                // the pulse before the edge, the receiver output is low during a mark
+                mark: self.pin.is_high(),
+                us: (now - last).as_micros().min(u32::MAX as u64) as u32,
+            };
+            last = now;
+
+            if let Some(event) = self.decoder.push(pulse) {
+                self.handle(event);
+            }
+        }
+    }
+}
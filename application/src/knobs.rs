@@ -0,0 +1,56 @@
+//! Potentiometer knobs, setting the brightness and the speed.
+//!
+//! Both knobs are sampled by the SAADC, with VDD as reference. The readings are smoothed, and
+//! only changes larger than a deadband are reported, so that noise doesn't turn into messages.
+
+use crate::runner::Msg;
+use ector::{Actor, Address, Inbox};
+use embassy_nrf::saadc::Saadc;
+use embassy_time::{Duration, Timer};
+use neopixel_logic::knobs::{brightness, Knob};
+
+/// Interval of sampling the knobs
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+pub struct Knobs {
+    saadc: Saadc<'static, 2>,
+    runner: Address<Msg>,
+    brightness: Knob,
+    speed: Knob,
+}
+
+impl Knobs {
+    /// Create the knobs, sampling the brightness from the first channel, and the speed from the
+    /// second one.
+    pub fn new(saadc: Saadc<'static, 2>, runner: Address<Msg>) -> Self {
+        Self {
+            saadc,
+            runner,
+            brightness: Knob::new(),
+            speed: Knob::new(),
+        }
+    }
+}
+
+#[ector::actor]
+impl Actor for Knobs {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        let mut buf = [0; 2];
+        loop {
+            self.saadc.sample(&mut buf).await;
+            if let Some(position) = self.brightness.update(buf[0]) {
+                let msg = Msg::SetBrightness(brightness(position));
+                self.runner.try_notify(msg).ok();
+            }
+            if let Some(position) = self.speed.update(buf[1]) {
+                self.runner.try_notify(Msg::SetSpeed(position)).ok();
+            }
+            Timer::after(SAMPLE_INTERVAL).await;
+        }
+    }
+}
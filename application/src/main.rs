@@ -15,6 +15,9 @@ use drogue_device::drivers::led::neopixel::rgb::NeoPixelRgb;
 use embassy_nrf::gpio::{AnyPin, Input, Level, Output, OutputDrive, Pin, Pull};
 use futures::future::{select, Either};
 use futures::pin_mut;
+use neopixel_logic::easing;
+#[cfg(feature = "dfu")]
+use static_cell::StaticCell;
 
 //mod fmt;
 
@@ -30,18 +33,71 @@ use panic_reset as _;
 #[cfg(feature = "log")]
 mod logger;
 
+#[cfg(feature = "accel")]
+mod accel;
+
+#[cfg(feature = "ambient")]
+mod ambient;
+
 #[cfg(feature = "ble")]
 mod app;
 #[cfg(feature = "ble")]
 mod gatt;
+#[cfg(feature = "ble")]
+mod info;
+
+#[cfg(feature = "dfu")]
+mod dfu;
+#[cfg(feature = "dfu")]
+mod storage;
+
+#[cfg(feature = "dmx")]
+mod dmx;
+
+#[cfg(feature = "ir")]
+mod ir;
+
+#[cfg(feature = "knobs")]
+mod knobs;
+
+#[cfg(feature = "midi")]
+mod midi;
+
+#[cfg(feature = "pir")]
+mod pir;
+
+#[cfg(feature = "thermal")]
+mod thermal;
+
+#[cfg(any(
+    all(feature = "ambient", feature = "ntc"),
+    all(feature = "ambient", feature = "knobs"),
+    all(feature = "knobs", feature = "ntc")
+))]
+compile_error!("Only one of the ambient light sensor, the NTC and the knobs can use the SAADC");
+
+#[cfg(feature = "triggers")]
+mod triggers;
+
+#[cfg(any(feature = "usb", feature = "uart"))]
+mod console;
+#[cfg(any(feature = "usb", feature = "uart"))]
+mod shell;
+#[cfg(feature = "usb")]
+mod usb;
 
 mod board;
 mod control;
 mod controller;
+mod flash;
 //mod led;
 mod runner;
 //mod softdevice;
 mod pattern;
+mod realtime;
+mod rng;
+mod status;
+mod wallclock;
 mod watchdog;
 
 #[cfg(feature = "ble")]
@@ -53,6 +109,28 @@ use runner::*;
 use controller::*;
 use watchdog::*;
 
+/// Critical sections disabling all interrupts, as on a single core. With BLE, the softdevice
+/// implements them instead, only disabling the interrupts it doesn't reserve.
+#[cfg(not(feature = "ble"))]
+mod single_core {
+    struct SingleCore;
+    critical_section::set_impl!(SingleCore);
+
+    unsafe impl critical_section::Impl for SingleCore {
+        unsafe fn acquire() -> bool {
+            let active = cortex_m::register::primask::read().is_active();
+            cortex_m::interrupt::disable();
+            active
+        }
+
+        unsafe fn release(active: bool) {
+            if active {
+                cortex_m::interrupt::enable();
+            }
+        }
+    }
+}
+
 // Application must run at a lower priority than softdevice
 fn config() -> Config {
     let mut config = embassy_nrf::config::Config::default();
@@ -64,7 +142,18 @@ fn config() -> Config {
 #[embassy_executor::main]
 //#[embassy::main]
 async fn main(s: embassy_executor::Spawner) {
-    let p = embassy_nrf::init(Default::default());
+    let mut config = Config::default();
+    // USB requires the crystal oscillator
+    #[cfg(feature = "usb")]
+    {
+        config.hfclk_source = embassy_nrf::config::HfclkSource::ExternalXtal;
+    }
+    // The RTC keeps the wall clock, which drifts a lot less using the crystal
+    #[cfg(feature = "lfxo")]
+    {
+        config.lfclk_source = embassy_nrf::config::LfclkSource::ExternalXtal;
+    }
+    let p = embassy_nrf::init(config);
 
     // Setup burrboard peripherals
     static BOARD: BurrBoard = BurrBoard::new();
@@ -77,6 +166,9 @@ async fn main(s: embassy_executor::Spawner) {
     );
 
     let mut user_led = Output::new(p.P1_10.degrade(), Level::Low, OutputDrive::Standard);
+
+    // Seed the random effects, before the softdevice takes over the RNG
+    let seed = rng::hardware_seed(p.RNG, embassy_nrf::interrupt::take!(RNG)).await;
     #[cfg(feature = "ble")]
     let enable_ble = enable_ble(&mut buttons.0, &mut user_led).await;
 
@@ -86,21 +178,252 @@ async fn main(s: embassy_executor::Spawner) {
             buttons,
             //neopixel: defmt::unwrap!(NeoPixelRgb::<'_, _, NUM_LEDS>::new(p.PWM0, p.P1_08)),
             neopixel: NeoPixelRgb::<'_, _, NUM_LEDS>::new(p.PWM0, p.P1_08).unwrap(),
+            seed,
         },
     );
 
-    // Launch the softdevice
+    // Launch the USB serial console
+    #[cfg(feature = "usb")]
+    usb::start(
+        s,
+        p.USBD,
+        embassy_nrf::interrupt::take!(USBD),
+        ap.runner.clone(),
+    );
+
+    // Launch the serial console on the UART
+    #[cfg(feature = "uart")]
+    {
+        let mut config = embassy_nrf::uarte::Config::default();
+        config.baudrate = embassy_nrf::uarte::Baudrate::BAUD115200;
+        let uart = embassy_nrf::uarte::UarteWithIdle::new(
+            p.UARTE1,
+            p.TIMER1,
+            p.PPI_CH2,
+            p.PPI_CH3,
+            embassy_nrf::interrupt::take!(UARTE1),
+            p.P1_02,
+            p.P1_03,
+            config,
+        );
+        s.spawn(console::uart_task(uart, ap.runner.clone()))
+            .unwrap();
+    }
+
+    // Launch the DMX512 receiver
+    #[cfg(feature = "dmx")]
+    {
+        let mut config = embassy_nrf::uarte::Config::default();
+        config.baudrate = embassy_nrf::uarte::Baudrate::BAUD250K;
+        let uart = embassy_nrf::uarte::UarteWithIdle::new(
+            p.UARTE0,
+            p.TIMER0,
+            p.PPI_CH0,
+            p.PPI_CH1,
+            embassy_nrf::interrupt::take!(UARTE0_UART0),
+            p.P0_24,
+            p.P0_25,
+            config,
+        );
+
+        static DMX: ActorContext<dmx::DmxReceiver<NUM_LEDS>> = ActorContext::new();
+        DMX.mount(
+            s,
+            dmx::DmxReceiver::new(uart, p.PPI_CH4, ap.runner.clone(), DMX_PATCH),
+        );
+    }
+
+    // Launch the ambient light sensor
+    #[cfg(feature = "ambient")]
+    {
+        use embassy_nrf::saadc::{ChannelConfig, Config, Resolution, Saadc};
+        // the readings are converted at 12 bits
+        let mut config = Config::default();
+        config.resolution = Resolution::_12BIT;
+        let channel = ChannelConfig::single_ended(p.P0_04);
+        let saadc = Saadc::new(
+            p.SAADC,
+            embassy_nrf::interrupt::take!(SAADC),
+            config,
+            [channel],
+        );
+
+        static AMBIENT: ActorContext<ambient::AmbientSensor> = ActorContext::new();
+        AMBIENT.mount(
+            s,
+            ambient::AmbientSensor::new(saadc, ap.runner.clone(), AMBIENT_CURVE),
+        );
+    }
+
+    // Launch the knobs
+    #[cfg(feature = "knobs")]
+    {
+        use embassy_nrf::saadc::{ChannelConfig, Config, Gain, Reference, Resolution, Saadc};
+        // the readings are converted at 12 bits
+        let mut config = Config::default();
+        config.resolution = Resolution::_12BIT;
+        let mut brightness = ChannelConfig::single_ended(p.P0_28);
+        brightness.reference = Reference::VDD1_4;
+        brightness.gain = Gain::GAIN1_4;
+        let mut speed = ChannelConfig::single_ended(p.P0_29);
+        speed.reference = Reference::VDD1_4;
+        speed.gain = Gain::GAIN1_4;
+        let saadc = Saadc::new(
+            p.SAADC,
+            embassy_nrf::interrupt::take!(SAADC),
+            config,
+            [brightness, speed],
+        );
+
+        static KNOBS: ActorContext<knobs::Knobs> = ActorContext::new();
+        KNOBS.mount(s, knobs::Knobs::new(saadc, ap.runner.clone()));
+    }
+
+    // Launch the IR receiver
+    #[cfg(feature = "ir")]
+    {
+        static IR: ActorContext<ir::IrReceiver> = ActorContext::new();
+        IR.mount(
+            s,
+            ir::IrReceiver::new(Input::new(p.P0_30.degrade(), Pull::Up), ap.runner.clone()),
+        );
+    }
+
+    // Launch the motion sensor
+    #[cfg(feature = "pir")]
+    {
+        static PIR: ActorContext<pir::MotionSensor> = ActorContext::new();
+        PIR.mount(
+            s,
+            pir::MotionSensor::new(Input::new(p.P0_31.degrade(), Pull::Down), ap.runner.clone()),
+        );
+    }
+
+    // Launch the accelerometer
+    #[cfg(feature = "accel")]
+    {
+        use embassy_nrf::{peripherals::TWISPI0, twim};
+        let twim = twim::Twim::new(
+            p.TWISPI0,
+            embassy_nrf::interrupt::take!(SPIM0_SPIS0_TWIM0_TWIS0_SPI0_TWI0),
+            p.P0_12,
+            p.P0_11,
+            twim::Config::default(),
+        );
+
+        type Accel = accel::Lis3dh<twim::Twim<'static, TWISPI0>>;
+        static ACCEL: ActorContext<accel::AccelInput<Accel>> = ActorContext::new();
+        match accel::Lis3dh::new(twim, accel::LIS3DH_ADDRESS) {
+            Ok(lis3dh) => {
+                ACCEL.mount(s, accel::AccelInput::new(lis3dh, ap.runner.clone()));
+            }
+            Err(err) => defmt::warn!(
+                "Failed to set up the accelerometer: {}",
+                defmt::Debug2Format(&err)
+            ),
+        }
+    }
+
+    // Launch the trigger inputs
+    #[cfg(feature = "triggers")]
+    {
+        let inputs = [
+            Input::new(p.P0_02.degrade(), Pull::Up),
+            Input::new(p.P0_03.degrade(), Pull::Up),
+            Input::new(p.P1_01.degrade(), Pull::Up),
+        ];
+
+        static TRIGGER_INPUTS: ActorContext<triggers::Triggers<3>> = ActorContext::new();
+        TRIGGER_INPUTS.mount(
+            s,
+            triggers::Triggers::new(inputs, TRIGGERS, ap.runner.clone()),
+        );
+    }
+
+    // Enable the softdevice
     #[cfg(feature = "ble")]
-    if enable_ble {
+    let app = if enable_ble {
         info!("Enable BLE");
         user_led.set_high();
 
-        static LED: Forever<Output<'static, AnyPin>> = Forever::new();
-        LED.put(user_led);
+        static LED: StaticCell<Output<'static, AnyPin>> = StaticCell::new();
+        LED.init(user_led);
+
+        static APP: StaticCell<App> = StaticCell::new();
+        Some(&*APP.init(App::enable(s, "Neopixel")))
+    } else {
+        None
+    };
+
+    // Launch the firmware manager, confirming the running firmware, and the storage of the
+    // settings. Once enabled, the softdevice owns the flash.
+    #[cfg(feature = "dfu")]
+    let firmware = {
+        #[cfg(feature = "ble")]
+        let partition = match app {
+            Some(app) => dfu::Partition::Softdevice(app.flash()),
+            None => dfu::Partition::Nvmc(embassy_nrf::nvmc::Nvmc::new(p.NVMC)),
+        };
+        #[cfg(not(feature = "ble"))]
+        let partition = dfu::Partition::Nvmc(embassy_nrf::nvmc::Nvmc::new(p.NVMC));
+
+        static PARTITION: StaticCell<dfu::SharedPartition> = StaticCell::new();
+        let partition = &*PARTITION.init(embassy_sync::mutex::Mutex::new(partition));
+
+        s.spawn(storage::storage_task(partition)).unwrap();
 
-        static APP: Forever<App> = Forever::new();
-        let app = APP.put(App::enable(s, "Neopixel"));
-        app.mount(s, &ap);
+        static FIRMWARE: ActorContext<dfu::FirmwareManager, 4> = ActorContext::new();
+        FIRMWARE.mount(s, dfu::FirmwareManager::new(partition))
+    };
+
+    // Launch the thermal protection. Once enabled, the softdevice owns the TEMP peripheral.
+    #[cfg(feature = "thermal")]
+    {
+        use embassy_nrf::temp::Temp;
+        #[cfg(feature = "ble")]
+        let die = match app {
+            Some(app) => thermal::DieSensor::Softdevice(app.softdevice()),
+            None => {
+                thermal::DieSensor::Temp(Temp::new(p.TEMP, embassy_nrf::interrupt::take!(TEMP)))
+            }
+        };
+        #[cfg(not(feature = "ble"))]
+        let die = thermal::DieSensor::Temp(Temp::new(p.TEMP, embassy_nrf::interrupt::take!(TEMP)));
+
+        #[cfg(feature = "ntc")]
+        let saadc = {
+            use embassy_nrf::saadc::{ChannelConfig, Config, Gain, Reference, Resolution, Saadc};
+            // the readings are converted at 12 bits
+            let mut config = Config::default();
+            config.resolution = Resolution::_12BIT;
+            let mut channel = ChannelConfig::single_ended(p.P0_05);
+            channel.reference = Reference::VDD1_4;
+            channel.gain = Gain::GAIN1_4;
+            Saadc::new(
+                p.SAADC,
+                embassy_nrf::interrupt::take!(SAADC),
+                config,
+                [channel],
+            )
+        };
+
+        static THERMAL: ActorContext<thermal::ThermalGuard> = ActorContext::new();
+        THERMAL.mount(
+            s,
+            thermal::ThermalGuard::new(
+                die,
+                #[cfg(feature = "ntc")]
+                saadc,
+                ap.runner.clone(),
+                THERMAL_LIMITS,
+            ),
+        );
+    }
+
+    // Launch the BLE services
+    #[cfg(feature = "ble")]
+    if let Some(app) = app {
+        app.mount(s, &ap, firmware);
     }
 
     // Launch watchdog
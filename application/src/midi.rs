@@ -0,0 +1,280 @@
+//! MIDI messages, as received from a USB MIDI device, and their mapping onto the controller.
+//!
+//! Notes flash a color at a position on the strip: the pitch selects the position, the pitch
+//! class the color and the velocity the intensity. Control changes and program changes adjust
+//! the brightness, the speed and the mode. All channels are accepted.
+
+use crate::{
+    flash::Trigger,
+    pattern::ModeDiscriminants,
+    runner::{Msg, Step},
+};
+use neopixel_logic::rgb::Rgb8;
+
+/// Lowest note (C2), mapped to the first pixel
+const LOWEST_NOTE: u8 = 36;
+/// Highest note (C7), mapped to the last pixel
+const HIGHEST_NOTE: u8 = 96;
+
+/// Colors of the pitch classes, starting with C, going round the color wheel.
+const NOTE_COLORS: [Rgb8; 12] = [
+    Rgb8::new(0xFF, 0x00, 0x00),
+    Rgb8::new(0xFF, 0x80, 0x00),
+    Rgb8::new(0xFF, 0xFF, 0x00),
+    Rgb8::new(0x80, 0xFF, 0x00),
+    Rgb8::new(0x00, 0xFF, 0x00),
+    Rgb8::new(0x00, 0xFF, 0x80),
+    Rgb8::new(0x00, 0xFF, 0xFF),
+    Rgb8::new(0x00, 0x80, 0xFF),
+    Rgb8::new(0x00, 0x00, 0xFF),
+    Rgb8::new(0x80, 0x00, 0xFF),
+    Rgb8::new(0xFF, 0x00, 0xFF),
+    Rgb8::new(0xFF, 0x00, 0x80),
+];
+
+/// Sets the speed (modulation wheel)
+const CC_SPEED: u8 = 1;
+/// Sets the brightness (channel volume)
+const CC_BRIGHTNESS: u8 = 7;
+/// Selects the mode by its identifier, values without a mode are ignored
+const CC_MODE: u8 = 16;
+/// Adjusts the brightness, from a relative encoder
+const CC_BRIGHTNESS_RELATIVE: u8 = 17;
+/// Adjusts the speed, from a relative encoder
+const CC_SPEED_RELATIVE: u8 = 18;
+/// Steps through the modes, from a relative encoder
+const CC_MODE_RELATIVE: u8 = 19;
+
+/// Code index numbers of the USB MIDI event packets we handle
+const CIN_NOTE_OFF: u8 = 0x8;
+const CIN_NOTE_ON: u8 = 0x9;
+const CIN_CONTROL_CHANGE: u8 = 0xB;
+const CIN_PROGRAM_CHANGE: u8 = 0xC;
+
+/// A channel voice message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+    ControlChange { channel: u8, control: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+}
+
+impl Message {
+    /// Parse a USB MIDI event packet. Other than the handled channel voice messages are ignored.
+    pub fn from_packet(packet: &[u8]) -> Option<Self> {
+        let (header, status, data1, data2) = match *packet {
+            [header, status, data1, data2] => (header, status, data1, data2),
+            _ => return None,
+        };
+
+        let channel = status & 0x0F;
+        let (data1, data2) = (data1 & 0x7F, data2 & 0x7F);
+
+        // the code index number must match the status
+        if header & 0x0F != status >> 4 {
+            return None;
+        }
+
+        match header & 0x0F {
+            CIN_NOTE_ON if data2 > 0 => Some(Self::NoteOn {
+                channel,
+                note: data1,
+                velocity: data2,
+            }),
+            // a note on, without velocity, is a note off
+            CIN_NOTE_ON | CIN_NOTE_OFF => Some(Self::NoteOff {
+                channel,
+                note: data1,
+            }),
+            CIN_CONTROL_CHANGE => Some(Self::ControlChange {
+                channel,
+                control: data1,
+                value: data2,
+            }),
+            CIN_PROGRAM_CHANGE => Some(Self::ProgramChange {
+                channel,
+                program: data1,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parse all event packets of a USB transfer.
+    pub fn from_packets(data: &[u8]) -> impl Iterator<Item = Self> + '_ {
+        data.chunks_exact(4).filter_map(Self::from_packet)
+    }
+
+    /// Translate into a message for the runner, if the message is mapped.
+    pub fn to_msg(&self) -> Option<Msg> {
+        match *self {
+            Self::NoteOn { note, velocity, .. } => Some(Msg::Flash(Trigger {
+                position: note_position(note),
+                color: note_color(note, velocity),
+            })),
+            Self::NoteOff { .. } => None,
+            Self::ControlChange { control, value, .. } => control_change(control, value),
+            Self::ProgramChange { program, .. } => {
+                ModeDiscriminants::from_id(program).map(Msg::StreamMode)
+            }
+        }
+    }
+}
+
+/// Position of a note on the strip, notes outside the range are mapped to the ends.
+fn note_position(note: u8) -> u8 {
+    let note = note.clamp(LOWEST_NOTE, HIGHEST_NOTE) - LOWEST_NOTE;
+    (note as usize * u8::MAX as usize / (HIGHEST_NOTE - LOWEST_NOTE) as usize) as u8
+}
+
+fn note_color(note: u8, velocity: u8) -> Rgb8 {
+    let color = NOTE_COLORS[note as usize % NOTE_COLORS.len()];
+    let level = scale(velocity) as u16;
+    let scale = |c: u8| (c as u16 * level / u8::MAX as u16) as u8;
+    Rgb8::new(scale(color.r), scale(color.g), scale(color.b))
+}
+
+fn control_change(control: u8, value: u8) -> Option<Msg> {
+    match control {
+        CC_SPEED => Some(Msg::SetSpeed(scale(value))),
+        CC_BRIGHTNESS => Some(Msg::SetBrightness(scale(value))),
+        CC_MODE => ModeDiscriminants::from_id(value).map(Msg::StreamMode),
+        CC_BRIGHTNESS_RELATIVE => relative(value, Step::Lighter, Step::Darker),
+        CC_SPEED_RELATIVE => relative(value, Step::Faster, Step::Slower),
+        CC_MODE_RELATIVE => relative(value, Step::Next, Step::Prev),
+        _ => None,
+    }
+}
+
+/// Map a relative encoder value (two's complement, `1` to `63` up, `65` to `127` down).
+fn relative(value: u8, up: Step, down: Step) -> Option<Msg> {
+    match value {
+        1..=63 => Some(Msg::StreamStep(up)),
+        65..=127 => Some(Msg::StreamStep(down)),
+        _ => None,
+    }
+}
+
+/// Scale a 7 bit value to the full range of `u8`.
+fn scale(value: u8) -> u8 {
+    let value = value & 0x7F;
+    (value << 1) | (value >> 6)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_packets() {
+        let data = [
+            0x09, 0x90, 60, 100, // note on, channel 1
+            0x08, 0x81, 60, 0, // note off, channel 2
+            0x09, 0x90, 62, 0, // note on, without velocity
+            0x0B, 0xB0, 7, 127, // control change
+            0x0C, 0xC3, 2, 0, // program change, channel 4
+            0x0F, 0xF8, 0, 0, // clock, ignored
+        ];
+
+        let mut messages = Message::from_packets(&data);
+        assert_eq!(
+            messages.next(),
+            Some(Message::NoteOn {
+                channel: 0,
+                note: 60,
+                velocity: 100
+            })
+        );
+        assert_eq!(
+            messages.next(),
+            Some(Message::NoteOff {
+                channel: 1,
+                note: 60
+            })
+        );
+        assert_eq!(
+            messages.next(),
+            Some(Message::NoteOff {
+                channel: 0,
+                note: 62
+            })
+        );
+        assert_eq!(
+            messages.next(),
+            Some(Message::ControlChange {
+                channel: 0,
+                control: 7,
+                value: 127
+            })
+        );
+        assert_eq!(
+            messages.next(),
+            Some(Message::ProgramChange {
+                channel: 3,
+                program: 2
+            })
+        );
+        assert_eq!(messages.next(), None);
+    }
+
+    #[test]
+    fn reject_mismatched_header() {
+        assert_eq!(Message::from_packet(&[0x09, 0xB0, 7, 127]), None);
+        assert_eq!(Message::from_packet(&[0x09, 0x90, 60]), None);
+    }
+
+    #[test]
+    fn map_notes() {
+        assert_eq!(note_position(LOWEST_NOTE), 0);
+        assert_eq!(note_position(0), 0);
+        assert_eq!(note_position(HIGHEST_NOTE), u8::MAX);
+        assert_eq!(note_position(127), u8::MAX);
+        assert_eq!(note_position(66), 127);
+
+        // C, full velocity
+        assert_eq!(note_color(60, 127), Rgb8::new(0xFF, 0x00, 0x00));
+        // G, half velocity
+        assert_eq!(note_color(67, 64), Rgb8::new(0x00, 0x40, 0x81));
+
+        assert!(matches!(
+            (Message::NoteOn {
+                channel: 0,
+                note: LOWEST_NOTE,
+                velocity: 127
+            })
+            .to_msg(),
+            Some(Msg::Flash(Trigger { position: 0, .. }))
+        ));
+    }
+
+    #[test]
+    fn map_controls() {
+        assert!(matches!(
+            control_change(CC_BRIGHTNESS, 127),
+            Some(Msg::SetBrightness(255))
+        ));
+        assert!(matches!(
+            control_change(CC_SPEED, 0),
+            Some(Msg::SetSpeed(0))
+        ));
+        assert!(matches!(
+            control_change(CC_MODE, 0),
+            Some(Msg::StreamMode(ModeDiscriminants::Off))
+        ));
+        assert!(matches!(
+            control_change(CC_MODE, 1),
+            Some(Msg::StreamMode(ModeDiscriminants::Fire))
+        ));
+        assert!(control_change(CC_MODE, 127).is_none());
+        assert!(matches!(
+            control_change(CC_BRIGHTNESS_RELATIVE, 1),
+            Some(Msg::StreamStep(Step::Lighter))
+        ));
+        assert!(matches!(
+            control_change(CC_SPEED_RELATIVE, 127),
+            Some(Msg::StreamStep(Step::Slower))
+        ));
+        assert!(control_change(CC_MODE_RELATIVE, 0).is_none());
+        assert!(control_change(64, 127).is_none());
+    }
+}
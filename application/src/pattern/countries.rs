@@ -1,28 +1,24 @@
-use crate::pattern::YELLOW;
-use crate::MyNeoPixel;
-use drogue_device::drivers::led::neopixel::{
-    filter::Filter,
-    rgb::{Rgb8, BLACK, BLUE, RED},
-};
+use crate::pattern::palettes::{Gradient, GERMANY, UKRAINE};
+use neopixel_logic::rgb::Rgb8;
+
+/// The color of the stripe of a pixel, two pixels wide, taken from the evenly spaced stops of the
+/// flag.
+fn stripe(flag: &Gradient, stripes: usize, i: usize) -> Rgb8 {
+    flag.sample(((i >> 1) % stripes * 255 / (stripes - 1)) as u8)
+}
 
 pub struct UA<const N: usize>;
 
 impl<const N: usize> UA<N> {
     pub fn new(pixels: &mut [Rgb8; N]) -> Self {
-        for i in 0..N {
-            pixels[i] = if (i >> 1) % 2 == 0 { BLUE } else { YELLOW };
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = stripe(&UKRAINE, 2, i);
         }
         Self
     }
 
-    pub async fn tick<F: Filter<Rgb8, 3>>(
-        &mut self,
-        pixels: &mut [Rgb8; N],
-        neopixel: &mut MyNeoPixel<N>,
-        f: &mut F,
-    ) {
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N]) {
         pixels.rotate_right(1);
-        neopixel.set_with_filter(&pixels, f).await.ok();
     }
 }
 
@@ -30,24 +26,13 @@ pub struct DE<const N: usize>;
 
 impl<const N: usize> DE<N> {
     pub fn new(pixels: &mut [Rgb8; N]) -> Self {
-        for i in 0..N {
-            pixels[i] = match (i >> 1) % 3 {
-                0 => BLACK,
-                1 => RED,
-                2 => YELLOW,
-                _ => BLACK,
-            };
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = stripe(&GERMANY, 3, i);
         }
         Self
     }
 
-    pub async fn tick<F: Filter<Rgb8, 3>>(
-        &mut self,
-        pixels: &mut [Rgb8; N],
-        neopixel: &mut MyNeoPixel<N>,
-        f: &mut F,
-    ) {
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N]) {
         pixels.rotate_left(1);
-        neopixel.set_with_filter(&pixels, f).await.ok();
     }
 }
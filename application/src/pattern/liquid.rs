@@ -0,0 +1,60 @@
+use crate::pattern::{scale, Context};
+use neopixel_logic::rgb::{Rgb8, BLACK};
+
+/// Acceleration of the liquid, when fully tilted, in pixels/s²
+const ACCELERATION: f32 = 80.0;
+/// Loss of velocity per second
+const FRICTION: f32 = 1.5;
+/// Velocity kept, when bouncing off the ends
+const BOUNCE: f32 = 0.3;
+
+/// A mass of liquid in the selected color, flowing along the strip, following the tilt.
+pub struct Liquid<const N: usize> {
+    /// Position of the center, in pixels
+    position: f32,
+    /// Velocity, in pixels/s
+    velocity: f32,
+}
+
+impl<const N: usize> Liquid<N> {
+    /// Length of the liquid, in pixels
+    const LENGTH: f32 = N as f32 / 4.0;
+
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            position: N as f32 / 2.0,
+            velocity: 0.0,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        self.flow(ctx.tilt, ctx.delta.as_millis() as f32 / 1000.0);
+
+        let half = Self::LENGTH / 2.0;
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            // fill the pixels covered by the liquid, partially covered ones partially
+            let center = i as f32 + 0.5;
+            let cover = (half + 0.5 - libm::fabsf(center - self.position)).clamp(0.0, 1.0);
+            *pixel = if cover > 0.0 {
+                scale(ctx.color, cover)
+            } else {
+                BLACK
+            };
+        }
+    }
+
+    fn flow(&mut self, tilt: i8, dt: f32) {
+        self.velocity += tilt as f32 / 127.0 * ACCELERATION * dt;
+        self.velocity -= self.velocity * (FRICTION * dt).min(1.0);
+        self.position += self.velocity * dt;
+
+        let (min, max) = (Self::LENGTH / 2.0, N as f32 - Self::LENGTH / 2.0);
+        if self.position < min {
+            self.position = min;
+            self.velocity = -self.velocity * BOUNCE;
+        } else if self.position > max {
+            self.position = max;
+            self.velocity = -self.velocity * BOUNCE;
+        }
+    }
+}
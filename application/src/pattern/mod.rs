@@ -1,38 +1,71 @@
 mod countries;
-mod fire;
+#[cfg(feature = "accel")]
+mod liquid;
 mod rainbow;
 
 use crate::{
+    board::{AUTOMATON_RESEED, AUTOMATON_WRAP, CLOCK_CLOCKWISE, CLOCK_OFFSET},
     pattern::{
         // countries::{DE, UA},
-        fire::Fire,
+        palettes::Palette,
         rainbow::{Rainbow, RainbowPart},
     },
-    MyNeoPixel,
 };
-use drogue_device::drivers::led::neopixel::{filter::Filter, rgb::Rgb8};
-use embassy_time::Duration;
-use strum::{EnumDiscriminants, EnumIter, IntoEnumIterator};
-
-pub const YELLOW: Rgb8 = Rgb8::new(0xFF, 0xFF, 0x00);
+use neopixel_logic::pattern::{
+    automaton::{Automaton, History, LIFE},
+    balls::BouncingBalls,
+    breathing::{Breathing, Pulse},
+    chase::{ColorWipe, Larson, RunningLights, TheaterChase},
+    clock::Clock,
+    comet::Comet,
+    fire::Fire,
+    fireworks::Fireworks,
+    plasma::{Aurora, LavaLamp, Plasma},
+    solid::{Gradient, Solid},
+    twinkle::{Confetti, Twinkle},
+};
+use neopixel_logic::rgb::Rgb8;
+use neopixel_protocol::ModeId;
+use strum::{EnumCount, EnumDiscriminants, EnumIter, EnumString, IntoEnumIterator, IntoStaticStr};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Context {
-    /// the speed configuration
-    pub speed: u8,
-    /// the time since the last run (could be zero)
-    pub delta: Duration,
-}
+pub use neopixel_logic::pattern::{add, palettes, scale, twinkle::Glitter, Context, Steps};
+pub use rainbow::IntoPixel;
 
 #[derive(EnumDiscriminants, strum::IntoStaticStr)]
-#[strum_discriminants(derive(EnumIter))]
+#[strum_discriminants(
+    derive(EnumCount, EnumIter, EnumString, IntoStaticStr),
+    strum(serialize_all = "kebab-case")
+)]
 pub enum Mode<const N: usize> {
     Off,
     //UA(UA<N>),
     //DE(DE<N>),
-    Fire(Fire<N>),
+    Fire(Fire<N, 55, 120>),
     Rainbow(Rainbow<N>),
     RainbowPart(RainbowPart<N, 200>),
+    Solid(Solid<N>),
+    /// Follows the tilt, measured by the accelerometer
+    #[cfg(feature = "accel")]
+    Liquid(liquid::Liquid<N>),
+    Comet(Comet<N>),
+    Fireworks(Fireworks<N>),
+    BouncingBalls(BouncingBalls<N>),
+    Twinkle(Twinkle<N, 8, 800>),
+    Confetti(Confetti<N, 15, 1000>),
+    Plasma(Plasma<N, 24, 96>),
+    LavaLamp(LavaLamp<N, 12, 32>),
+    Aurora(Aurora<N, 48, 128>),
+    TheaterChase(TheaterChase<N, 3>),
+    Larson(Larson<N>),
+    RunningLights(RunningLights<N, 2>),
+    ColorWipe(ColorWipe<N>),
+    Breathing(Breathing<N, 4000>),
+    Pulse(Pulse<N, 60>),
+    Gradient(Gradient<N>),
+    Rule30(History<N, 30, AUTOMATON_WRAP, AUTOMATON_RESEED>),
+    Rule110(Automaton<N, 110, AUTOMATON_WRAP, AUTOMATON_RESEED>),
+    Life(Automaton<N, LIFE, AUTOMATON_WRAP, AUTOMATON_RESEED>),
+    Clock(Clock<N, CLOCK_OFFSET, CLOCK_CLOCKWISE>),
 }
 
 impl ModeDiscriminants {
@@ -61,7 +94,74 @@ impl ModeDiscriminants {
             }
         }
 
-        next.unwrap_or(Self::RainbowPart)
+        next.or_else(|| ModeDiscriminants::iter().next_back())
+            .unwrap_or(Self::Off)
+    }
+
+    /// The identifier of the mode, which doesn't change when modes are added.
+    pub fn id(&self) -> ModeId {
+        match self {
+            Self::Off => ModeId::Off,
+            Self::Fire => ModeId::Fire,
+            Self::Rainbow => ModeId::Rainbow,
+            Self::RainbowPart => ModeId::RainbowPart,
+            Self::Solid => ModeId::Solid,
+            #[cfg(feature = "accel")]
+            Self::Liquid => ModeId::Liquid,
+            Self::Comet => ModeId::Comet,
+            Self::Fireworks => ModeId::Fireworks,
+            Self::BouncingBalls => ModeId::BouncingBalls,
+            Self::Twinkle => ModeId::Twinkle,
+            Self::Confetti => ModeId::Confetti,
+            Self::Plasma => ModeId::Plasma,
+            Self::LavaLamp => ModeId::LavaLamp,
+            Self::Aurora => ModeId::Aurora,
+            Self::TheaterChase => ModeId::TheaterChase,
+            Self::Larson => ModeId::Larson,
+            Self::RunningLights => ModeId::RunningLights,
+            Self::ColorWipe => ModeId::ColorWipe,
+            Self::Breathing => ModeId::Breathing,
+            Self::Pulse => ModeId::Pulse,
+            Self::Gradient => ModeId::Gradient,
+            Self::Rule30 => ModeId::Rule30,
+            Self::Rule110 => ModeId::Rule110,
+            Self::Life => ModeId::Life,
+            Self::Clock => ModeId::Clock,
+        }
+    }
+
+    /// The mode of an identifier, if the firmware has it.
+    pub fn from_id(id: u8) -> Option<Self> {
+        let id = ModeId::from_u8(id)?;
+        Self::iter().find(|mode| mode.id() == id)
+    }
+
+    /// The palette of the mode, unless another one was selected.
+    pub fn palette(&self) -> Palette {
+        match self {
+            Self::Fire => Palette::Heat,
+            Self::Plasma => Palette::Party,
+            Self::LavaLamp => Palette::Lava,
+            Self::Aurora => Palette::Ocean,
+            _ => Palette::Rainbow,
+        }
+    }
+
+    /// If the mode shows the selected color.
+    pub fn uses_color(&self) -> bool {
+        match self {
+            #[cfg(feature = "accel")]
+            Self::Liquid => true,
+            Self::Solid
+            | Self::Gradient
+            | Self::Twinkle
+            | Self::TheaterChase
+            | Self::Larson
+            | Self::RunningLights
+            | Self::Breathing
+            | Self::Pulse => true,
+            _ => false,
+        }
     }
 
     pub fn new<const N: usize>(&self, pixels: &mut [Rgb8; N]) -> Mode<N> {
@@ -72,25 +172,65 @@ impl ModeDiscriminants {
             Self::Rainbow => Mode::Rainbow(Rainbow::new(pixels)),
             Self::RainbowPart => Mode::RainbowPart(RainbowPart::new(pixels)),
             Self::Fire => Mode::Fire(Fire::new(pixels)),
+            Self::Solid => Mode::Solid(Solid::new(pixels)),
+            #[cfg(feature = "accel")]
+            Self::Liquid => Mode::Liquid(liquid::Liquid::new(pixels)),
+            Self::Comet => Mode::Comet(Comet::new(pixels)),
+            Self::Fireworks => Mode::Fireworks(Fireworks::new(pixels)),
+            Self::BouncingBalls => Mode::BouncingBalls(BouncingBalls::new(pixels)),
+            Self::Twinkle => Mode::Twinkle(Twinkle::new(pixels)),
+            Self::Confetti => Mode::Confetti(Confetti::new(pixels)),
+            Self::Plasma => Mode::Plasma(Plasma::new(pixels)),
+            Self::LavaLamp => Mode::LavaLamp(LavaLamp::new(pixels)),
+            Self::Aurora => Mode::Aurora(Aurora::new(pixels)),
+            Self::TheaterChase => Mode::TheaterChase(TheaterChase::new(pixels)),
+            Self::Larson => Mode::Larson(Larson::new(pixels)),
+            Self::RunningLights => Mode::RunningLights(RunningLights::new(pixels)),
+            Self::ColorWipe => Mode::ColorWipe(ColorWipe::new(pixels)),
+            Self::Breathing => Mode::Breathing(Breathing::new(pixels)),
+            Self::Pulse => Mode::Pulse(Pulse::new(pixels)),
+            Self::Gradient => Mode::Gradient(Gradient::new(pixels)),
+            Self::Rule30 => Mode::Rule30(History::new(pixels)),
+            Self::Rule110 => Mode::Rule110(Automaton::new(pixels)),
+            Self::Life => Mode::Life(Automaton::new(pixels)),
+            Self::Clock => Mode::Clock(Clock::new(pixels)),
         }
     }
 }
 
 impl<const N: usize> Mode<N> {
-    pub async fn tick<F: Filter<Rgb8, 3>>(
-        &mut self,
-        pixels: &mut [Rgb8; N],
-        neopixel: &mut MyNeoPixel<N>,
-        ctx: Context,
-        f: &mut F,
-    ) {
+    /// Render the next frame of the mode into the pixels, without writing them to the strip.
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
         match self {
+            // keep the pixels of the previous mode, for the next one
             Self::Off => {}
-            //Self::UA(pattern) => pattern.tick(pixels, neopixel, f).await,
-            //Self::DE(pattern) => pattern.tick(pixels, neopixel, f).await,
-            Self::Rainbow(pattern) => pattern.tick(pixels, neopixel, ctx, f).await,
-            Self::RainbowPart(pattern) => pattern.tick(pixels, neopixel, ctx, f).await,
-            Self::Fire(pattern) => pattern.tick(pixels, neopixel, ctx, f).await,
+            //Self::UA(pattern) => pattern.tick(pixels),
+            //Self::DE(pattern) => pattern.tick(pixels),
+            Self::Rainbow(pattern) => pattern.tick(pixels, ctx),
+            Self::RainbowPart(pattern) => pattern.tick(pixels, ctx),
+            Self::Fire(pattern) => pattern.tick(pixels, ctx),
+            Self::Solid(pattern) => pattern.tick(pixels, ctx),
+            #[cfg(feature = "accel")]
+            Self::Liquid(pattern) => pattern.tick(pixels, ctx),
+            Self::Comet(pattern) => pattern.tick(pixels, ctx),
+            Self::Fireworks(pattern) => pattern.tick(pixels, ctx),
+            Self::BouncingBalls(pattern) => pattern.tick(pixels, ctx),
+            Self::Twinkle(pattern) => pattern.tick(pixels, ctx),
+            Self::Confetti(pattern) => pattern.tick(pixels, ctx),
+            Self::Plasma(pattern) => pattern.tick(pixels, ctx),
+            Self::LavaLamp(pattern) => pattern.tick(pixels, ctx),
+            Self::Aurora(pattern) => pattern.tick(pixels, ctx),
+            Self::TheaterChase(pattern) => pattern.tick(pixels, ctx),
+            Self::Larson(pattern) => pattern.tick(pixels, ctx),
+            Self::RunningLights(pattern) => pattern.tick(pixels, ctx),
+            Self::ColorWipe(pattern) => pattern.tick(pixels, ctx),
+            Self::Breathing(pattern) => pattern.tick(pixels, ctx),
+            Self::Pulse(pattern) => pattern.tick(pixels, ctx),
+            Self::Gradient(pattern) => pattern.tick(pixels, ctx),
+            Self::Rule30(pattern) => pattern.tick(pixels, ctx),
+            Self::Rule110(pattern) => pattern.tick(pixels, ctx),
+            Self::Life(pattern) => pattern.tick(pixels, ctx),
+            Self::Clock(pattern) => pattern.tick(pixels, ctx),
         }
     }
 }
@@ -1,8 +1,8 @@
-use crate::pattern::Context;
-use crate::MyNeoPixel;
+use crate::pattern::{palettes::Palette, Context};
 use core::num::NonZeroUsize;
-use drogue_device::drivers::led::neopixel::{filter::Filter, rgb::Rgb8};
 use embassy_time::{Duration, Instant};
+use neopixel_logic::rgb::Rgb8;
+use neopixel_protocol::Color;
 use palette::rgb::Rgb;
 use palette::{Hsv, IntoColor};
 
@@ -27,37 +27,46 @@ impl IntoPixel for Hsv {
     }
 }
 
+impl IntoPixel for Color {
+    fn into_pixel(self) -> Rgb8 {
+        let (r, g, b) = self.rgb();
+        Rgb8::new(r, g, b)
+    }
+}
+
 pub struct Rainbow<const N: usize> {
     last_shift: Instant,
+    palette: Palette,
 }
 
 impl<const N: usize> Rainbow<N> {
     pub fn new(pixels: &mut [Rgb8; N]) -> Self {
-        for i in 0..N {
-            let v = (360f32 / (N as f32)) * (i as f32);
-
-            let color = Hsv::new(v, 1.0, 1.0);
-            let color: Rgb = color.into_color();
-            pixels[i] = color.into_pixel();
-        }
+        let palette = Palette::Rainbow;
+        Self::fill(pixels, palette);
 
         Self {
             last_shift: Instant::now(),
+            palette,
         }
     }
 
-    pub async fn tick<F: Filter<Rgb8, 3>>(
-        &mut self,
-        pixels: &mut [Rgb8; N],
-        neopixel: &mut MyNeoPixel<N>,
-        ctx: Context,
-        f: &mut F,
-    ) {
+    /// Spread the palette over the strip.
+    fn fill(pixels: &mut [Rgb8; N], palette: Palette) {
+        let gradient = palette.gradient();
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = gradient.sample((i * 256 / N) as u8);
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        if ctx.palette != self.palette {
+            self.palette = ctx.palette;
+            Self::fill(pixels, self.palette);
+        }
+
         if let Some(num) = self.need_update(ctx) {
             // shift
             pixels.rotate_left(num.into());
-            // and render
-            neopixel.set_with_filter(&pixels, f).await.ok();
         }
     }
 
@@ -93,26 +102,19 @@ impl<const N: usize, const MAX: usize> RainbowPart<N, MAX> {
         Self
     }
 
-    pub async fn tick<F: Filter<Rgb8, 3>>(
-        &mut self,
-        pixels: &mut [Rgb8; N],
-        neopixel: &mut MyNeoPixel<N>,
-        ctx: Context,
-        f: &mut F,
-    ) {
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
         let now = (Instant::now().as_millis()) as f32;
 
         let add = (360f32 / 2.0) / (MAX as f32);
         let offset = now / ctx.speed as f32 / 10f32;
 
+        let gradient = ctx.palette.gradient();
         for i in 0..N {
+            // the position on the palette, in degrees
             let v = add * i as f32 + offset;
 
-            let color = Hsv::new(v, 1.0, 1.0);
-            let color: Rgb = color.into_color();
-            pixels[i] = color.into_pixel();
+            let index = (v * 256f32 / 360f32) as u64 % 256;
+            pixels[i] = gradient.sample(index as u8);
         }
-
-        neopixel.set_with_filter(&pixels, f).await.ok();
     }
 }
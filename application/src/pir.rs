@@ -0,0 +1,43 @@
+//! Motion sensor, using a PIR module with a digital output (like the HC-SR501).
+
+use crate::runner::Msg;
+use ector::{Actor, Address, Inbox};
+use embassy_futures::select::{select, Either};
+use embassy_nrf::gpio::{AnyPin, Input};
+use embassy_time::{Duration, Timer};
+
+/// Interval of reporting motion, while the output of the sensor stays high
+const REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct MotionSensor {
+    pin: Input<'static, AnyPin>,
+    runner: Address<Msg>,
+}
+
+impl MotionSensor {
+    pub fn new(pin: Input<'static, AnyPin>, runner: Address<Msg>) -> Self {
+        Self { pin, runner }
+    }
+}
+
+#[ector::actor]
+impl Actor for MotionSensor {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        loop {
+            self.pin.wait_for_high().await;
+            defmt::debug!("Motion detected");
+            loop {
+                self.runner.try_notify(Msg::Motion).ok();
+                match select(self.pin.wait_for_low(), Timer::after(REPEAT_INTERVAL)).await {
+                    Either::First(_) => break,
+                    Either::Second(_) => {}
+                }
+            }
+        }
+    }
+}
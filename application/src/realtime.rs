@@ -0,0 +1,30 @@
+use crate::NUM_LEDS;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use neopixel_logic::rgb::{Rgb8, BLACK};
+
+/// The most recent frame, received from an external source.
+static FRAME: Mutex<CriticalSectionRawMutex, RefCell<[Rgb8; NUM_LEDS]>> =
+    Mutex::new(RefCell::new([BLACK; NUM_LEDS]));
+
+/// Store a new frame. The runner must be notified with [`crate::runner::Msg::Frame`] to show it.
+pub fn update(pixels: &[Rgb8]) {
+    FRAME.lock(|frame| {
+        let mut frame = frame.borrow_mut();
+        for (target, pixel) in frame
+            .iter_mut()
+            .zip(pixels.iter().chain(core::iter::repeat(&BLACK)))
+        {
+            *target = *pixel;
+        }
+    });
+}
+
+/// Copy the most recent frame.
+pub fn read(pixels: &mut [Rgb8]) {
+    FRAME.lock(|frame| {
+        for (target, pixel) in pixels.iter_mut().zip(frame.borrow().iter()) {
+            *target = *pixel;
+        }
+    });
+}
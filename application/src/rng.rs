@@ -0,0 +1,16 @@
+//! Seeding the random number generator of the effects, in the `neopixel-logic` crate.
+
+pub use neopixel_logic::rng::Rng;
+
+/// Read a seed from the hardware random number generator.
+///
+/// This must be done before enabling the softdevice, which takes over the peripheral.
+pub async fn hardware_seed(
+    rng: embassy_nrf::peripherals::RNG,
+    irq: embassy_nrf::interrupt::RNG,
+) -> u32 {
+    let mut rng = embassy_nrf::rng::Rng::new(rng, irq);
+    let mut seed = [0; 4];
+    rng.fill_bytes(&mut seed).await;
+    u32::from_le_bytes(seed)
+}
@@ -1,20 +1,24 @@
 use crate::{
     control::{Action, ControlEvent, Event},
-    pattern::ModeDiscriminants,
-    Controller, MyNeoPixel,
+    easing::Easing,
+    flash::Trigger,
+    pattern::{palettes::Palette, ModeDiscriminants},
+    realtime, status, Controller, MyNeoPixel,
 };
-use drogue_device::drivers::led::neopixel::rgb;
+use drogue_device::drivers::led::neopixel::rgb::{self, Rgb8};
 use ector::{Actor, Address, Inbox};
-use embassy_time::{Duration, Ticker};
+use embassy_time::{Duration, Ticker, Timer};
 use futures::{
     future::{select, Either},
     pin_mut, StreamExt,
 };
+use neopixel_protocol::Color;
 
 pub struct Runner<const N: usize> {
     pub neopixel: MyNeoPixel<N>,
     ticker: Ticker,
     controller: Controller<N>,
+    frame: [Rgb8; N],
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -25,20 +29,105 @@ pub enum Msg {
     Slower,
     ResetSpeed,
     SetMode(ModeDiscriminants),
+    /// Select a mode, following a stream like DMX or MIDI
+    StreamMode(ModeDiscriminants),
+    /// Step the brightness, the speed or the mode, following a stream like MIDI
+    StreamStep(Step),
+    On,
+    Off,
+    Toggle,
+    /// Select a color, switching to a mode showing it
+    SetColor(Color),
+    /// Select the second color, switching to the gradient mode
+    SetSecondColor(Color),
+    /// Turn the hue of the color being picked, around the color wheel
+    NextHue,
+    /// Lower the saturation of the color being picked, wrapping around to full saturation
+    NextSaturation,
+    /// Select the palette of the current mode
+    SetPalette(Palette),
+    /// Cycle through the palettes, for the current mode
+    NextPalette,
+    /// Select the easing curve, of the modes using one
+    SetEasing(Easing),
     StartSleep(Duration),
     StopSleep,
     SleepConfig(Event),
     Lighter,
     Darker,
     ResetBrightness,
+    /// Set the brightness, from a continuous control like a knob, a fader or a MIDI controller
+    SetBrightness(u8),
+    /// The brightness, following the ambient light
+    Ambient(u8),
+    AutoBrightness(bool),
+    /// Set the speed, from a continuous control like a knob, a fader or a MIDI controller
+    SetSpeed(u8),
+    /// Flash a color, on top of the current mode
+    Flash(Trigger),
+    /// Blink the whole strip in a color, on top of the current mode
+    Notify(Rgb8),
+    /// Flash random pixels in random colors, on top of the current mode
+    Sparkle,
+    /// Glitter on top of the current mode, or not
+    Glitter(bool),
+    /// The tilt along the strip, from `-127` (first pixel down) to `127` (last pixel down)
+    Tilt(i8),
+    /// Derate the brightness to a level, due to the temperature
+    Thermal(u8),
+    /// Motion was detected
+    Motion,
+    /// Time without motion, after which the lights are switched off, `None` to disable
+    MotionTimeout(Option<Duration>),
+    /// A new frame is available from [`realtime`]
+    Frame,
+}
+
+/// A step of a relative control, like a MIDI encoder.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    Lighter,
+    Darker,
+    Faster,
+    Slower,
+    Next,
+    Prev,
+}
+
+impl Msg {
+    /// If the message controls the lights manually, rather than from a sensor or stream.
+    ///
+    /// Continuous controls report their position when started, and DMX and MIDI are sent
+    /// continuously, so setting the brightness or the speed doesn't count as manual control.
+    fn is_manual(&self) -> bool {
+        !matches!(
+            self,
+            Msg::StreamMode(_)
+                | Msg::StreamStep(_)
+                | Msg::SetBrightness(_)
+                | Msg::SetSpeed(_)
+                | Msg::Ambient(_)
+                | Msg::Thermal(_)
+                | Msg::Motion
+                | Msg::MotionTimeout(_)
+                | Msg::Flash(_)
+                | Msg::Notify(_)
+                | Msg::Sparkle
+                | Msg::Tilt(_)
+                | Msg::Frame
+        )
+    }
 }
 
 pub enum State {
     Running,
     ConfigureSleep,
+    Realtime,
 }
 
 const TICKER_SPEED: Duration = Duration::from_millis(50);
+/// Time without frames, after which realtime mode ends
+const REALTIME_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[ector::actor]
 impl<const N: usize> Actor for Runner<N> {
@@ -48,9 +137,16 @@ impl<const N: usize> Actor for Runner<N> {
     where
         M: Inbox<Self::Message<'m>>,
     {
+        #[cfg(feature = "dfu")]
+        if let Some(colors) = crate::storage::load() {
+            self.controller.restore_colors(colors);
+        }
+
         let mut state = State::Running;
 
         loop {
+            // publish on every transition, like the end of the sleep config starting the timer
+            status::publish(self.controller.state());
             match state {
                 State::Running => {
                     state = self.running(&mut inbox).await;
@@ -58,19 +154,24 @@ impl<const N: usize> Actor for Runner<N> {
                 State::ConfigureSleep => {
                     state = self.configure_sleep(&mut inbox).await;
                 }
+                State::Realtime => {
+                    state = self.realtime(&mut inbox).await;
+                }
             }
         }
     }
 }
 
 impl<const N: usize> Runner<N> {
-    pub fn new(neopixel: MyNeoPixel<N>) -> Self {
+    /// Create a new runner, seeding the effects with random numbers.
+    pub fn new(neopixel: MyNeoPixel<N>, seed: u32) -> Self {
         let ticker = Ticker::every(TICKER_SPEED);
-        let controller = Controller::<N>::new();
+        let controller = Controller::<N>::new(seed);
         Self {
             neopixel,
             ticker,
             controller,
+            frame: [rgb::BLACK; N],
         }
     }
 
@@ -117,6 +218,8 @@ impl<const N: usize> Runner<N> {
 
     async fn running<M: Inbox<Msg>>(&mut self, inbox: &mut M) -> State {
         loop {
+            status::publish(self.controller.state());
+
             let next = inbox.next();
             let delay = self.ticker.next();
 
@@ -125,59 +228,196 @@ impl<const N: usize> Runner<N> {
 
             match select(next, delay).await {
                 Either::Left((m, _)) => {
-                    defmt::info!("Message: {}", defmt::Debug2Format(&m));
-                    match m {
-                        Msg::Next => {
-                            self.controller.next();
-                        }
-                        Msg::Prev => {
-                            self.controller.prev();
-                        }
-                        Msg::SetMode(mode) => {
-                            self.controller.mode(mode);
-                        }
-                        Msg::StartSleep(duration) => {
-                            self.controller.start_sleep(duration);
-                        }
-                        Msg::StopSleep => {
-                            self.controller.stop_sleep();
-                        }
-                        Msg::SleepConfig(Event::Reset) => {
-                            self.controller.stop_sleep();
-                        }
-                        Msg::SleepConfig(Event::Start) => {
-                            defmt::info!("Start sleep config");
-                            return State::ConfigureSleep;
-                        }
-                        Msg::SleepConfig(_) => {
-                            // ignore
-                        }
-                        Msg::Faster => {
-                            self.controller.faster();
-                        }
-                        Msg::Slower => {
-                            self.controller.slower();
-                        }
-                        Msg::ResetSpeed => {
-                            self.controller.reset_speed();
-                        }
-                        Msg::Lighter => {
-                            self.controller.lighter();
-                        }
-                        Msg::Darker => {
-                            self.controller.darker();
-                        }
-                        Msg::ResetBrightness => {
-                            self.controller.reset_brightness();
-                        }
+                    if let Some(state) = self.handle(m) {
+                        return state;
                     }
                 }
                 Either::Right((_, _d)) => {
                     self.controller.tick(&mut self.neopixel).await;
+                    #[cfg(feature = "dfu")]
+                    crate::dfu::heartbeat();
+                }
+            }
+        }
+    }
+
+    /// Show frames received from an external source, until they time out.
+    ///
+    /// The mode of the controller is kept, so that it continues once the frames stop.
+    async fn realtime<M: Inbox<Msg>>(&mut self, inbox: &mut M) -> State {
+        defmt::info!("Begin realtime");
+
+        loop {
+            realtime::read(&mut self.frame);
+            self.controller
+                .render(&mut self.neopixel, &self.frame)
+                .await;
+            #[cfg(feature = "dfu")]
+            crate::dfu::heartbeat();
+
+            let next = inbox.next();
+            let timeout = Timer::after(REALTIME_TIMEOUT);
+
+            pin_mut!(next);
+            pin_mut!(timeout);
+
+            match select(next, timeout).await {
+                Either::Left((Msg::Frame, _)) => {}
+                Either::Left((m, _)) => {
+                    // other changes apply to the mode, which continues later
+                    if let Some(state) = self.handle(m) {
+                        return state;
+                    }
+                }
+                Either::Right(_) => {
+                    defmt::info!("Realtime timeout");
+                    return State::Running;
                 }
             }
         }
     }
+
+    /// Keep the selected colors in flash, to restore them after a reset.
+    fn save_colors(&self) {
+        #[cfg(feature = "dfu")]
+        crate::storage::save(self.controller.colors());
+    }
+
+    /// Handle a message, returning the state to switch to, if required.
+    fn handle(&mut self, m: Msg) -> Option<State> {
+        defmt::info!("Message: {}", defmt::Debug2Format(&m));
+        if m.is_manual() {
+            self.controller.manual();
+        }
+        match m {
+            Msg::Next => {
+                self.controller.next();
+            }
+            Msg::Prev => {
+                self.controller.prev();
+            }
+            Msg::SetMode(mode) | Msg::StreamMode(mode) => {
+                self.controller.mode(mode);
+            }
+            Msg::StreamStep(step) => match step {
+                Step::Lighter => self.controller.lighter(),
+                Step::Darker => self.controller.darker(),
+                Step::Faster => self.controller.faster(),
+                Step::Slower => self.controller.slower(),
+                Step::Next => self.controller.next(),
+                Step::Prev => self.controller.prev(),
+            },
+            Msg::On => {
+                self.controller.on();
+            }
+            Msg::Off => {
+                self.controller.off();
+            }
+            Msg::Toggle => {
+                self.controller.toggle();
+            }
+            Msg::SetColor(color) => {
+                self.controller.color(color);
+                self.save_colors();
+            }
+            Msg::SetSecondColor(color) => {
+                self.controller.second_color(color);
+                self.save_colors();
+            }
+            Msg::NextHue => {
+                self.controller.next_hue();
+                self.save_colors();
+            }
+            Msg::NextSaturation => {
+                self.controller.next_saturation();
+                self.save_colors();
+            }
+            Msg::SetPalette(palette) => {
+                self.controller.set_palette(palette);
+            }
+            Msg::NextPalette => {
+                self.controller.next_palette();
+            }
+            Msg::SetEasing(easing) => {
+                self.controller.set_easing(easing);
+            }
+            Msg::StartSleep(duration) => {
+                self.controller.start_sleep(duration);
+            }
+            Msg::StopSleep => {
+                self.controller.stop_sleep();
+            }
+            Msg::SleepConfig(Event::Reset) => {
+                self.controller.stop_sleep();
+            }
+            Msg::SleepConfig(Event::Start) => {
+                defmt::info!("Start sleep config");
+                return Some(State::ConfigureSleep);
+            }
+            Msg::SleepConfig(_) => {
+                // ignore
+            }
+            Msg::Faster => {
+                self.controller.faster();
+            }
+            Msg::Slower => {
+                self.controller.slower();
+            }
+            Msg::ResetSpeed => {
+                self.controller.reset_speed();
+            }
+            Msg::Lighter => {
+                self.controller.lighter();
+            }
+            Msg::Darker => {
+                self.controller.darker();
+            }
+            Msg::ResetBrightness => {
+                self.controller.reset_brightness();
+            }
+            Msg::SetBrightness(brightness) => {
+                self.controller.set_brightness(brightness);
+            }
+            Msg::Ambient(brightness) => {
+                self.controller.ambient(brightness);
+            }
+            Msg::AutoBrightness(enabled) => {
+                self.controller.auto_brightness(enabled);
+            }
+            Msg::SetSpeed(speed) => {
+                self.controller.set_speed(speed);
+            }
+            Msg::Flash(trigger) => {
+                self.controller.flash(trigger);
+            }
+            Msg::Notify(color) => {
+                self.controller.notify(color);
+            }
+            Msg::Sparkle => {
+                self.controller.sparkle();
+            }
+            Msg::Glitter(enabled) => {
+                self.controller.glitter(enabled);
+            }
+            Msg::Tilt(tilt) => {
+                self.controller.tilt(tilt);
+            }
+            Msg::Thermal(level) => {
+                self.controller.thermal(level);
+            }
+            Msg::Motion => {
+                self.controller.motion();
+            }
+            Msg::MotionTimeout(timeout) => {
+                self.controller.motion_timeout(timeout);
+            }
+            Msg::Frame => {
+                return Some(State::Realtime);
+            }
+        }
+        status::publish(self.controller.state());
+        None
+    }
 }
 
 fn faster(current: u64) -> u64 {
@@ -209,7 +449,7 @@ impl TryFrom<ControlEvent> for Msg {
     fn try_from(value: ControlEvent) -> Result<Self, Self::Error> {
         defmt::info!("Control button: {0}", defmt::Debug2Format(&value));
         match value {
-            // A - pattern
+            // A - pattern and palette
             ControlEvent {
                 action: Action::A,
                 event: Event::Increase,
@@ -222,8 +462,12 @@ impl TryFrom<ControlEvent> for Msg {
                 action: Action::A,
                 event: Event::Reset,
             } => Ok(Msg::SetMode(ModeDiscriminants::Off.next())),
+            ControlEvent {
+                action: Action::A,
+                event: Event::Click,
+            } => Ok(Msg::NextPalette),
 
-            // B - speed
+            // B - speed and color
             ControlEvent {
                 action: Action::B,
                 event: Event::Increase,
@@ -236,6 +480,14 @@ impl TryFrom<ControlEvent> for Msg {
                 action: Action::B,
                 event: Event::Reset,
             } => Ok(Msg::ResetSpeed),
+            ControlEvent {
+                action: Action::B,
+                event: Event::Click,
+            } => Ok(Msg::NextHue),
+            ControlEvent {
+                action: Action::B,
+                event: Event::Hold,
+            } => Ok(Msg::NextSaturation),
 
             // C - brightness
             ControlEvent {
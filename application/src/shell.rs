@@ -0,0 +1,131 @@
+//! The commands of the console, and the messages they translate to.
+//!
+//! The grammar of the commands is in the `neopixel-console` crate.
+
+#[cfg(feature = "ir")]
+use crate::ir::Action;
+use crate::{
+    easing::Easing,
+    pattern::{palettes::Palette, ModeDiscriminants},
+    runner::Msg,
+};
+use core::fmt::Write;
+#[cfg(not(feature = "ir"))]
+use core::str::FromStr;
+use embassy_time::Duration;
+use neopixel_console::Value;
+use neopixel_protocol::LightState;
+
+/// The commands, with the names of the firmware.
+pub type Command = neopixel_console::Command<ModeDiscriminants, Palette, Easing, Action>;
+
+/// Without a remote control, there are no actions to learn.
+#[cfg(not(feature = "ir"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {}
+
+#[cfg(not(feature = "ir"))]
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(_: &str) -> Result<Self, ()> {
+        Err(())
+    }
+}
+
+/// Translate a command into a message for the runner, if the command requires one.
+pub fn to_msg(command: &Command, state: &LightState) -> Option<Msg> {
+    match command {
+        Command::Mode(mode) => Some(Msg::SetMode(*mode)),
+        Command::NextMode => Some(Msg::Next),
+        Command::PrevMode => Some(Msg::Prev),
+        Command::Palette(palette) => Some(Msg::SetPalette(*palette)),
+        Command::NextPalette => Some(Msg::NextPalette),
+        Command::Easing(easing) => Some(Msg::SetEasing(*easing)),
+        Command::Color(color) => Some(Msg::SetColor(*color)),
+        Command::SecondColor(color) => Some(Msg::SetSecondColor(*color)),
+        Command::Brightness(Value::Reset) => Some(Msg::ResetBrightness),
+        Command::Brightness(value) => Some(Msg::SetBrightness(value.apply(state.brightness))),
+        Command::AutoBrightness(enabled) => Some(Msg::AutoBrightness(*enabled)),
+        Command::Speed(Value::Reset) => Some(Msg::ResetSpeed),
+        Command::Speed(value) => Some(Msg::SetSpeed(value.apply(state.speed))),
+        Command::Sleep(Some(secs)) => Some(Msg::StartSleep(Duration::from_secs(*secs))),
+        Command::Sleep(None) => Some(Msg::StopSleep),
+        Command::Motion(timeout) => Some(Msg::MotionTimeout(timeout.map(Duration::from_secs))),
+        Command::Glitter(enabled) => Some(Msg::Glitter(*enabled)),
+        Command::Learn(_) => None,
+        Command::SetTime(_) | Command::Time => None,
+        Command::Status | Command::Modes | Command::Reset | Command::Help => None,
+    }
+}
+
+/// Get the mode from its identifier, as reported in the state.
+pub fn mode_name(id: u8) -> &'static str {
+    ModeDiscriminants::from_id(id)
+        .map(Into::into)
+        .unwrap_or("unknown")
+}
+
+/// Write a line of the list of modes, marking the current one.
+pub fn write_mode<W: Write>(
+    w: &mut W,
+    mode: ModeDiscriminants,
+    state: &LightState,
+) -> core::fmt::Result {
+    let marker = if mode.id() as u8 == state.mode {
+        '*'
+    } else {
+        ' '
+    };
+    write!(w, "{} {}\r\n", marker, Into::<&'static str>::into(mode))
+}
+
+#[cfg(feature = "ir")]
+pub fn write_learn_help<W: Write>(w: &mut W) -> core::fmt::Result {
+    write!(w, "learn <")?;
+    for (i, name) in Action::names().enumerate() {
+        write!(w, "{}{}", if i == 0 { "" } else { "|" }, name)?;
+    }
+    write!(w, ">\r\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use neopixel_protocol::Color;
+
+    const STATE: LightState = LightState {
+        mode: 1,
+        brightness: 16,
+        speed: 127,
+        on: true,
+        sleeping: false,
+    };
+
+    fn to_msg(line: &str) -> Option<Msg> {
+        super::to_msg(&Command::parse(line).unwrap(), &STATE)
+    }
+
+    #[test]
+    fn msg() {
+        assert!(matches!(
+            to_msg("brightness -20"),
+            Some(Msg::SetBrightness(0))
+        ));
+        assert!(matches!(
+            to_msg("brightness +4"),
+            Some(Msg::SetBrightness(20))
+        ));
+        assert!(matches!(to_msg("speed 255"), Some(Msg::SetSpeed(255))));
+        assert!(matches!(to_msg("sleep off"), Some(Msg::StopSleep)));
+        assert!(matches!(
+            to_msg("palette lava"),
+            Some(Msg::SetPalette(Palette::Lava))
+        ));
+        assert!(matches!(
+            to_msg("color2 0,0,255"),
+            Some(Msg::SetSecondColor(Color::Rgb { blue: 255, .. }))
+        ));
+        assert!(to_msg("status").is_none());
+    }
+}
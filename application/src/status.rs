@@ -0,0 +1,25 @@
+use core::cell::Cell;
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    channel::Channel,
+};
+use neopixel_protocol::LightState;
+
+static CURRENT: Mutex<CriticalSectionRawMutex, Cell<LightState>> =
+    Mutex::new(Cell::new(LightState::new()));
+
+/// Receives the state, whenever it changed.
+pub static CHANGED: Channel<CriticalSectionRawMutex, LightState, 1> = Channel::new();
+
+/// Publish the current state, notifying about changes.
+pub fn publish(state: LightState) {
+    let changed = CURRENT.lock(|current| current.replace(state) != state);
+    if changed {
+        CHANGED.try_send(state).ok();
+    }
+}
+
+/// Get the most recently published state.
+pub fn current() -> LightState {
+    CURRENT.lock(|current| current.get())
+}
@@ -0,0 +1,149 @@
+//! Settings kept in flash across resets, in the storage region of the bootloader layout.
+//!
+//! The storage region is a single page (see `memory-dfu.x`), holding one record. It is erased
+//! and written again on every change, so changes are collected for a while before saving them.
+
+use crate::dfu::{Partition, PartitionError, SharedPartition};
+use embassy_futures::select::{select, Either};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_time::{Duration, Timer};
+use embedded_storage::nor_flash::NorFlash;
+#[cfg(feature = "ble")]
+use embedded_storage_async::nor_flash::AsyncNorFlash;
+use neopixel_protocol::Color;
+
+/// Time without further changes, before the colors are saved.
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// Marks a record, and its version. An erased page doesn't match it.
+const MAGIC: [u8; 4] = *b"NPC1";
+
+/// Length of a record, padded to whole words for writing.
+const RECORD_LEN: usize = 16;
+
+/// Latest colors to save, only the latest ones matter.
+static COLORS: Signal<CriticalSectionRawMutex, [Color; 2]> = Signal::new();
+
+/// The storage region, taken from the linker script.
+fn region() -> (u32, u32) {
+    extern "C" {
+        static __storage_start: u32;
+        static __storage_end: u32;
+    }
+    unsafe {
+        let start = &__storage_start as *const u32 as u32;
+        let end = &__storage_end as *const u32 as u32;
+        (start, end)
+    }
+}
+
+/// Read the saved colors, if any. The flash is memory mapped, so this works before the
+/// softdevice is enabled, and without access to the NVMC.
+pub fn load() -> Option<[Color; 2]> {
+    let (start, _) = region();
+    let record = unsafe { core::slice::from_raw_parts(start as *const u8, RECORD_LEN) };
+    decode(record)
+}
+
+/// Save the colors, once they stopped changing.
+pub fn save(colors: [Color; 2]) {
+    COLORS.signal(colors);
+}
+
+fn encode(colors: [Color; 2]) -> [u8; RECORD_LEN] {
+    let mut record = [0xFF; RECORD_LEN];
+    record[..MAGIC.len()].copy_from_slice(&MAGIC);
+    let data = &mut record[MAGIC.len()..];
+    for (chunk, color) in data.chunks_mut(Color::ENCODED_LEN).zip(colors) {
+        chunk.copy_from_slice(&color.encode());
+    }
+    record
+}
+
+fn decode(record: &[u8]) -> Option<[Color; 2]> {
+    let data = record.strip_prefix(&MAGIC)?;
+    let first = Color::decode(data.get(..Color::ENCODED_LEN)?).ok()?;
+    let second = Color::decode(data.get(Color::ENCODED_LEN..2 * Color::ENCODED_LEN)?).ok()?;
+    Some([first, second])
+}
+
+/// Erase the storage region, and write the record.
+async fn write(
+    partition: &SharedPartition,
+    record: &[u8; RECORD_LEN],
+) -> Result<(), PartitionError> {
+    let (start, end) = region();
+    match &mut *partition.lock().await {
+        Partition::Nvmc(flash) => {
+            flash.erase(start, end).map_err(PartitionError::Nvmc)?;
+            flash.write(start, record).map_err(PartitionError::Nvmc)
+        }
+        #[cfg(feature = "ble")]
+        Partition::Softdevice(flash) => {
+            flash
+                .erase(start, end)
+                .await
+                .map_err(PartitionError::Softdevice)?;
+            flash
+                .write(start, record)
+                .await
+                .map_err(PartitionError::Softdevice)
+        }
+    }
+}
+
+/// Save the colors whenever they changed, sharing the flash with the firmware updates.
+#[embassy_executor::task]
+pub async fn storage_task(partition: &'static SharedPartition) {
+    let mut saved = load();
+    loop {
+        let mut colors = COLORS.wait().await;
+        while let Either::First(changed) = select(COLORS.wait(), Timer::after(SAVE_DELAY)).await {
+            colors = changed;
+        }
+
+        if saved == Some(colors) {
+            continue;
+        }
+        // the record must be in RAM for the softdevice
+        let record = encode(colors);
+        match write(partition, &record).await {
+            Ok(()) => {
+                defmt::info!("Saved colors");
+                saved = Some(colors);
+            }
+            Err(err) => defmt::warn!("Failed to save colors: {}", defmt::Debug2Format(&err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let colors = [
+            Color::Rgb {
+                red: 1,
+                green: 2,
+                blue: 3,
+            },
+            Color::Hsv {
+                hue: 300,
+                saturation: 128,
+                value: 255,
+            },
+        ];
+        let record = encode(colors);
+        assert_eq!(&record[..4], b"NPC1");
+        assert_eq!(&record[14..], &[0xFF, 0xFF]);
+        assert_eq!(decode(&record), Some(colors));
+
+        // erased flash, or a record of another version
+        assert_eq!(decode(&[0xFF; RECORD_LEN]), None);
+        let mut other = record;
+        other[3] = b'0';
+        assert_eq!(decode(&other), None);
+    }
+}
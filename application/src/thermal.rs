@@ -0,0 +1,137 @@
+//! Thermal protection, derating the brightness when getting hot.
+//!
+//! The temperature is measured by the die temperature sensor of the nRF52840, and optionally by an
+//! NTC thermistor next to the strip, sampled by the SAADC. The higher of both is used. Above a
+//! threshold, the brightness is reduced progressively, and only raised again once the temperature
+//! dropped by some margin.
+
+use crate::runner::Msg;
+use core::cell::Cell;
+use ector::{Actor, Address, Inbox};
+use embassy_nrf::temp::Temp;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::{Duration, Timer};
+#[cfg(feature = "ntc")]
+use neopixel_logic::thermal::ntc_temperature;
+use neopixel_logic::thermal::Derating;
+#[cfg(feature = "ble")]
+use nrf_softdevice::{raw, Softdevice};
+
+pub use neopixel_logic::thermal::{celsius, Limits, Temperature};
+
+/// Interval of sampling the temperature
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The current thermal state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Status {
+    pub temperature: Temperature,
+    /// The level the brightness is derated to, `255` if not derated
+    pub level: u8,
+}
+
+static CURRENT: Mutex<CriticalSectionRawMutex, Cell<Option<Status>>> = Mutex::new(Cell::new(None));
+
+/// Get the most recent thermal state, if measured yet.
+pub fn current() -> Option<Status> {
+    CURRENT.lock(|current| current.get())
+}
+
+/// The die temperature sensor, which is reserved by the softdevice once enabled.
+pub enum DieSensor {
+    Temp(Temp<'static>),
+    #[cfg(feature = "ble")]
+    Softdevice(&'static Softdevice),
+}
+
+impl DieSensor {
+    async fn read(&mut self) -> Option<Temperature> {
+        match self {
+            DieSensor::Temp(temp) => Some(temp.read().await.to_bits()),
+            #[cfg(feature = "ble")]
+            DieSensor::Softdevice(_) => {
+                // in quarter degrees as well
+                let mut temperature = 0;
+                match unsafe { raw::sd_temp_get(&mut temperature) } {
+                    raw::NRF_SUCCESS => Some(temperature),
+                    err => {
+                        defmt::warn!("Failed to read the temperature: {}", err);
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct ThermalGuard {
+    die: DieSensor,
+    #[cfg(feature = "ntc")]
+    saadc: embassy_nrf::saadc::Saadc<'static, 1>,
+    runner: Address<Msg>,
+    derating: Derating,
+}
+
+impl ThermalGuard {
+    pub fn new(
+        die: DieSensor,
+        #[cfg(feature = "ntc")] saadc: embassy_nrf::saadc::Saadc<'static, 1>,
+        runner: Address<Msg>,
+        limits: Limits,
+    ) -> Self {
+        Self {
+            die,
+            #[cfg(feature = "ntc")]
+            saadc,
+            runner,
+            derating: Derating::new(limits),
+        }
+    }
+
+    async fn temperature(&mut self) -> Option<Temperature> {
+        let die = self.die.read().await?;
+
+        #[cfg(feature = "ntc")]
+        {
+            let mut buf = [0; 1];
+            self.saadc.sample(&mut buf).await;
+            match ntc_temperature(buf[0]) {
+                Some(ntc) => return Some(ntc.max(die)),
+                None => defmt::warn!("NTC disconnected: {}", buf[0]),
+            }
+        }
+
+        Some(die)
+    }
+}
+
+#[ector::actor]
+impl Actor for ThermalGuard {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        loop {
+            if let Some(temperature) = self.temperature().await {
+                if let Some(level) = self.derating.update(temperature) {
+                    if level < u8::MAX {
+                        defmt::warn!("Temperature: {}/4°C, derating to {}", temperature, level);
+                    } else {
+                        defmt::info!("Temperature: {}/4°C, derating ended", temperature);
+                    }
+                    self.runner.try_notify(Msg::Thermal(level)).ok();
+                }
+
+                let status = Status {
+                    temperature,
+                    level: self.derating.level(),
+                };
+                CURRENT.lock(|current| current.set(Some(status)));
+            }
+
+            Timer::after(SAMPLE_INTERVAL).await;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Trigger inputs, wiring doorbells, door contacts and relay outputs of other systems to actions.
+//!
+//! The inputs are polled, as there are not enough GPIOTE channels to spare, and debounced. Edge
+//! triggers fire on a change of the input, level triggers fire when the input becomes active, and
+//! when it is active on startup, and may have an action for when it becomes inactive again.
+
+use crate::{pattern::ModeDiscriminants, runner::Msg};
+use ector::{Actor, Address, Inbox};
+use embassy_nrf::gpio::{AnyPin, Input};
+use embassy_time::{Duration, Instant, Timer};
+use neopixel_logic::rgb::Rgb8;
+use neopixel_logic::triggers::Evaluator;
+
+pub use neopixel_logic::triggers::Sense;
+
+/// Interval of sampling the inputs
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Mode(ModeDiscriminants),
+    /// Blink the whole strip
+    Notify(Rgb8),
+    On,
+    Off,
+    Toggle,
+    Sleep(Duration),
+}
+
+impl Action {
+    pub fn to_msg(&self) -> Msg {
+        match *self {
+            Self::Mode(mode) => Msg::SetMode(mode),
+            Self::Notify(color) => Msg::Notify(color),
+            Self::On => Msg::On,
+            Self::Off => Msg::Off,
+            Self::Toggle => Msg::Toggle,
+            Self::Sleep(duration) => Msg::StartSleep(duration),
+        }
+    }
+}
+
+/// Configuration of a trigger input, as defined by the board.
+pub type Config = neopixel_logic::triggers::Config<Action>;
+
+pub struct Triggers<const N: usize> {
+    inputs: [Input<'static, AnyPin>; N],
+    evaluators: [Evaluator<Action>; N],
+    runner: Address<Msg>,
+}
+
+impl<const N: usize> Triggers<N> {
+    pub fn new(
+        inputs: [Input<'static, AnyPin>; N],
+        configs: [Config; N],
+        runner: Address<Msg>,
+    ) -> Self {
+        Self {
+            inputs,
+            evaluators: configs.map(Evaluator::new),
+            runner,
+        }
+    }
+}
+
+#[ector::actor]
+impl<const N: usize> Actor for Triggers<N> {
+    type Message<'m> = ();
+
+    async fn on_mount<M>(&mut self, _: Address<Self::Message<'m>>, _inbox: M)
+    where
+        M: Inbox<Self::Message<'m>>,
+    {
+        loop {
+            let now = Instant::now();
+            for (input, evaluator) in self.inputs.iter().zip(self.evaluators.iter_mut()) {
+                if let Some(action) = evaluator.update(input.is_high(), now) {
+                    defmt::info!("Trigger: {}", defmt::Debug2Format(&action));
+                    self.runner.try_notify(action.to_msg()).ok();
+                }
+            }
+            Timer::after(POLL_INTERVAL).await;
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::console::{Console, Port};
+#[cfg(feature = "midi")]
+use crate::midi;
+use crate::runner;
+use ector::Address;
+use embassy_executor::Spawner;
+use embassy_nrf::{interrupt, peripherals::USBD, usb::Driver};
+#[cfg(feature = "midi")]
+use embassy_usb::driver::{Endpoint, EndpointError, EndpointOut};
+use embassy_usb::{Builder, Config, UsbDevice};
+use embassy_usb_serial::{CdcAcmClass, State};
+use static_cell::StaticCell;
+
+pub type MyDriver = Driver<'static, USBD>;
+
+const MAX_PACKET_SIZE: u16 = 64;
+
+/// Start the USB device, with a serial console.
+pub fn start(s: Spawner, usbd: USBD, irq: interrupt::USBD, runner: Address<runner::Msg>) {
+    // USB requires the crystal oscillator, started by `main` and requested from the softdevice
+    // once enabled
+    let driver = Driver::new(usbd, irq);
+
+    let mut config = Config::new(0xc0de, 0xcafe);
+    config.manufacturer = Some(crate::board::MANUFACTURER_NAME);
+    config.product = Some(crate::board::MODEL_NUMBER);
+    config.max_power = 100;
+    config.max_packet_size_0 = MAX_PACKET_SIZE as u8;
+
+    // Required for windows compatibility.
+    config.device_class = 0xEF;
+    config.device_sub_class = 0x02;
+    config.device_protocol = 0x01;
+    config.composite_with_iads = true;
+
+    static DEVICE_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static SERIAL_STATE: StaticCell<State> = StaticCell::new();
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        DEVICE_DESCRIPTOR.init([0; 256]),
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        CONTROL_BUF.init([0; 64]),
+        None,
+    );
+
+    let serial = CdcAcmClass::new(
+        &mut builder,
+        SERIAL_STATE.init(State::new()),
+        MAX_PACKET_SIZE,
+    );
+
+    #[cfg(feature = "midi")]
+    let midi = MidiClass::new(&mut builder, MAX_PACKET_SIZE);
+
+    s.spawn(usb_task(builder.build())).unwrap();
+    #[cfg(feature = "midi")]
+    s.spawn(midi_task(midi, runner.clone())).unwrap();
+    s.spawn(serial_task(serial, runner)).unwrap();
+}
+
+#[embassy_executor::task]
+async fn usb_task(mut device: UsbDevice<'static, MyDriver>) {
+    device.run().await;
+}
+
+#[embassy_executor::task]
+async fn serial_task(mut class: CdcAcmClass<'static, MyDriver>, runner: Address<runner::Msg>) {
+    loop {
+        class.wait_connection().await;
+        defmt::info!("USB serial connected");
+        let mut console = Console::new(&runner);
+        let _ = console.run(&mut Port::Usb(&mut class)).await;
+        defmt::info!("USB serial disconnected");
+    }
+}
+
+#[cfg(feature = "midi")]
+#[embassy_executor::task]
+async fn midi_task(mut class: MidiClass<'static, MyDriver>, runner: Address<runner::Msg>) {
+    let mut buf = [0; MAX_PACKET_SIZE as usize];
+    loop {
+        class.wait_connection().await;
+        defmt::info!("USB MIDI connected");
+        while let Ok(n) = class.read_packet(&mut buf).await {
+            for msg in midi::Message::from_packets(&buf[..n]).filter_map(|m| m.to_msg()) {
+                runner.try_notify(msg).ok();
+            }
+        }
+        defmt::info!("USB MIDI disconnected");
+    }
+}
+
+/// A USB MIDI streaming interface, receiving from the host only.
+///
+/// The host sends to an embedded MIDI IN jack, which is connected to an external MIDI OUT jack.
+#[cfg(feature = "midi")]
+struct MidiClass<'d, D: embassy_usb::driver::Driver<'d>> {
+    read_ep: D::EndpointOut,
+}
+
+#[cfg(feature = "midi")]
+impl<'d, D: embassy_usb::driver::Driver<'d>> MidiClass<'d, D> {
+    const USB_CLASS_AUDIO: u8 = 0x01;
+    const AUDIO_SUBCLASS_CONTROL: u8 = 0x01;
+    const AUDIO_SUBCLASS_MIDI_STREAMING: u8 = 0x03;
+    const CS_INTERFACE: u8 = 0x24;
+    const CS_ENDPOINT: u8 = 0x25;
+
+    const JACK_IN: u8 = 0x01;
+    const JACK_OUT: u8 = 0x02;
+
+    fn new(builder: &mut Builder<'d, D>, max_packet_size: u16) -> Self {
+        let mut func = builder.function(Self::USB_CLASS_AUDIO, Self::AUDIO_SUBCLASS_CONTROL, 0x00);
+
+        // Audio control interface, which only refers to the streaming interface
+        let mut iface = func.interface();
+        let streaming_if = u8::from(iface.interface_number()) + 1;
+        let mut alt = iface.alt_setting(Self::USB_CLASS_AUDIO, Self::AUDIO_SUBCLASS_CONTROL, 0x00);
+        alt.descriptor(
+            Self::CS_INTERFACE,
+            &[
+                0x01, // bDescriptorSubtype: HEADER
+                0x00,
+                0x01, // bcdADC: 1.0
+                0x09,
+                0x00, // wTotalLength
+                0x01, // bInCollection
+                streaming_if,
+            ],
+        );
+
+        // MIDI streaming interface
+        let mut iface = func.interface();
+        let mut alt = iface.alt_setting(
+            Self::USB_CLASS_AUDIO,
+            Self::AUDIO_SUBCLASS_MIDI_STREAMING,
+            0x00,
+        );
+        alt.descriptor(
+            Self::CS_INTERFACE,
+            &[
+                0x01, // bDescriptorSubtype: MS_HEADER
+                0x00, 0x01, // bcdMSC: 1.0
+                0x22, 0x00, // wTotalLength: header, jacks and endpoint
+            ],
+        );
+        alt.descriptor(
+            Self::CS_INTERFACE,
+            &[
+                0x02, // bDescriptorSubtype: MIDI_IN_JACK
+                0x01, // bJackType: EMBEDDED
+                Self::JACK_IN,
+                0x00, // iJack
+            ],
+        );
+        alt.descriptor(
+            Self::CS_INTERFACE,
+            &[
+                0x03, // bDescriptorSubtype: MIDI_OUT_JACK
+                0x02, // bJackType: EXTERNAL
+                Self::JACK_OUT,
+                0x01, // bNrInputPins
+                Self::JACK_IN,
+                0x01, // baSourcePin
+                0x00, // iJack
+            ],
+        );
+        let read_ep = alt.endpoint_bulk_out(max_packet_size);
+        alt.descriptor(
+            Self::CS_ENDPOINT,
+            &[
+                0x01, // bDescriptorSubtype: MS_GENERAL
+                0x01, // bNumEmbMIDIJack
+                Self::JACK_IN,
+            ],
+        );
+
+        Self { read_ep }
+    }
+
+    async fn wait_connection(&mut self) {
+        self.read_ep.wait_enabled().await
+    }
+
+    async fn read_packet(&mut self, data: &mut [u8]) -> Result<usize, EndpointError> {
+        self.read_ep.read(data).await
+    }
+}
@@ -0,0 +1,73 @@
+//! The wall clock, keeping the time of day.
+//!
+//! Once set, the time is counted by the RTC driving `embassy_time`. It is not kept over a reset,
+//! until set again, there is no time.
+//!
+//! The RTC runs from the internal RC oscillator, unless built with the `lfxo` feature for boards
+//! with a 32.768 kHz crystal. Calibrated by the softdevice, the RC oscillator is accurate to
+//! 500 ppm, so the clock may drift by about 45 seconds a day. Without BLE it isn't calibrated,
+//! and drifts a lot more. The crystal keeps it within about two seconds a day.
+
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::Instant;
+use neopixel_logic::pattern::clock::MILLIS_PER_DAY;
+use neopixel_protocol::TimeOfDay;
+
+static ANCHOR: Mutex<CriticalSectionRawMutex, Cell<Option<Anchor>>> = Mutex::new(Cell::new(None));
+
+/// The time of day, at an instant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Anchor {
+    instant: Instant,
+    time: TimeOfDay,
+}
+
+impl Anchor {
+    /// The milliseconds since midnight, at a later instant.
+    fn millis(&self, now: Instant) -> u32 {
+        let elapsed = (now - self.instant).as_millis() % MILLIS_PER_DAY as u64;
+        ((self.time.seconds() as u64 * 1000 + elapsed) % MILLIS_PER_DAY as u64) as u32
+    }
+}
+
+/// Set the time of day, from now on.
+pub fn set(time: TimeOfDay) {
+    let anchor = Anchor {
+        instant: Instant::now(),
+        time,
+    };
+    ANCHOR.lock(|current| current.set(Some(anchor)));
+}
+
+/// The milliseconds since midnight, unless the clock was not set.
+pub fn millis() -> Option<u32> {
+    let now = Instant::now();
+    ANCHOR
+        .lock(|current| current.get())
+        .map(|anchor| anchor.millis(now))
+}
+
+/// The time of day, unless the clock was not set.
+pub fn now() -> Option<TimeOfDay> {
+    millis().map(|millis| TimeOfDay::from_seconds(millis / 1000))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn anchor() {
+        let anchor = Anchor {
+            instant: Instant::from_secs(100),
+            time: TimeOfDay::new(23, 59, 0).unwrap(),
+        };
+        let millis = |ms| anchor.millis(Instant::from_millis(100_000 + ms));
+        assert_eq!(millis(0), (23 * 3600 + 59 * 60) * 1000);
+        assert_eq!(millis(59_999), MILLIS_PER_DAY - 1);
+        // past midnight, and days later
+        assert_eq!(millis(60_250), 250);
+        assert_eq!(millis(3 * MILLIS_PER_DAY as u64 + 60_250), 250);
+    }
+}
@@ -0,0 +1,254 @@
+//! Decoder for the Adalight protocol, as used by Hyperion, Prismatik and others.
+//!
+//! A frame starts with the header `Ada`, followed by the number of LEDs minus one (`u16`, big
+//! endian) and a checksum of the count (`hi ^ lo ^ 0x55`). The RGB values of all LEDs follow.
+
+use crate::{Rgb, BLACK};
+
+const MAGIC: &[u8] = b"Ada";
+
+/// Length of the header, without the checksum.
+const HEADER_LEN: usize = 5;
+
+/// Result of feeding a byte into the decoder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoded {
+    /// The byte is not part of a frame
+    Ignored,
+    /// The byte is part of a frame, which is not yet complete
+    Consumed,
+    /// A frame is complete, and can be taken from the decoder
+    Frame,
+    /// What looked like a header turned out to be none. Its bytes can be taken from the decoder,
+    /// the current byte may start the next header
+    Rejected,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Looking for the header, with the number of bytes matched so far
+    Magic(usize),
+    CountHigh,
+    CountLow(u8),
+    Checksum(u8, u8),
+    /// Receiving pixel data, with the number of bytes received so far
+    Data(usize),
+}
+
+pub struct Decoder<const N: usize> {
+    state: State,
+    /// Number of LEDs announced by the header
+    leds: usize,
+    /// The header received so far
+    header: [u8; HEADER_LEN],
+    /// Number of bytes of the rejected header
+    rejected: usize,
+    current: [u8; 3],
+    frame: [Rgb; N],
+}
+
+impl<const N: usize> Decoder<N> {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Magic(0),
+            leds: 0,
+            header: [b'A', b'd', b'a', 0, 0],
+            rejected: 0,
+            current: [0; 3],
+            frame: [BLACK; N],
+        }
+    }
+
+    /// The last complete frame. Pixels not covered by the frame are black.
+    pub fn frame(&self) -> &[Rgb; N] {
+        &self.frame
+    }
+
+    /// The bytes of the last header, which turned out to be none.
+    pub fn rejected(&self) -> &[u8] {
+        &self.header[..self.rejected]
+    }
+
+    /// Whether the decoder is waiting for a header, and not inside a frame.
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Magic(0)
+    }
+
+    pub fn push(&mut self, b: u8) -> Decoded {
+        match self.state {
+            State::Magic(n) => {
+                if b == MAGIC[n] {
+                    self.state = if n + 1 == MAGIC.len() {
+                        State::CountHigh
+                    } else {
+                        State::Magic(n + 1)
+                    };
+                    Decoded::Consumed
+                } else if n > 0 {
+                    self.reject(n, b)
+                } else {
+                    self.resync(b)
+                }
+            }
+            State::CountHigh => {
+                self.header[3] = b;
+                self.state = State::CountLow(b);
+                Decoded::Consumed
+            }
+            State::CountLow(hi) => {
+                self.header[4] = b;
+                self.state = State::Checksum(hi, b);
+                Decoded::Consumed
+            }
+            State::Checksum(hi, lo) => {
+                if hi ^ lo ^ 0x55 == b {
+                    self.leds = u16::from_be_bytes([hi, lo]) as usize + 1;
+                    self.state = State::Data(0);
+                    Decoded::Consumed
+                } else {
+                    // not a header after all
+                    self.reject(HEADER_LEN, b)
+                }
+            }
+            State::Data(n) => {
+                self.current[n % 3] = b;
+                if n % 3 == 2 {
+                    let led = n / 3;
+                    if led < N {
+                        let [r, g, b] = self.current;
+                        self.frame[led] = [r, g, b];
+                    }
+                }
+
+                if n + 1 == self.leds * 3 {
+                    for pixel in self.frame.iter_mut().skip(self.leds) {
+                        *pixel = BLACK;
+                    }
+                    self.state = State::Magic(0);
+                    Decoded::Frame
+                } else {
+                    self.state = State::Data(n + 1);
+                    Decoded::Consumed
+                }
+            }
+        }
+    }
+
+    /// Give up on the header received so far, keeping its bytes to be taken.
+    fn reject(&mut self, len: usize, b: u8) -> Decoded {
+        self.rejected = len;
+        self.resync(b);
+        Decoded::Rejected
+    }
+
+    /// Start looking for the next header, which might start with the current byte.
+    fn resync(&mut self, b: u8) -> Decoded {
+        if b == MAGIC[0] {
+            self.state = State::Magic(1);
+            Decoded::Consumed
+        } else {
+            self.state = State::Magic(0);
+            Decoded::Ignored
+        }
+    }
+}
+
+impl<const N: usize> Default for Decoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(leds: &[(u8, u8, u8)]) -> heapless::Vec<u8, 64> {
+        let [hi, lo] = ((leds.len() - 1) as u16).to_be_bytes();
+        let mut data = heapless::Vec::new();
+        data.extend_from_slice(&[b'A', b'd', b'a', hi, lo, hi ^ lo ^ 0x55])
+            .unwrap();
+        for (r, g, b) in leds {
+            data.extend_from_slice(&[*r, *g, *b]).unwrap();
+        }
+        data
+    }
+
+    fn decode<const N: usize>(decoder: &mut Decoder<N>, data: &[u8]) -> usize {
+        data.iter()
+            .filter(|b| decoder.push(**b) == Decoded::Frame)
+            .count()
+    }
+
+    #[test]
+    fn decode_frame() {
+        let mut decoder = Decoder::<3>::new();
+        let data = frame(&[(1, 2, 3), (4, 5, 6), (7, 8, 9)]);
+
+        for b in &data[..data.len() - 1] {
+            assert_eq!(decoder.push(*b), Decoded::Consumed);
+        }
+        assert_eq!(decoder.push(data[data.len() - 1]), Decoded::Frame);
+        assert_eq!(decoder.frame(), &[[1, 2, 3], [4, 5, 6], [7, 8, 9]]);
+    }
+
+    #[test]
+    fn captured_stream() {
+        // two frames, as sent by Prismatik for a 2 LED setup, preceded by a partial frame
+        let stream = [
+            0x12, 0x34, 0x56, //
+            b'A', b'd', b'a', 0x00, 0x01, 0x54, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, //
+            b'A', b'd', b'a', 0x00, 0x01, 0x54, 0x10, 0x20, 0x30, 0x40, 0x50, 0x60,
+        ];
+        let mut decoder = Decoder::<2>::new();
+        assert_eq!(decode(&mut decoder, &stream), 2);
+        assert_eq!(decoder.frame(), &[[0x10, 0x20, 0x30], [0x40, 0x50, 0x60]]);
+    }
+
+    #[test]
+    fn resync_on_garbage() {
+        let mut decoder = Decoder::<1>::new();
+
+        // garbage, including partial headers, is ignored
+        assert_eq!(decoder.push(b'x'), Decoded::Ignored);
+        assert_eq!(decode(&mut decoder, b"AdAAdxAd"), 0);
+        assert_eq!(decoder.push(b'z'), Decoded::Rejected);
+        assert_eq!(decoder.rejected(), b"Ad");
+        assert!(decoder.is_idle());
+
+        // a header directly following a partial header is found
+        let mut data = heapless::Vec::<u8, 64>::new();
+        data.extend_from_slice(b"AdA").unwrap();
+        data.extend_from_slice(&frame(&[(9, 8, 7)])).unwrap();
+        assert_eq!(decode(&mut decoder, &data), 1);
+        assert_eq!(decoder.frame(), &[[9, 8, 7]]);
+    }
+
+    #[test]
+    fn reject_checksum_mismatch() {
+        let mut decoder = Decoder::<1>::new();
+        let mut data = frame(&[(1, 1, 1)]);
+        data[5] ^= 0xFF;
+
+        assert_eq!(decode(&mut decoder, &data), 0);
+        assert_eq!(decoder.rejected(), &data[..5]);
+        // the following frame is decoded
+        assert_eq!(decode(&mut decoder, &frame(&[(2, 2, 2)])), 1);
+        assert_eq!(decoder.frame(), &[[2, 2, 2]]);
+    }
+
+    #[test]
+    fn different_led_count() {
+        // more LEDs than the strip, extra ones are dropped
+        let mut decoder = Decoder::<2>::new();
+        assert_eq!(
+            decode(&mut decoder, &frame(&[(1, 1, 1), (2, 2, 2), (3, 3, 3)])),
+            1
+        );
+        assert_eq!(decoder.frame(), &[[1, 1, 1], [2, 2, 2]]);
+
+        // fewer LEDs than the strip, remaining ones are black
+        assert_eq!(decode(&mut decoder, &frame(&[(4, 4, 4)])), 1);
+        assert_eq!(decoder.frame(), &[[4, 4, 4], BLACK]);
+    }
+}
@@ -0,0 +1,261 @@
+//! The commands of the console.
+//!
+//! The names of the modes, palettes, easing curves and remote control actions are defined by the
+//! firmware, so the commands are generic over them: anything parsed from its name will do.
+
+use crate::parse::{parse_duration, parse_hsv, parse_rgb, parse_time, ParseError, Value};
+use core::fmt::Write;
+use core::str::FromStr;
+use neopixel_protocol::{Color, TimeOfDay};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command<M, P, E, A> {
+    Mode(M),
+    NextMode,
+    PrevMode,
+    /// Select the palette of the current mode
+    Palette(P),
+    NextPalette,
+    /// Select the easing curve, of the modes using one
+    Easing(E),
+    Color(Color),
+    /// Select the second color, for the end of the gradient
+    SecondColor(Color),
+    Brightness(Value),
+    /// Follow the ambient light, or not
+    AutoBrightness(bool),
+    Speed(Value),
+    /// Time in seconds, after which the lights are switched off
+    Sleep(Option<u64>),
+    /// Time without motion in seconds, after which the lights are switched off
+    Motion(Option<u64>),
+    /// Sparkle on top of the mode, or not
+    Glitter(bool),
+    /// Bind the next key of an IR remote to an action
+    Learn(A),
+    /// Set the wall clock
+    SetTime(TimeOfDay),
+    /// Show the wall clock
+    Time,
+    Status,
+    Modes,
+    Reset,
+    Help,
+}
+
+impl<M: FromStr, P: FromStr, E: FromStr, A: FromStr> Command<M, P, E, A> {
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let mut args = line.split_ascii_whitespace();
+        let command = args.next().ok_or(ParseError::Empty)?;
+        let mut arg = || args.next().ok_or(ParseError::MissingArgument);
+
+        match command {
+            "mode" => match arg()? {
+                "next" => Ok(Self::NextMode),
+                "prev" => Ok(Self::PrevMode),
+                name => M::from_str(name)
+                    .map(Self::Mode)
+                    .map_err(|_| ParseError::InvalidArgument),
+            },
+            "palette" => match arg()? {
+                "next" => Ok(Self::NextPalette),
+                name => P::from_str(name)
+                    .map(Self::Palette)
+                    .map_err(|_| ParseError::InvalidArgument),
+            },
+            "easing" => E::from_str(arg()?)
+                .map(Self::Easing)
+                .map_err(|_| ParseError::InvalidArgument),
+            "color" | "color2" => {
+                let color = match arg()? {
+                    "hsv" => parse_hsv(arg()?)?,
+                    value => parse_rgb(value)?,
+                };
+                match command {
+                    "color" => Ok(Self::Color(color)),
+                    _ => Ok(Self::SecondColor(color)),
+                }
+            }
+            "brightness" => match arg()? {
+                "auto" => Ok(Self::AutoBrightness(true)),
+                "manual" => Ok(Self::AutoBrightness(false)),
+                value => Ok(Self::Brightness(Value::parse(value)?)),
+            },
+            "speed" => Ok(Self::Speed(Value::parse(arg()?)?)),
+            "sleep" => match arg()? {
+                "off" => Ok(Self::Sleep(None)),
+                duration => parse_duration(duration).map(|secs| Self::Sleep(Some(secs))),
+            },
+            "motion" => match arg()? {
+                "off" => Ok(Self::Motion(None)),
+                duration => parse_duration(duration).map(|secs| Self::Motion(Some(secs))),
+            },
+            "glitter" => match arg()? {
+                "on" => Ok(Self::Glitter(true)),
+                "off" => Ok(Self::Glitter(false)),
+                _ => Err(ParseError::InvalidArgument),
+            },
+            "learn" => A::from_str(arg()?)
+                .map(Self::Learn)
+                .map_err(|_| ParseError::InvalidArgument),
+            "time" => match args.next() {
+                Some(time) => parse_time(time).map(Self::SetTime),
+                None => Ok(Self::Time),
+            },
+            "status" => Ok(Self::Status),
+            "modes" => Ok(Self::Modes),
+            "reset" => Ok(Self::Reset),
+            "help" => Ok(Self::Help),
+            _ => Err(ParseError::UnknownCommand),
+        }
+    }
+}
+
+/// Write the list of commands. The actions to learn depend on the remote control, so the firmware
+/// lists them.
+pub fn write_help<W: Write>(w: &mut W) -> core::fmt::Result {
+    write!(
+        w,
+        "mode <name|next|prev>\r\n\
+         palette <name|next>\r\n\
+         easing <linear|sine|quadratic|exponential>\r\n\
+         color[2] <rrggbb|r,g,b|hsv h,s,v>\r\n\
+         brightness <value|+n|-n|reset|auto|manual>\r\n\
+         speed <value|+n|-n|reset>\r\n\
+         sleep <duration|off>\r\n\
+         motion <duration|off>\r\n\
+         glitter <on|off>\r\n\
+         time [hh:mm[:ss]]\r\n\
+         status\r\n\
+         modes\r\n\
+         reset\r\n"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Names, like the ones of the firmware.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Name {
+        Fire,
+        RainbowPart,
+        Ocean,
+        Exponential,
+        Toggle,
+    }
+
+    impl FromStr for Name {
+        type Err = ();
+
+        fn from_str(name: &str) -> Result<Self, ()> {
+            match name {
+                "fire" => Ok(Self::Fire),
+                "rainbow-part" => Ok(Self::RainbowPart),
+                "ocean" => Ok(Self::Ocean),
+                "exponential" => Ok(Self::Exponential),
+                "toggle" => Ok(Self::Toggle),
+                _ => Err(()),
+            }
+        }
+    }
+
+    type TestCommand = Command<Name, Name, Name, Name>;
+
+    fn parse(line: &str) -> Result<TestCommand, ParseError> {
+        TestCommand::parse(line)
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse("mode fire"), Ok(Command::Mode(Name::Fire)));
+        assert_eq!(
+            parse("mode rainbow-part"),
+            Ok(Command::Mode(Name::RainbowPart))
+        );
+        assert_eq!(parse("  mode   next "), Ok(Command::NextMode));
+        assert_eq!(parse("palette ocean"), Ok(Command::Palette(Name::Ocean)));
+        assert_eq!(parse("palette next"), Ok(Command::NextPalette));
+        assert_eq!(
+            parse("easing exponential"),
+            Ok(Command::Easing(Name::Exponential))
+        );
+        let orange = Color::Rgb {
+            red: 0xFF,
+            green: 0x80,
+            blue: 0x00,
+        };
+        assert_eq!(parse("color ff8000"), Ok(Command::Color(orange)));
+        assert_eq!(parse("color #FF8000"), Ok(Command::Color(orange)));
+        assert_eq!(parse("color2 255,128,0"), Ok(Command::SecondColor(orange)));
+        assert_eq!(
+            parse("color hsv 300,128,255"),
+            Ok(Command::Color(Color::Hsv {
+                hue: 300,
+                saturation: 128,
+                value: 255
+            }))
+        );
+        assert_eq!(
+            parse("brightness 40"),
+            Ok(Command::Brightness(Value::Absolute(40)))
+        );
+        assert_eq!(
+            parse("brightness manual"),
+            Ok(Command::AutoBrightness(false))
+        );
+        assert_eq!(parse("speed +5"), Ok(Command::Speed(Value::Relative(5))));
+        assert_eq!(parse("speed reset"), Ok(Command::Speed(Value::Reset)));
+        assert_eq!(parse("sleep 15m"), Ok(Command::Sleep(Some(15 * 60))));
+        assert_eq!(parse("sleep 90s"), Ok(Command::Sleep(Some(90))));
+        assert_eq!(parse("sleep off"), Ok(Command::Sleep(None)));
+        assert_eq!(parse("motion 2m"), Ok(Command::Motion(Some(2 * 60))));
+        assert_eq!(parse("learn toggle"), Ok(Command::Learn(Name::Toggle)));
+        assert_eq!(
+            parse("time 13:45"),
+            Ok(Command::SetTime(TimeOfDay::new(13, 45, 0).unwrap()))
+        );
+        assert_eq!(
+            parse("time 7:05:30"),
+            Ok(Command::SetTime(TimeOfDay::new(7, 5, 30).unwrap()))
+        );
+        assert_eq!(parse("glitter on"), Ok(Command::Glitter(true)));
+        assert_eq!(parse("time"), Ok(Command::Time));
+        assert_eq!(parse("status"), Ok(Command::Status));
+        assert_eq!(parse("modes"), Ok(Command::Modes));
+        assert_eq!(parse("reset"), Ok(Command::Reset));
+        assert_eq!(parse("help"), Ok(Command::Help));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse(""), Err(ParseError::Empty));
+        assert_eq!(parse("dance"), Err(ParseError::UnknownCommand));
+        assert_eq!(parse("mode"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("mode disco"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("palette plaid"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("easing elastic"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("learn dance"), Err(ParseError::InvalidArgument));
+        for line in [
+            "color ff80",
+            "color ff800g",
+            "color +f8000",
+            "color 255,128",
+            "color 255,128,0,0",
+            "color 256,0,0",
+            "color hsv 360,0,0",
+            "color hsv 30,255",
+            "time 24:00",
+            "time 12:60",
+            "time 12",
+            "time 12:00:00:00",
+            "time 12:3a",
+        ] {
+            assert_eq!(parse(line), Err(ParseError::InvalidArgument), "{}", line);
+        }
+        assert_eq!(parse("color hsv"), Err(ParseError::MissingArgument));
+        assert_eq!(parse("brightness 300"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse("sleep 5d"), Err(ParseError::InvalidArgument));
+    }
+}
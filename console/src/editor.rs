@@ -0,0 +1,105 @@
+//! A minimal line editor, for terminals.
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+const CTRL_U: u8 = 0x15;
+
+/// Result of feeding a byte into the line editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Nothing to do
+    None,
+    /// Echo the character
+    Echo(u8),
+    /// Erase the last character on the terminal
+    Erase,
+    /// Erase a number of characters on the terminal
+    Clear(usize),
+    /// A line is complete, and can be taken from the editor
+    Line,
+}
+
+/// A minimal line editor, supporting backspace and clearing the line.
+pub struct LineEditor<const N: usize> {
+    buffer: heapless::String<N>,
+    complete: bool,
+}
+
+impl<const N: usize> LineEditor<N> {
+    pub const fn new() -> Self {
+        Self {
+            buffer: heapless::String::new(),
+            complete: false,
+        }
+    }
+
+    pub fn push(&mut self, b: u8) -> Edit {
+        if self.complete {
+            self.buffer.clear();
+            self.complete = false;
+        }
+
+        match b {
+            b'\r' | b'\n' => {
+                if self.buffer.is_empty() && b == b'\n' {
+                    // second half of a CR/LF
+                    Edit::None
+                } else {
+                    self.complete = true;
+                    Edit::Line
+                }
+            }
+            BACKSPACE | DELETE => match self.buffer.pop() {
+                Some(_) => Edit::Erase,
+                None => Edit::None,
+            },
+            CTRL_U => {
+                let len = self.buffer.len();
+                self.buffer.clear();
+                Edit::Clear(len)
+            }
+            0x20..=0x7E => match self.buffer.push(b as char) {
+                Ok(()) => Edit::Echo(b),
+                Err(()) => Edit::None,
+            },
+            _ => Edit::None,
+        }
+    }
+
+    /// The current line, complete after [`Edit::Line`] was returned.
+    pub fn line(&self) -> &str {
+        &self.buffer
+    }
+}
+
+impl<const N: usize> Default for LineEditor<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_editor() {
+        let mut editor = LineEditor::<16>::new();
+        for b in b"modx" {
+            assert_eq!(editor.push(*b), Edit::Echo(*b));
+        }
+        assert_eq!(editor.push(DELETE), Edit::Erase);
+        for b in b"e fire" {
+            editor.push(*b);
+        }
+        assert_eq!(editor.push(b'\r'), Edit::Line);
+        assert_eq!(editor.line(), "mode fire");
+
+        // LF of CR/LF is ignored, and the next line starts empty
+        assert_eq!(editor.push(b'\n'), Edit::None);
+        assert_eq!(editor.push(b's'), Edit::Echo(b's'));
+        assert_eq!(editor.push(CTRL_U), Edit::Clear(1));
+        assert_eq!(editor.push(b'\r'), Edit::Line);
+        assert_eq!(editor.line(), "");
+    }
+}
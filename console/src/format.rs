@@ -0,0 +1,108 @@
+//! Formatting the output of commands.
+
+use crate::ParseError;
+use core::fmt::Write;
+use neopixel_protocol::{LightState, TimeOfDay};
+
+/// Write the state of the lights, with the name of the current mode.
+pub fn write_status<W: Write>(w: &mut W, mode: &str, state: &LightState) -> core::fmt::Result {
+    write!(w, "mode: {}\r\n", mode)?;
+    write!(w, "brightness: {}\r\n", state.brightness)?;
+    write!(w, "speed: {}\r\n", state.speed)?;
+    write!(w, "on: {}\r\n", yes_no(state.on))?;
+    write!(w, "sleeping: {}\r\n", yes_no(state.sleeping))
+}
+
+pub fn write_time<W: Write>(w: &mut W, time: Option<TimeOfDay>) -> core::fmt::Result {
+    match time {
+        Some(time) => write!(
+            w,
+            "time: {:02}:{:02}:{:02}\r\n",
+            time.hours, time.minutes, time.seconds
+        ),
+        None => write!(w, "time: not set\r\n"),
+    }
+}
+
+/// Write the temperature, in quarter degrees Celsius, and the level the brightness is derated
+/// to.
+pub fn write_thermal<W: Write>(w: &mut W, temperature: i32, level: u8) -> core::fmt::Result {
+    let (sign, t) = match temperature {
+        t if t < 0 => ("-", -t),
+        t => ("", t),
+    };
+    write!(w, "temperature: {}{}.{:02} C\r\n", sign, t / 4, t % 4 * 25)?;
+    let derating = 100 - level as u32 * 100 / u8::MAX as u32;
+    write!(w, "derating: {}%\r\n", derating)
+}
+
+pub fn write_error<W: Write>(w: &mut W, err: ParseError) -> core::fmt::Result {
+    let msg = match err {
+        ParseError::Empty => return Ok(()),
+        ParseError::UnknownCommand => "unknown command, try 'help'",
+        ParseError::MissingArgument => "missing argument",
+        ParseError::InvalidArgument => "invalid argument",
+    };
+    write!(w, "error: {}\r\n", msg)
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_status() {
+        let state = LightState {
+            mode: 1,
+            brightness: 16,
+            speed: 127,
+            on: true,
+            sleeping: false,
+        };
+        let mut out = heapless::String::<128>::new();
+        write_status(&mut out, "fire", &state).unwrap();
+        assert_eq!(
+            out,
+            "mode: fire\r\nbrightness: 16\r\nspeed: 127\r\non: yes\r\nsleeping: no\r\n"
+        );
+    }
+
+    #[test]
+    fn format_time() {
+        let mut out = heapless::String::<32>::new();
+        write_time(&mut out, TimeOfDay::new(7, 5, 30)).unwrap();
+        assert_eq!(out, "time: 07:05:30\r\n");
+
+        out.clear();
+        write_time(&mut out, None).unwrap();
+        assert_eq!(out, "time: not set\r\n");
+    }
+
+    #[test]
+    fn format_thermal() {
+        let mut out = heapless::String::<64>::new();
+        write_thermal(&mut out, 56 * 4 + 1, 128).unwrap();
+        assert_eq!(out, "temperature: 56.25 C\r\nderating: 50%\r\n");
+
+        out.clear();
+        write_thermal(&mut out, -2, u8::MAX).unwrap();
+        assert_eq!(out, "temperature: -0.50 C\r\nderating: 0%\r\n");
+    }
+
+    #[test]
+    fn format_error() {
+        let mut out = heapless::String::<64>::new();
+        write_error(&mut out, ParseError::Empty).unwrap();
+        assert_eq!(out, "");
+        write_error(&mut out, ParseError::MissingArgument).unwrap();
+        assert_eq!(out, "error: missing argument\r\n");
+    }
+}
@@ -0,0 +1,213 @@
+//! Routing the bytes received on a serial port to the frame decoders, and the console.
+
+use crate::{adalight, tpm2, Rgb};
+
+/// Result of receiving a byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Received {
+    /// The byte is not part of a frame, but input to the console
+    Console(u8),
+    /// The byte is part of a frame or packet, which is not yet complete
+    Consumed,
+    /// A frame is complete, and can be taken from the receiver
+    Frame,
+    /// A TPM2 command packet was received, which must be answered with [`tpm2::RESPONSE`]
+    Command,
+    /// The start of a frame turned out to be none, its bytes are input to the console after all
+    Replay(Replay),
+}
+
+/// Bytes to pass on to the console, which were taken for the start of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Replay {
+    bytes: [u8; 6],
+    len: usize,
+}
+
+impl Replay {
+    fn new(rejected: &[u8]) -> Self {
+        let mut replay = Self {
+            bytes: [0; 6],
+            len: rejected.len(),
+        };
+        replay.bytes[..rejected.len()].copy_from_slice(rejected);
+        replay
+    }
+
+    fn push(&mut self, b: u8) {
+        self.bytes[self.len] = b;
+        self.len += 1;
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Protocol {
+    Adalight,
+    Tpm2,
+}
+
+/// Passes the received bytes to the frame decoders, and the rest to the console.
+///
+/// Once a decoder started receiving a frame, it gets all bytes until the frame is complete, or
+/// turns out to be none. Pixel data can't be mistaken for the start of another frame that way.
+pub struct Receiver<const N: usize> {
+    adalight: adalight::Decoder<N>,
+    tpm2: tpm2::Decoder<N>,
+    /// The decoder inside a frame, if any
+    active: Option<Protocol>,
+    /// The decoder which completed the last frame
+    last: Protocol,
+}
+
+impl<const N: usize> Receiver<N> {
+    pub const fn new() -> Self {
+        Self {
+            adalight: adalight::Decoder::new(),
+            tpm2: tpm2::Decoder::new(),
+            active: None,
+            last: Protocol::Adalight,
+        }
+    }
+
+    /// The last complete frame, of either protocol.
+    pub fn frame(&self) -> &[Rgb; N] {
+        match self.last {
+            Protocol::Adalight => self.adalight.frame(),
+            Protocol::Tpm2 => self.tpm2.frame(),
+        }
+    }
+
+    pub fn push(&mut self, b: u8) -> Received {
+        match self.active {
+            Some(Protocol::Adalight) => self.adalight(b).or_else(|| self.tpm2(b)),
+            Some(Protocol::Tpm2) => self.tpm2(b).or_else(|| self.adalight(b)),
+            None => self.adalight(b).or_else(|| self.tpm2(b)),
+        }
+        .unwrap_or(Received::Console(b))
+    }
+
+    fn adalight(&mut self, b: u8) -> Option<Received> {
+        let decoded = self.adalight.push(b);
+        self.active = (!self.adalight.is_idle()).then_some(Protocol::Adalight);
+        match decoded {
+            adalight::Decoded::Ignored => None,
+            adalight::Decoded::Consumed => Some(Received::Consumed),
+            adalight::Decoded::Frame => {
+                self.last = Protocol::Adalight;
+                Some(Received::Frame)
+            }
+            adalight::Decoded::Rejected => {
+                let mut replay = Replay::new(self.adalight.rejected());
+                // the byte itself may start the next header, or a TPM2 packet
+                if self.active.is_none() && self.tpm2(b).is_none() {
+                    replay.push(b);
+                }
+                Some(Received::Replay(replay))
+            }
+        }
+    }
+
+    fn tpm2(&mut self, b: u8) -> Option<Received> {
+        let received = match self.tpm2.push(b) {
+            tpm2::Decoded::Ignored => None,
+            tpm2::Decoded::Consumed => Some(Received::Consumed),
+            tpm2::Decoded::Frame => {
+                self.last = Protocol::Tpm2;
+                Some(Received::Frame)
+            }
+            tpm2::Decoded::Command => Some(Received::Command),
+        };
+        self.active = (!self.tpm2.is_idle()).then_some(Protocol::Tpm2);
+        received
+    }
+}
+
+impl<const N: usize> Default for Receiver<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn receive<const N: usize>(receiver: &mut Receiver<N>, data: &[u8]) -> heapless::Vec<u8, 64> {
+        let mut console = heapless::Vec::new();
+        for b in data {
+            match receiver.push(*b) {
+                Received::Console(b) => console.push(b).unwrap(),
+                Received::Replay(replay) => console.extend_from_slice(replay.bytes()).unwrap(),
+                _ => {}
+            }
+        }
+        console
+    }
+
+    #[test]
+    fn console() {
+        let mut receiver = Receiver::<2>::new();
+        assert_eq!(receive(&mut receiver, b"mode fire\r"), b"mode fire\r");
+    }
+
+    #[test]
+    fn console_with_adalight_start() {
+        let mut receiver = Receiver::<2>::new();
+        // the start of a header is passed on, once it turns out to be none
+        assert_eq!(receive(&mut receiver, b"color FFA500\r"), b"color FFA500\r");
+        assert_eq!(receive(&mut receiver, b"AAdAd\r"), b"AAdAd\r");
+        // up to the checksum
+        assert_eq!(receive(&mut receiver, b"Adamant\r"), b"Adamant\r");
+        assert_eq!(receiver.frame(), &[[0; 3]; 2]);
+    }
+
+    #[test]
+    fn adalight_with_tpm2_start() {
+        let mut receiver = Receiver::<2>::new();
+        // pixel data starting like a TPM2 packet
+        let data = [
+            b'A', b'd', b'a', 0x00, 0x01, 0x54, 0xC9, 0xDA, 0x00, 0x03, 0x02, 0x01,
+        ];
+        assert!(receive(&mut receiver, &data).is_empty());
+        assert_eq!(receiver.frame(), &[[0xC9, 0xDA, 0x00], [0x03, 0x02, 0x01]]);
+        assert_eq!(receiver.push(b's'), Received::Console(b's'));
+    }
+
+    #[test]
+    fn tpm2_with_adalight_header() {
+        let mut receiver = Receiver::<2>::new();
+        // pixel data looking like an Adalight header
+        let data = [
+            0xC9, 0xDA, 0x00, 0x06, b'A', b'd', b'a', 0x00, 0x00, 0x55, 0x36,
+        ];
+        for b in &data[..data.len() - 1] {
+            assert_eq!(receiver.push(*b), Received::Consumed);
+        }
+        assert_eq!(receiver.push(0x36), Received::Frame);
+        assert_eq!(receiver.frame(), &[[b'A', b'd', b'a'], [0x00, 0x00, 0x55]]);
+        assert_eq!(receiver.push(b's'), Received::Console(b's'));
+    }
+
+    #[test]
+    fn tpm2_command() {
+        let mut receiver = Receiver::<2>::new();
+        let data = [0xC9, 0xC0, 0x00, 0x02, 0x0A, 0x20, 0x36];
+        for b in &data[..data.len() - 1] {
+            assert_eq!(receiver.push(*b), Received::Consumed);
+        }
+        assert_eq!(receiver.push(0x36), Received::Command);
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut receiver = Receiver::<2>::new();
+        // a TPM2 packet without end byte, followed by console input
+        let data = [0xC9, 0xDA, 0x00, 0x03, 1, 2, 3, b'x', b'y'];
+        assert_eq!(receive(&mut receiver, &data), b"xy");
+        assert_eq!(receiver.frame(), &[[0; 3]; 2]);
+    }
+}
@@ -0,0 +1,26 @@
+#![no_std]
+
+//! The serial console of the firmware, independent of the transport: the line editor, the
+//! grammar of the commands, the output formats, and the decoders of the realtime frame
+//! protocols.
+//!
+//! Like the protocol crate, this crate must stay free of hardware dependencies, so that its tests
+//! run on the host.
+
+pub mod adalight;
+pub mod command;
+pub mod editor;
+pub mod format;
+pub mod frames;
+pub mod parse;
+pub mod tpm2;
+
+pub use command::Command;
+pub use editor::{Edit, LineEditor};
+pub use parse::{ParseError, Value};
+
+/// A pixel, as red, green and blue.
+pub type Rgb = [u8; 3];
+
+/// A pixel which is off.
+pub const BLACK: Rgb = [0; 3];
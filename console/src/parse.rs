@@ -0,0 +1,195 @@
+//! Parsing the arguments of commands.
+
+use core::str::FromStr;
+use neopixel_protocol::{Color, TimeOfDay};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+}
+
+/// A value, which can be set, adjusted or reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    Absolute(u8),
+    Relative(i16),
+    Reset,
+}
+
+impl Value {
+    /// Parse a value, like `40`, `+5`, `-5` or `reset`.
+    pub fn parse(arg: &str) -> Result<Self, ParseError> {
+        if arg == "reset" {
+            return Ok(Self::Reset);
+        }
+        if arg.starts_with('+') || arg.starts_with('-') {
+            return i16::from_str(arg)
+                .map(Self::Relative)
+                .map_err(|_| ParseError::InvalidArgument);
+        }
+        u8::from_str(arg)
+            .map(Self::Absolute)
+            .map_err(|_| ParseError::InvalidArgument)
+    }
+
+    /// The new value, based on the current one.
+    pub fn apply(&self, current: u8) -> u8 {
+        match self {
+            Self::Absolute(value) => *value,
+            Self::Relative(delta) => (current as i16 + delta).clamp(0, u8::MAX as i16) as u8,
+            // handled by the caller
+            Self::Reset => current,
+        }
+    }
+}
+
+/// Parse a duration in seconds, like `90s`, `15m` or `1h`. Without a unit, minutes are assumed.
+pub fn parse_duration(arg: &str) -> Result<u64, ParseError> {
+    let (value, unit) = match arg.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => arg.split_at(idx),
+        None => (arg, "m"),
+    };
+    let value = u64::from_str(value).map_err(|_| ParseError::InvalidArgument)?;
+    match unit {
+        "s" => Ok(value),
+        "m" => Ok(value * 60),
+        "h" => Ok(value * 60 * 60),
+        _ => Err(ParseError::InvalidArgument),
+    }
+}
+
+/// Parse a color given as red, green and blue, like `ff8000` or `255,128,0`.
+pub fn parse_rgb(arg: &str) -> Result<Color, ParseError> {
+    let [red, green, blue] = if arg.contains(',') {
+        let [r, g, b] = split_components(arg)?;
+        [parse_number(r)?, parse_number(g)?, parse_number(b)?]
+    } else {
+        let hex = arg.strip_prefix('#').unwrap_or(arg);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseError::InvalidArgument);
+        }
+        let value = u32::from_str_radix(hex, 16).map_err(|_| ParseError::InvalidArgument)?;
+        let [_, r, g, b] = value.to_be_bytes();
+        [r, g, b]
+    };
+    Ok(Color::Rgb { red, green, blue })
+}
+
+/// Parse a color given as hue (in degrees), saturation and value, like `30,255,255`.
+pub fn parse_hsv(arg: &str) -> Result<Color, ParseError> {
+    let [h, s, v] = split_components(arg)?;
+    let hue = parse_number(h)?;
+    if hue >= 360 {
+        return Err(ParseError::InvalidArgument);
+    }
+    Ok(Color::Hsv {
+        hue,
+        saturation: parse_number(s)?,
+        value: parse_number(v)?,
+    })
+}
+
+/// Parse a time of day, like `13:45` or `13:45:30`.
+pub fn parse_time(arg: &str) -> Result<TimeOfDay, ParseError> {
+    let mut parts = arg.split(':');
+    let (hours, minutes, seconds) = match [parts.next(), parts.next(), parts.next(), parts.next()] {
+        [Some(h), Some(m), None, None] => (h, m, "0"),
+        [Some(h), Some(m), Some(s), None] => (h, m, s),
+        _ => return Err(ParseError::InvalidArgument),
+    };
+    TimeOfDay::new(
+        parse_number(hours)?,
+        parse_number(minutes)?,
+        parse_number(seconds)?,
+    )
+    .ok_or(ParseError::InvalidArgument)
+}
+
+fn split_components(arg: &str) -> Result<[&str; 3], ParseError> {
+    let mut parts = arg.split(',');
+    match [parts.next(), parts.next(), parts.next(), parts.next()] {
+        [Some(a), Some(b), Some(c), None] => Ok([a, b, c]),
+        _ => Err(ParseError::InvalidArgument),
+    }
+}
+
+fn parse_number<T: FromStr>(arg: &str) -> Result<T, ParseError> {
+    T::from_str(arg).map_err(|_| ParseError::InvalidArgument)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn value() {
+        assert_eq!(Value::parse("40"), Ok(Value::Absolute(40)));
+        assert_eq!(Value::parse("+5"), Ok(Value::Relative(5)));
+        assert_eq!(Value::parse("-20"), Ok(Value::Relative(-20)));
+        assert_eq!(Value::parse("reset"), Ok(Value::Reset));
+        assert_eq!(Value::parse("300"), Err(ParseError::InvalidArgument));
+        assert_eq!(Value::parse("lots"), Err(ParseError::InvalidArgument));
+
+        assert_eq!(Value::Absolute(255).apply(16), 255);
+        assert_eq!(Value::Relative(4).apply(16), 20);
+        assert_eq!(Value::Relative(-20).apply(16), 0);
+        assert_eq!(Value::Relative(300).apply(16), 255);
+        assert_eq!(Value::Reset.apply(16), 16);
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(parse_duration("15m"), Ok(15 * 60));
+        assert_eq!(parse_duration("15"), Ok(15 * 60));
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("1h"), Ok(60 * 60));
+        assert_eq!(parse_duration("5d"), Err(ParseError::InvalidArgument));
+        assert_eq!(parse_duration("m"), Err(ParseError::InvalidArgument));
+    }
+
+    #[test]
+    fn color() {
+        let orange = Color::Rgb {
+            red: 0xFF,
+            green: 0x80,
+            blue: 0x00,
+        };
+        assert_eq!(parse_rgb("ff8000"), Ok(orange));
+        assert_eq!(parse_rgb("#FF8000"), Ok(orange));
+        assert_eq!(parse_rgb("255,128,0"), Ok(orange));
+        assert_eq!(
+            parse_hsv("300,128,255"),
+            Ok(Color::Hsv {
+                hue: 300,
+                saturation: 128,
+                value: 255
+            })
+        );
+
+        for arg in [
+            "ff80",
+            "ff800g",
+            "+f8000",
+            "255,128",
+            "255,128,0,0",
+            "256,0,0",
+        ] {
+            assert_eq!(parse_rgb(arg), Err(ParseError::InvalidArgument), "{}", arg);
+        }
+        for arg in ["360,0,0", "30,255"] {
+            assert_eq!(parse_hsv(arg), Err(ParseError::InvalidArgument), "{}", arg);
+        }
+    }
+
+    #[test]
+    fn time() {
+        assert_eq!(parse_time("13:45"), Ok(TimeOfDay::new(13, 45, 0).unwrap()));
+        assert_eq!(parse_time("7:05:30"), Ok(TimeOfDay::new(7, 5, 30).unwrap()));
+        for arg in ["24:00", "12:60", "12", "12:00:00:00", "12:3a"] {
+            assert_eq!(parse_time(arg), Err(ParseError::InvalidArgument), "{}", arg);
+        }
+    }
+}
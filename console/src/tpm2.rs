@@ -0,0 +1,394 @@
+//! Decoder for the TPM2 protocol, as used by Jinx!, Glediator, PixelController and others.
+//!
+//! A packet starts with a start byte, followed by the packet type, the size of the payload (`u16`,
+//! big endian) and the payload, and ends with an end byte. Packets using the TPM2.net start byte
+//! additionally carry a packet number and the number of packets, so that a frame can be split
+//! across multiple packets.
+
+use crate::{Rgb, BLACK};
+
+const START: u8 = 0xC9;
+const START_NET: u8 = 0x9C;
+const END: u8 = 0x36;
+
+const TYPE_DATA: u8 = 0xDA;
+const TYPE_COMMAND: u8 = 0xC0;
+const TYPE_RESPONSE: u8 = 0xAA;
+
+/// The response to command packets. No command is supported, so the response carries no data.
+/// It lets the sender continue right away, instead of waiting for a response until it times out.
+pub const RESPONSE: [u8; 5] = [START, TYPE_RESPONSE, 0x00, 0x00, END];
+
+/// Result of feeding a byte into the decoder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoded {
+    /// The byte is not part of a packet
+    Ignored,
+    /// The byte is part of a packet, which is not yet complete
+    Consumed,
+    /// A frame is complete, and can be taken from the decoder
+    Frame,
+    /// A command packet was received, which must be answered with [`RESPONSE`]
+    Command,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Start,
+    Type {
+        net: bool,
+    },
+    SizeHigh {
+        net: bool,
+        kind: u8,
+    },
+    SizeLow {
+        net: bool,
+        kind: u8,
+        high: u8,
+    },
+    PacketNumber {
+        kind: u8,
+        size: usize,
+    },
+    PacketCount {
+        kind: u8,
+        size: usize,
+        number: u8,
+    },
+    Data {
+        kind: u8,
+        size: usize,
+        received: usize,
+    },
+    End {
+        kind: u8,
+    },
+}
+
+pub struct Decoder<const N: usize> {
+    state: State,
+    /// Packet number and count of the current packet
+    packet: (u8, u8),
+    /// Number of packets of the current frame received so far
+    received_packets: u8,
+    /// Offset of the current packet, in bytes, inside the frame
+    offset: usize,
+    /// Size of the payload of the current packet
+    size: usize,
+    current: [u8; 3],
+    /// The frame being received
+    pending: [Rgb; N],
+    /// The last complete frame
+    frame: [Rgb; N],
+}
+
+impl<const N: usize> Decoder<N> {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Start,
+            packet: (1, 1),
+            received_packets: 0,
+            offset: 0,
+            size: 0,
+            current: [0; 3],
+            pending: [BLACK; N],
+            frame: [BLACK; N],
+        }
+    }
+
+    /// The last complete frame.
+    pub fn frame(&self) -> &[Rgb; N] {
+        &self.frame
+    }
+
+    /// Whether the decoder is waiting for a packet, and not inside one.
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Start
+    }
+
+    pub fn push(&mut self, b: u8) -> Decoded {
+        match self.state {
+            State::Start => return self.start(b),
+            State::Type { net } => match b {
+                TYPE_DATA | TYPE_COMMAND | TYPE_RESPONSE => {
+                    self.state = State::SizeHigh { net, kind: b }
+                }
+                _ => return self.resync(b),
+            },
+            State::SizeHigh { net, kind } => self.state = State::SizeLow { net, kind, high: b },
+            State::SizeLow { net, kind, high } => {
+                let size = u16::from_be_bytes([high, b]) as usize;
+                self.state = if net {
+                    State::PacketNumber { kind, size }
+                } else {
+                    self.packet = (1, 1);
+                    self.begin(kind, size)
+                };
+            }
+            State::PacketNumber { kind, size } => {
+                self.state = State::PacketCount {
+                    kind,
+                    size,
+                    number: b,
+                }
+            }
+            State::PacketCount { kind, size, number } => {
+                self.packet = (number, b);
+                self.state = self.begin(kind, size);
+            }
+            State::Data {
+                kind,
+                size,
+                received,
+            } => {
+                if kind == TYPE_DATA {
+                    self.data_byte(received, b);
+                }
+                self.state = if received + 1 == size {
+                    State::End { kind }
+                } else {
+                    State::Data {
+                        kind,
+                        size,
+                        received: received + 1,
+                    }
+                };
+            }
+            State::End { kind } => {
+                if b != END {
+                    // corrupted, drop the frame
+                    self.received_packets = 0;
+                    return self.resync(b);
+                }
+                self.state = State::Start;
+                return match kind {
+                    TYPE_DATA => self.packet_complete(),
+                    TYPE_COMMAND => Decoded::Command,
+                    _ => Decoded::Consumed,
+                };
+            }
+        }
+
+        Decoded::Consumed
+    }
+
+    /// Begin receiving the payload of a packet.
+    fn begin(&mut self, kind: u8, size: usize) -> State {
+        if kind == TYPE_DATA {
+            if self.packet.0 <= 1 {
+                // first packet of a frame, pixels not covered by the frame are black
+                self.offset = 0;
+                self.received_packets = 0;
+                self.pending = [BLACK; N];
+            }
+            self.size = size;
+        }
+
+        if size == 0 {
+            State::End { kind }
+        } else {
+            State::Data {
+                kind,
+                size,
+                received: 0,
+            }
+        }
+    }
+
+    fn start(&mut self, b: u8) -> Decoded {
+        match b {
+            START => {
+                self.state = State::Type { net: false };
+                Decoded::Consumed
+            }
+            START_NET => {
+                self.state = State::Type { net: true };
+                Decoded::Consumed
+            }
+            _ => Decoded::Ignored,
+        }
+    }
+
+    /// Start looking for the next packet, which might start with the current byte.
+    fn resync(&mut self, b: u8) -> Decoded {
+        self.state = State::Start;
+        self.start(b)
+    }
+
+    fn data_byte(&mut self, received: usize, b: u8) {
+        let n = self.offset + received;
+        self.current[n % 3] = b;
+        if n % 3 == 2 && n / 3 < N {
+            let [r, g, b] = self.current;
+            self.pending[n / 3] = [r, g, b];
+        }
+    }
+
+    fn packet_complete(&mut self) -> Decoded {
+        let (number, count) = self.packet;
+
+        // packets are numbered from one, and must arrive in order
+        if number.max(1) != self.received_packets + 1 {
+            self.received_packets = 0;
+            return Decoded::Consumed;
+        }
+        self.received_packets += 1;
+        self.offset += self.size;
+
+        if self.received_packets >= count.max(1) {
+            self.frame = self.pending;
+            self.received_packets = 0;
+            Decoded::Frame
+        } else {
+            Decoded::Consumed
+        }
+    }
+}
+
+impl<const N: usize> Default for Decoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type Packet = heapless::Vec<u8, 64>;
+
+    fn packet(kind: u8, payload: &[u8]) -> Packet {
+        let [hi, lo] = (payload.len() as u16).to_be_bytes();
+        let mut data = Packet::new();
+        data.extend_from_slice(&[START, kind, hi, lo]).unwrap();
+        data.extend_from_slice(payload).unwrap();
+        data.push(END).unwrap();
+        data
+    }
+
+    fn net_packet(number: u8, count: u8, payload: &[u8]) -> Packet {
+        let [hi, lo] = (payload.len() as u16).to_be_bytes();
+        let mut data = Packet::new();
+        data.extend_from_slice(&[START_NET, TYPE_DATA, hi, lo, number, count])
+            .unwrap();
+        data.extend_from_slice(payload).unwrap();
+        data.push(END).unwrap();
+        data
+    }
+
+    fn decode<const N: usize>(decoder: &mut Decoder<N>, data: &[u8]) -> usize {
+        data.iter()
+            .filter(|b| decoder.push(**b) == Decoded::Frame)
+            .count()
+    }
+
+    #[test]
+    fn data_frame() {
+        let mut decoder = Decoder::<2>::new();
+        let data = packet(TYPE_DATA, &[1, 2, 3, 4, 5, 6]);
+
+        for b in &data[..data.len() - 1] {
+            assert_eq!(decoder.push(*b), Decoded::Consumed);
+        }
+        assert_eq!(decoder.push(END), Decoded::Frame);
+        assert_eq!(decoder.frame(), &[[1, 2, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn command_frame() {
+        let mut decoder = Decoder::<2>::new();
+        let data = packet(TYPE_COMMAND, &[0x0A, 0x20]);
+
+        let decoded: heapless::Vec<Decoded, 8> = data.iter().map(|b| decoder.push(*b)).collect();
+        assert_eq!(decoded.last(), Some(&Decoded::Command));
+        assert_eq!(decoder.frame(), &[BLACK, BLACK]);
+        assert!(decoder.is_idle());
+
+        // the response is a valid packet itself
+        assert_eq!(packet(TYPE_RESPONSE, &[]), RESPONSE);
+    }
+
+    #[test]
+    fn multi_packet_frame() {
+        let mut decoder = Decoder::<4>::new();
+
+        assert_eq!(
+            decode(&mut decoder, &net_packet(1, 2, &[1, 1, 1, 2, 2, 2])),
+            0
+        );
+        assert_eq!(
+            decode(&mut decoder, &net_packet(2, 2, &[3, 3, 3, 4, 4, 4])),
+            1
+        );
+        assert_eq!(
+            decoder.frame(),
+            &[[1, 1, 1], [2, 2, 2], [3, 3, 3], [4, 4, 4]]
+        );
+    }
+
+    #[test]
+    fn packet_split_across_reads() {
+        // the same stream, received in arbitrary pieces
+        let mut stream = heapless::Vec::<u8, 64>::new();
+        stream
+            .extend_from_slice(&net_packet(1, 2, &[1, 2, 3, 4]))
+            .unwrap();
+        stream
+            .extend_from_slice(&net_packet(2, 2, &[5, 6]))
+            .unwrap();
+
+        for split in 1..stream.len() {
+            let mut decoder = Decoder::<2>::new();
+            let (a, b) = stream.split_at(split);
+            assert_eq!(decode(&mut decoder, a) + decode(&mut decoder, b), 1);
+            assert_eq!(decoder.frame(), &[[1, 2, 3], [4, 5, 6]]);
+        }
+    }
+
+    #[test]
+    fn missing_packet() {
+        let mut decoder = Decoder::<4>::new();
+
+        assert_eq!(decode(&mut decoder, &net_packet(1, 3, &[1, 1, 1])), 0);
+        // packet two got lost
+        assert_eq!(decode(&mut decoder, &net_packet(3, 3, &[3, 3, 3])), 0);
+        assert_eq!(decoder.frame(), &[BLACK; 4]);
+
+        // the next frame is received
+        assert_eq!(decode(&mut decoder, &net_packet(1, 1, &[9, 9, 9])), 1);
+        assert_eq!(decoder.frame()[0], [9, 9, 9]);
+    }
+
+    #[test]
+    fn corrupted_packet() {
+        let mut decoder = Decoder::<2>::new();
+
+        // missing end byte, the frame is dropped
+        let mut data = packet(TYPE_DATA, &[1, 2, 3, 4, 5, 6]);
+        data.pop();
+        data.push(0x00).unwrap();
+        assert_eq!(decode(&mut decoder, &data), 0);
+        assert_eq!(decoder.frame(), &[BLACK, BLACK]);
+
+        // garbage and an invalid type are skipped, and the next packet is received
+        assert_eq!(decode(&mut decoder, &[0x01, 0x02, START, 0x42]), 0);
+        assert_eq!(decode(&mut decoder, &packet(TYPE_DATA, &[7, 8, 9])), 1);
+        assert_eq!(decoder.frame(), &[[7, 8, 9], BLACK]);
+    }
+
+    #[test]
+    fn truncated_packet_followed_by_start() {
+        let mut decoder = Decoder::<1>::new();
+
+        // a packet is cut off, and the next one starts where the end byte is expected
+        let mut data = Packet::new();
+        data.extend_from_slice(&[START, TYPE_DATA, 0x00, 0x03, 1, 2, 3])
+            .unwrap();
+        data.extend_from_slice(&packet(TYPE_DATA, &[4, 5, 6]))
+            .unwrap();
+
+        assert_eq!(decode(&mut decoder, &data), 1);
+        assert_eq!(decoder.frame(), &[[4, 5, 6]]);
+    }
+}
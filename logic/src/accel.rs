@@ -0,0 +1,411 @@
+//! Accelerometer input, detecting tilt, shake and tap gestures.
+//!
+//! The accelerometer is mounted with its x axis along the strip. The thresholds count samples,
+//! taken every 10ms.
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// Standard gravity, in mg
+const GRAVITY: f32 = 1000.0;
+/// Deviation from gravity, above which the sample is considered a movement, in mg
+const ACTIVE: f32 = 500.0;
+/// Deviation from gravity, below which the sample is considered at rest, in mg
+const STILL: f32 = 100.0;
+/// Maximum length of a tap
+const TAP_MAX: u32 = 3;
+/// Time without movement, before a tap
+const TAP_QUIET: u32 = 10;
+/// Minimum and maximum time between the taps of a double tap
+const DOUBLE_TAP_MIN: u32 = 8;
+const DOUBLE_TAP_MAX: u32 = 40;
+/// Number of samples with movement, out of the last `SHAKE_WINDOW` samples, making a shake
+const SHAKE_SAMPLES: u32 = 15;
+const SHAKE_WINDOW: u32 = 50;
+/// Time ignoring movements, after a shake or a double tap
+const COOLDOWN: u32 = 100;
+/// Weight of a new sample in the gravity, as shift (1/8)
+const SMOOTHING: u32 = 3;
+/// Change of the tilt, required before it gets reported
+const TILT_STEP: i8 = 4;
+
+/// Acceleration, in mg.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sample {
+    pub x: i16,
+    pub y: i16,
+    pub z: i16,
+}
+
+impl Sample {
+    pub const fn new(x: i16, y: i16, z: i16) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The deviation of the magnitude from gravity, in mg.
+    fn deviation(&self) -> f32 {
+        let (x, y, z) = (self.x as f32, self.y as f32, self.z as f32);
+        libm::fabsf(libm::sqrtf(x * x + y * y + z * z) - GRAVITY)
+    }
+}
+
+/// Access to an accelerometer.
+pub trait Accelerometer {
+    type Error;
+
+    fn read(&mut self) -> Result<Sample, Self::Error>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// Tilt along the strip, from `-127` (first pixel down) to `127` (last pixel down)
+    Tilt(i8),
+    Shake,
+    DoubleTap,
+}
+
+/// Detects gestures from the samples.
+pub struct Gestures {
+    /// Number of the current sample
+    n: u32,
+    /// Samples with movement, one bit per sample, the current sample in the lowest bit
+    history: u64,
+    /// Length of the current movement
+    run: u32,
+    last_tap: Option<u32>,
+    cooldown_until: u32,
+    /// The smoothed gravity, along the strip, with additional bits of precision
+    gravity: Option<i32>,
+    tilt: i8,
+}
+
+impl Gestures {
+    pub const fn new() -> Self {
+        Self {
+            n: 0,
+            history: 0,
+            run: 0,
+            last_tap: None,
+            cooldown_until: 0,
+            gravity: None,
+            tilt: 0,
+        }
+    }
+
+    /// Read a sample, returning the detected gesture.
+    pub fn poll<A: Accelerometer>(&mut self, accel: &mut A) -> Result<Option<Gesture>, A::Error> {
+        Ok(self.update(accel.read()?))
+    }
+
+    /// Feed a sample, returning the detected gesture.
+    pub fn update(&mut self, sample: Sample) -> Option<Gesture> {
+        self.n = self.n.wrapping_add(1);
+        let deviation = sample.deviation();
+        let active = deviation > ACTIVE;
+        self.history = (self.history << 1) | active as u64;
+
+        let gesture = if active {
+            self.run += 1;
+            self.shake()
+        } else {
+            let run = core::mem::replace(&mut self.run, 0);
+            if run > 0 {
+                self.tap(run)
+            } else {
+                None
+            }
+        };
+
+        if deviation < STILL {
+            self.gravity(sample);
+        }
+
+        gesture.or_else(|| self.tilt())
+    }
+
+    fn cooling_down(&self) -> bool {
+        self.n < self.cooldown_until
+    }
+
+    fn cooldown(&mut self) {
+        self.cooldown_until = self.n + COOLDOWN;
+        self.last_tap = None;
+    }
+
+    fn shake(&mut self) -> Option<Gesture> {
+        let window = self.history & ((1 << SHAKE_WINDOW) - 1);
+        if self.cooling_down() || window.count_ones() < SHAKE_SAMPLES {
+            return None;
+        }
+        self.cooldown();
+        Some(Gesture::Shake)
+    }
+
+    /// A movement of `run` samples ended with the previous sample.
+    fn tap(&mut self, run: u32) -> Option<Gesture> {
+        // taps are short, and follow a quiet moment
+        let quiet = (self.history >> (run + 1)) & ((1 << TAP_QUIET) - 1) == 0;
+        if self.cooling_down() || run > TAP_MAX || !quiet {
+            return None;
+        }
+
+        match self.last_tap {
+            Some(last) if (DOUBLE_TAP_MIN..=DOUBLE_TAP_MAX).contains(&(self.n - last)) => {
+                self.cooldown();
+                Some(Gesture::DoubleTap)
+            }
+            _ => {
+                self.last_tap = Some(self.n);
+                None
+            }
+        }
+    }
+
+    fn gravity(&mut self, sample: Sample) {
+        let x = sample.x as i32;
+        let gravity = match self.gravity {
+            Some(gravity) => gravity - (gravity >> SMOOTHING) + x,
+            None => x << SMOOTHING,
+        };
+        self.gravity = Some(gravity);
+    }
+
+    fn tilt(&mut self) -> Option<Gesture> {
+        let gravity = self.gravity? >> SMOOTHING;
+        let tilt = (gravity * 127 / GRAVITY as i32).clamp(-127, 127) as i8;
+        if tilt.abs_diff(self.tilt) < TILT_STEP as u8 {
+            return None;
+        }
+        self.tilt = tilt;
+        Some(Gesture::Tilt(tilt))
+    }
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// I2C address, with SDO low
+pub const LIS3DH_ADDRESS: u8 = 0x18;
+
+const WHO_AM_I: u8 = 0x0F;
+const LIS3DH_ID: u8 = 0x33;
+const CTRL_REG1: u8 = 0x20;
+const CTRL_REG4: u8 = 0x23;
+const OUT_X_L: u8 = 0x28;
+/// Increment the register address, when reading multiple bytes
+const AUTO_INCREMENT: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    I2c(E),
+    /// The device didn't identify as LIS3DH
+    WrongDevice(u8),
+}
+
+/// A LIS3DH, running at 100Hz with a range of ±4g, in high resolution mode.
+pub struct Lis3dh<I> {
+    i2c: I,
+    address: u8,
+}
+
+impl<I, E> Lis3dh<I>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+{
+    pub fn new(mut i2c: I, address: u8) -> Result<Self, Error<E>> {
+        let mut id = [0];
+        i2c.write_read(address, &[WHO_AM_I], &mut id)
+            .map_err(Error::I2c)?;
+        if id[0] != LIS3DH_ID {
+            return Err(Error::WrongDevice(id[0]));
+        }
+
+        // 100Hz, all axes enabled
+        i2c.write(address, &[CTRL_REG1, 0x57]).map_err(Error::I2c)?;
+        // block data update, ±4g, high resolution
+        i2c.write(address, &[CTRL_REG4, 0x98]).map_err(Error::I2c)?;
+
+        Ok(Self { i2c, address })
+    }
+}
+
+impl<I, E> Accelerometer for Lis3dh<I>
+where
+    I: Write<Error = E> + WriteRead<Error = E>,
+{
+    type Error = Error<E>;
+
+    fn read(&mut self) -> Result<Sample, Self::Error> {
+        let mut buf = [0; 6];
+        self.i2c
+            .write_read(self.address, &[OUT_X_L | AUTO_INCREMENT], &mut buf)
+            .map_err(Error::I2c)?;
+        // left aligned 12 bit values, with 2mg per digit
+        let axis = |i: usize| (i16::from_le_bytes([buf[i], buf[i + 1]]) >> 4) * 2;
+        Ok(Sample::new(axis(0), axis(2), axis(4)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use heapless::Vec;
+
+    const REST: Sample = Sample::new(0, 0, 1000);
+    /// A sharp knock on the enclosure
+    const KNOCK: Sample = Sample::new(300, -200, 2600);
+
+    /// Replays recorded samples.
+    struct Replay<'a> {
+        samples: &'a [Sample],
+    }
+
+    impl Accelerometer for Replay<'_> {
+        type Error = ();
+
+        fn read(&mut self) -> Result<Sample, ()> {
+            let (first, rest) = self.samples.split_first().ok_or(())?;
+            self.samples = rest;
+            Ok(*first)
+        }
+    }
+
+    type Trace = Vec<Sample, 512>;
+
+    fn rest(trace: &mut Trace, samples: usize) {
+        for _ in 0..samples {
+            trace.push(REST).unwrap();
+        }
+    }
+
+    /// Collect the gestures, with the number of the sample.
+    fn replay(trace: &[Sample]) -> Vec<(usize, Gesture), 32> {
+        let mut gestures = Gestures::new();
+        let mut accel = Replay { samples: trace };
+        let mut result = Vec::new();
+        let mut n = 0;
+        while let Ok(gesture) = gestures.poll(&mut accel) {
+            if let Some(gesture) = gesture {
+                result.push((n, gesture)).unwrap();
+            }
+            n += 1;
+        }
+        result
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut trace = Trace::new();
+        rest(&mut trace, 50);
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 20);
+        trace.push(KNOCK).unwrap();
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 50);
+
+        assert_eq!(replay(&trace), [(73, Gesture::DoubleTap)]);
+    }
+
+    #[test]
+    fn single_taps() {
+        // too far apart
+        let mut trace = Trace::new();
+        rest(&mut trace, 50);
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 60);
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 50);
+        assert!(replay(&trace).is_empty());
+
+        // too close
+        let mut trace = Trace::new();
+        rest(&mut trace, 50);
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 2);
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 50);
+        assert!(replay(&trace).is_empty());
+    }
+
+    #[test]
+    fn shake() {
+        let mut trace = Trace::new();
+        rest(&mut trace, 50);
+        // shaking along the strip at 5Hz, for 2s
+        for i in 0..200 {
+            let phase = i as f32 * 2.0 * core::f32::consts::PI / 20.0;
+            let x = (libm::sinf(phase) * 1500.0) as i16;
+            trace.push(Sample::new(x, 0, 1000)).unwrap();
+        }
+        rest(&mut trace, 50);
+
+        let gestures = replay(&trace);
+        // once per cooldown, without mistaking the shaking for taps
+        assert!(gestures.len() >= 2);
+        assert!(gestures.iter().all(|(_, g)| *g == Gesture::Shake));
+        assert!(gestures[0].0 < 100);
+    }
+
+    #[test]
+    fn tilt() {
+        let mut trace = Trace::new();
+        rest(&mut trace, 20);
+        // tilted by 30°, towards the last pixel
+        for _ in 0..50 {
+            trace.push(Sample::new(500, 0, 866)).unwrap();
+        }
+        // and back, with a tap in between, which doesn't affect the tilt
+        trace.push(KNOCK).unwrap();
+        rest(&mut trace, 50);
+
+        let gestures = replay(&trace);
+        let tilts: Vec<i8, 32> = gestures
+            .iter()
+            .filter_map(|(_, g)| match g {
+                Gesture::Tilt(tilt) => Some(*tilt),
+                _ => None,
+            })
+            .collect();
+
+        // rising to about half, and falling back to level
+        let peak = tilts.iter().copied().max().unwrap();
+        assert!((60..=64).contains(&peak), "peak: {}", peak);
+        assert!(tilts.last().unwrap().abs() < TILT_STEP);
+        assert!(tilts
+            .iter()
+            .take_while(|t| **t < peak)
+            .collect::<Vec<_, 32>>()
+            .windows(2)
+            .all(|w| w[1] > w[0]));
+    }
+
+    #[test]
+    fn lis3dh() {
+        struct Bus;
+        impl Write for Bus {
+            type Error = ();
+            fn write(&mut self, _: u8, _: &[u8]) -> Result<(), ()> {
+                Ok(())
+            }
+        }
+        impl WriteRead for Bus {
+            type Error = ();
+            fn write_read(&mut self, _: u8, reg: &[u8], buf: &mut [u8]) -> Result<(), ()> {
+                match reg[0] {
+                    WHO_AM_I => buf[0] = LIS3DH_ID,
+                    // x: 0.5g, y: -0.25g, z: 1g
+                    r if r == OUT_X_L | AUTO_INCREMENT => {
+                        buf.copy_from_slice(&[0xA0, 0x0F, 0x30, 0xF8, 0x40, 0x1F])
+                    }
+                    _ => return Err(()),
+                }
+                Ok(())
+            }
+        }
+
+        let mut accel = Lis3dh::new(Bus, LIS3DH_ADDRESS).unwrap();
+        assert_eq!(accel.read(), Ok(Sample::new(500, -250, 1000)));
+    }
+}
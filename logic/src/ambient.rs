@@ -0,0 +1,166 @@
+//! Automatic brightness, following the ambient light.
+//!
+//! The light level is read from a phototransistor (like the TEMT6000) with a load resistor. The
+//! readings are smoothed, and mapped to a brightness along a curve.
+
+/// Weight of a new reading in the average, as shift (1/8)
+const SMOOTHING: u32 = 3;
+/// Change of the brightness, required before it gets adjusted
+const HYSTERESIS: u8 = 2;
+
+/// Voltage of the phototransistor (TEMT6000 with 10kΩ), per 100 lux, in mV
+const MV_PER_100_LUX: u32 = 200;
+/// Voltage of the full SAADC range (gain 1/6 with the internal reference of 0.6V), in mV
+const FULL_SCALE_MV: u32 = 3600;
+
+/// Convert a 12 bit SAADC reading into lux.
+pub fn to_lux(raw: i16) -> u16 {
+    let mv = raw.max(0) as u32 * FULL_SCALE_MV / 4096;
+    (mv * 100 / MV_PER_100_LUX).min(u16::MAX as u32) as u16
+}
+
+/// Maps the light level to a brightness, interpolating linearly between the points.
+#[derive(Clone, Copy, Debug)]
+pub struct Curve {
+    /// Points of light level (lux) and brightness, with ascending light levels
+    points: &'static [(u16, u8)],
+}
+
+impl Curve {
+    pub const fn new(points: &'static [(u16, u8)]) -> Self {
+        Self { points }
+    }
+
+    pub fn brightness(&self, lux: u16) -> u8 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return u8::MAX,
+        };
+        if lux <= first.0 {
+            return first.1;
+        }
+
+        for window in self.points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (window[0], window[1]);
+            if lux <= x1 {
+                let (dx, x) = ((x1 - x0) as i32, (lux - x0) as i32);
+                let dy = y1 as i32 - y0 as i32;
+                return (y0 as i32 + dy * x / dx.max(1)) as u8;
+            }
+        }
+
+        last.1
+    }
+}
+
+/// Smooths the light level, and derives the brightness from it.
+pub struct AutoBrightness {
+    curve: Curve,
+    /// The smoothed light level, with additional bits of precision
+    average: Option<u32>,
+    brightness: Option<u8>,
+}
+
+impl AutoBrightness {
+    pub const fn new(curve: Curve) -> Self {
+        Self {
+            curve,
+            average: None,
+            brightness: None,
+        }
+    }
+
+    /// Feed a new reading, returning the brightness, if it should be changed.
+    pub fn update(&mut self, lux: u16) -> Option<u8> {
+        let sample = (lux as u32) << SMOOTHING;
+        let average = match self.average {
+            Some(average) => average - (average >> SMOOTHING) + lux as u32,
+            None => sample,
+        };
+        self.average = Some(average);
+
+        let brightness = self.curve.brightness((average >> SMOOTHING) as u16);
+        match self.brightness {
+            Some(current) if current.abs_diff(brightness) < HYSTERESIS => None,
+            _ => {
+                self.brightness = Some(brightness);
+                Some(brightness)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CURVE: Curve = Curve::new(&[(0, 4), (10, 8), (100, 32), (1000, 255)]);
+
+    /// Light level in a living room (lux, every 500ms): evening with the ceiling light on,
+    /// flickering slightly, switched off, and a car passing by the window.
+    const EVENING: [u16; 24] = [
+        148, 152, 150, 147, 153, 151, 149, 150, 152, 148, // ceiling light
+        3, 2, 3, 3, 2, 3, 3, 2, // switched off
+        40, 85, 30, // headlights
+        3, 2, 3,
+    ];
+
+    #[test]
+    fn curve() {
+        assert_eq!(CURVE.brightness(0), 4);
+        assert_eq!(CURVE.brightness(5), 6);
+        assert_eq!(CURVE.brightness(10), 8);
+        assert_eq!(CURVE.brightness(55), 20);
+        assert_eq!(CURVE.brightness(1000), 255);
+        assert_eq!(CURVE.brightness(u16::MAX), 255);
+
+        assert_eq!(Curve::new(&[]).brightness(10), u8::MAX);
+        assert_eq!(Curve::new(&[(10, 50)]).brightness(0), 50);
+        assert_eq!(Curve::new(&[(10, 50)]).brightness(20), 50);
+    }
+
+    #[test]
+    fn to_lux_readings() {
+        assert_eq!(to_lux(-3), 0);
+        assert_eq!(to_lux(0), 0);
+        // 200 mV
+        assert_eq!(to_lux(228), 100);
+        assert_eq!(to_lux(4095), 1799);
+    }
+
+    #[test]
+    fn steady_light() {
+        let mut auto = AutoBrightness::new(CURVE);
+        let updates: heapless::Vec<u8, 24> = EVENING[..10]
+            .iter()
+            .filter_map(|lux| auto.update(*lux))
+            .collect();
+
+        // flicker doesn't change the brightness
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0], CURVE.brightness(148));
+    }
+
+    #[test]
+    fn follow_changes() {
+        let mut auto = AutoBrightness::new(CURVE);
+        let updates: heapless::Vec<u8, 24> =
+            EVENING.iter().filter_map(|lux| auto.update(*lux)).collect();
+
+        // the brightness decreases steadily, without following the headlights
+        assert!(updates.windows(2).all(|w| w[1] < w[0]));
+        let last = *updates.last().unwrap();
+        assert!(last < 16, "brightness: {}", last);
+    }
+
+    #[test]
+    fn converge() {
+        let mut auto = AutoBrightness::new(CURVE);
+        auto.update(0);
+        let mut last = None;
+        for _ in 0..50 {
+            last = auto.update(1000).or(last);
+        }
+        assert!(last.unwrap() >= 255 - HYSTERESIS, "brightness: {:?}", last);
+    }
+}
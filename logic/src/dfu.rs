@@ -0,0 +1,559 @@
+//! Receiving firmware images for the bootloader, in chunks over an unreliable transport.
+
+use core::future::Future;
+
+/// Size of a flash page, the unit we write the DFU partition in.
+pub const PAGE_SIZE: usize = 4096;
+
+/// Number of checks in a row the running firmware must pass, before it confirms itself.
+///
+/// A check passes if the lights were updated since the last one. If the firmware panics or the
+/// watchdog kicks in before it is confirmed, the bootloader will revert to the previous firmware
+/// on the next boot.
+pub const HEALTH_CHECKS: u32 = 30;
+
+/// Maximum length of a control command.
+pub const CONTROL_LEN: usize = 16;
+
+/// Maximum length of a data chunk: the offset (4 bytes) plus the payload.
+pub const CHUNK_LEN: usize = 4 + 128;
+
+/// Access to the flash for the firmware update.
+pub trait DfuFlash {
+    type Error;
+
+    type WritePageFuture<'m>: Future<Output = Result<(), Self::Error>>
+    where
+        Self: 'm;
+
+    /// Write a page of the firmware image, at an offset relative to the start of the image.
+    fn write_page<'m>(
+        &'m mut self,
+        offset: usize,
+        page: &'m [u8; PAGE_SIZE],
+    ) -> Self::WritePageFuture<'m>;
+
+    type MarkUpdatedFuture<'m>: Future<Output = Result<(), Self::Error>>
+    where
+        Self: 'm;
+
+    /// Mark the written firmware image to be swapped in on the next boot.
+    fn mark_updated(&mut self) -> Self::MarkUpdatedFuture<'_>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Start a new update, with the size and CRC-32 of the complete image.
+    Start { size: u32, crc: u32 },
+    /// All data was sent, verify and mark for swapping.
+    Finish,
+    /// Abort the current update.
+    Abort,
+}
+
+impl Command {
+    const START: u8 = 0x01;
+    const FINISH: u8 = 0x02;
+    const ABORT: u8 = 0x03;
+
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        match data {
+            [Self::START, s0, s1, s2, s3, c0, c1, c2, c3] => Ok(Self::Start {
+                size: u32::from_le_bytes([*s0, *s1, *s2, *s3]),
+                crc: u32::from_le_bytes([*c0, *c1, *c2, *c3]),
+            }),
+            [Self::FINISH] => Ok(Self::Finish),
+            [Self::ABORT] => Ok(Self::Abort),
+            _ => Err(Error::InvalidCommand),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidCommand,
+    NotStarted,
+    TooLarge,
+    UnexpectedOffset,
+    ChecksumMismatch,
+    Flash,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Idle,
+    /// Receiving, waiting for data at the offset.
+    Receiving(u32),
+    /// Image verified and marked for swapping.
+    Complete,
+    Failed(Error),
+}
+
+impl Status {
+    /// Encode as status code, followed by the offset (little endian).
+    pub fn to_bytes(&self) -> [u8; 5] {
+        let (code, offset) = match self {
+            Self::Idle => (0x00, 0),
+            Self::Receiving(offset) => (0x01, *offset),
+            Self::Complete => (0x02, 0),
+            Self::Failed(Error::InvalidCommand) => (0x80, 0),
+            Self::Failed(Error::NotStarted) => (0x81, 0),
+            Self::Failed(Error::TooLarge) => (0x82, 0),
+            Self::Failed(Error::UnexpectedOffset) => (0x83, 0),
+            Self::Failed(Error::ChecksumMismatch) => (0x84, 0),
+            Self::Failed(Error::Flash) => (0x85, 0),
+        };
+        let o = offset.to_le_bytes();
+        [code, o[0], o[1], o[2], o[3]]
+    }
+}
+
+/// Receives a firmware image in chunks, and writes it to the DFU partition page by page.
+///
+/// Chunks must be sent in order. A chunk with an unexpected offset is rejected, and the
+/// status reports the offset to continue with.
+pub struct DfuReceiver {
+    max_size: u32,
+    size: u32,
+    expected_crc: u32,
+    crc: Crc32,
+    offset: u32,
+    page: [u8; PAGE_SIZE],
+    page_len: usize,
+    status: Status,
+}
+
+impl DfuReceiver {
+    /// Create a receiver, accepting images up to the maximum size.
+    pub const fn new(max_size: u32) -> Self {
+        Self {
+            max_size,
+            size: 0,
+            expected_crc: 0,
+            crc: Crc32::new(),
+            offset: 0,
+            page: [0xFF; PAGE_SIZE],
+            page_len: 0,
+            status: Status::Idle,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Handle a control command.
+    pub async fn command<F: DfuFlash>(&mut self, flash: &mut F, command: Command) -> Status {
+        let result = match command {
+            Command::Start { size, crc } => self.start(size, crc),
+            Command::Finish => self.finish(flash).await,
+            Command::Abort => {
+                self.status = Status::Idle;
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            self.status = Status::Failed(err);
+        }
+        self.status
+    }
+
+    /// Handle a data chunk, consisting of the offset (little endian) and the payload.
+    pub async fn chunk<F: DfuFlash>(&mut self, flash: &mut F, chunk: &[u8]) -> Status {
+        let result = match chunk {
+            [o0, o1, o2, o3, data @ ..] => {
+                let offset = u32::from_le_bytes([*o0, *o1, *o2, *o3]);
+                self.write(flash, offset, data).await
+            }
+            _ => Err(Error::InvalidCommand),
+        };
+        match result {
+            Ok(()) => {}
+            // report where to continue, but keep the update running
+            Err(Error::UnexpectedOffset) => {}
+            Err(err) => self.status = Status::Failed(err),
+        }
+        self.status
+    }
+
+    fn start(&mut self, size: u32, crc: u32) -> Result<(), Error> {
+        if size > self.max_size {
+            return Err(Error::TooLarge);
+        }
+
+        self.size = size;
+        self.expected_crc = crc;
+        self.crc = Crc32::new();
+        self.offset = 0;
+        self.page_len = 0;
+        self.status = Status::Receiving(0);
+
+        Ok(())
+    }
+
+    async fn write<F: DfuFlash>(
+        &mut self,
+        flash: &mut F,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        if !matches!(self.status, Status::Receiving(_)) {
+            return Err(Error::NotStarted);
+        }
+        if offset != self.offset {
+            return Err(Error::UnexpectedOffset);
+        }
+        if self.offset as usize + data.len() > self.size as usize {
+            return Err(Error::TooLarge);
+        }
+
+        self.crc.update(data);
+
+        let mut data = data;
+        while !data.is_empty() {
+            let len = data.len().min(PAGE_SIZE - self.page_len);
+            self.page[self.page_len..self.page_len + len].copy_from_slice(&data[..len]);
+            self.page_len += len;
+            self.offset += len as u32;
+            data = &data[len..];
+
+            if self.page_len == PAGE_SIZE {
+                self.flush(flash).await?;
+            }
+        }
+
+        self.status = Status::Receiving(self.offset);
+
+        Ok(())
+    }
+
+    async fn finish<F: DfuFlash>(&mut self, flash: &mut F) -> Result<(), Error> {
+        if !matches!(self.status, Status::Receiving(_)) {
+            return Err(Error::NotStarted);
+        }
+        if self.offset != self.size {
+            return Err(Error::UnexpectedOffset);
+        }
+        if self.crc.finish() != self.expected_crc {
+            return Err(Error::ChecksumMismatch);
+        }
+
+        if self.page_len > 0 {
+            self.flush(flash).await?;
+        }
+        flash.mark_updated().await.map_err(|_| Error::Flash)?;
+
+        self.status = Status::Complete;
+        Ok(())
+    }
+
+    /// Write the current page, padding it with the erased value.
+    async fn flush<F: DfuFlash>(&mut self, flash: &mut F) -> Result<(), Error> {
+        self.page[self.page_len..].fill(0xFF);
+        let page_offset = self.offset as usize - self.page_len;
+        flash
+            .write_page(page_offset, &self.page)
+            .await
+            .map_err(|_| Error::Flash)?;
+        self.page_len = 0;
+        Ok(())
+    }
+}
+
+/// CRC-32 (IEEE 802.3), as used by zlib and most tooling.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.0 ^= *b as u32;
+            for _ in 0..8 {
+                let mask = (!(self.0 & 1)).wrapping_add(1);
+                self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks the lights keep being updated, before confirming the running firmware.
+#[derive(Default)]
+pub struct HealthCheck {
+    heartbeats: u32,
+    passed: u32,
+}
+
+impl HealthCheck {
+    pub const fn new() -> Self {
+        Self {
+            heartbeats: 0,
+            passed: 0,
+        }
+    }
+
+    /// Check the number of heartbeats so far, returning `true` once enough checks passed in a row.
+    pub fn check(&mut self, heartbeats: u32) -> bool {
+        if heartbeats != self.heartbeats {
+            self.passed += 1;
+        } else {
+            self.passed = 0;
+        }
+        self.heartbeats = heartbeats;
+        self.passed >= HEALTH_CHECKS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use heapless::Vec;
+
+    const MAX_SIZE: u32 = 3 * PAGE_SIZE as u32;
+
+    /// Run a future, which completes right away with the memory flash.
+    fn run<F: Future>(future: F) -> F::Output {
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+        const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+        let waker = unsafe { Waker::from_raw(RAW) };
+        futures::pin_mut!(future);
+        match future.poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("blocked on the memory flash"),
+        }
+    }
+
+    struct MemoryFlash {
+        data: [u8; 3 * PAGE_SIZE],
+        writes: usize,
+        updated: bool,
+    }
+
+    impl MemoryFlash {
+        fn new() -> Self {
+            Self {
+                data: [0; 3 * PAGE_SIZE],
+                writes: 0,
+                updated: false,
+            }
+        }
+    }
+
+    impl DfuFlash for MemoryFlash {
+        type Error = ();
+
+        type WritePageFuture<'m> = core::future::Ready<Result<(), ()>>;
+
+        fn write_page<'m>(
+            &'m mut self,
+            offset: usize,
+            page: &'m [u8; PAGE_SIZE],
+        ) -> Self::WritePageFuture<'m> {
+            assert_eq!(offset % PAGE_SIZE, 0);
+            self.data[offset..offset + PAGE_SIZE].copy_from_slice(page);
+            self.writes += 1;
+            core::future::ready(Ok(()))
+        }
+
+        type MarkUpdatedFuture<'m> = core::future::Ready<Result<(), ()>>;
+
+        fn mark_updated(&mut self) -> Self::MarkUpdatedFuture<'_> {
+            self.updated = true;
+            core::future::ready(Ok(()))
+        }
+    }
+
+    fn image() -> [u8; 5000] {
+        let mut image = [0u8; 5000];
+        for (i, b) in image.iter_mut().enumerate() {
+            *b = (i * 7 % 251) as u8;
+        }
+        image
+    }
+
+    fn crc(data: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.finish()
+    }
+
+    fn chunk(offset: usize, data: &[u8]) -> Vec<u8, CHUNK_LEN> {
+        let mut result = Vec::new();
+        result
+            .extend_from_slice(&(offset as u32).to_le_bytes())
+            .unwrap();
+        result.extend_from_slice(data).unwrap();
+        result
+    }
+
+    fn start(image: &[u8]) -> Command {
+        Command::Start {
+            size: image.len() as u32,
+            crc: crc(image),
+        }
+    }
+
+    #[test]
+    fn crc32() {
+        assert_eq!(crc(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc(b""), 0);
+    }
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(
+            Command::parse(&[0x01, 0x10, 0x00, 0x00, 0x00, 0x04, 0x03, 0x02, 0x01]),
+            Ok(Command::Start {
+                size: 16,
+                crc: 0x01020304
+            })
+        );
+        assert_eq!(Command::parse(&[0x02]), Ok(Command::Finish));
+        assert_eq!(Command::parse(&[0x03]), Ok(Command::Abort));
+        assert_eq!(Command::parse(&[0x01, 0x10]), Err(Error::InvalidCommand));
+        assert_eq!(Command::parse(&[]), Err(Error::InvalidCommand));
+    }
+
+    #[test]
+    fn update() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        assert_eq!(
+            run(receiver.command(&mut flash, start(&image))),
+            Status::Receiving(0)
+        );
+
+        for (i, data) in image.chunks(128).enumerate() {
+            let status = run(receiver.chunk(&mut flash, &chunk(i * 128, data)));
+            assert_eq!(status, Status::Receiving((i * 128 + data.len()) as u32));
+        }
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Complete
+        );
+        assert_eq!(flash.writes, 2);
+        assert!(flash.updated);
+        assert_eq!(&flash.data[..image.len()], &image[..]);
+        // remainder of the last page is padded
+        assert!(flash.data[image.len()..2 * PAGE_SIZE]
+            .iter()
+            .all(|b| *b == 0xFF));
+    }
+
+    #[test]
+    fn resend_after_unexpected_offset() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        run(receiver.command(&mut flash, start(&image)));
+        run(receiver.chunk(&mut flash, &chunk(0, &image[..128])));
+
+        // a lost chunk is reported, and the update continues with the expected offset
+        assert_eq!(
+            run(receiver.chunk(&mut flash, &chunk(256, &image[256..384]))),
+            Status::Receiving(128)
+        );
+        for (i, data) in image[128..].chunks(128).enumerate() {
+            run(receiver.chunk(&mut flash, &chunk(128 + i * 128, data)));
+        }
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Complete
+        );
+        assert_eq!(&flash.data[..image.len()], &image[..]);
+    }
+
+    #[test]
+    fn reject_checksum_mismatch() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        run(receiver.command(
+            &mut flash,
+            Command::Start {
+                size: image.len() as u32,
+                crc: crc(&image) ^ 1,
+            },
+        ));
+        for (i, data) in image.chunks(128).enumerate() {
+            run(receiver.chunk(&mut flash, &chunk(i * 128, data)));
+        }
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Failed(Error::ChecksumMismatch)
+        );
+        assert!(!flash.updated);
+    }
+
+    #[test]
+    fn reject_incomplete() {
+        let image = image();
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        run(receiver.command(&mut flash, start(&image)));
+        run(receiver.chunk(&mut flash, &chunk(0, &image[..128])));
+
+        assert_eq!(
+            run(receiver.command(&mut flash, Command::Finish)),
+            Status::Failed(Error::UnexpectedOffset)
+        );
+        assert!(!flash.updated);
+    }
+
+    #[test]
+    fn reject_not_started() {
+        let mut flash = MemoryFlash::new();
+        let mut receiver = DfuReceiver::new(MAX_SIZE);
+
+        assert_eq!(
+            run(receiver.chunk(&mut flash, &chunk(0, &[1, 2, 3]))),
+            Status::Failed(Error::NotStarted)
+        );
+        assert_eq!(
+            run(receiver.command(
+                &mut flash,
+                Command::Start {
+                    size: MAX_SIZE + 1,
+                    crc: 0
+                }
+            )),
+            Status::Failed(Error::TooLarge)
+        );
+    }
+
+    #[test]
+    fn health_check() {
+        let mut health = HealthCheck::new();
+        for beats in 1..HEALTH_CHECKS {
+            assert!(!health.check(beats));
+        }
+        // stalled, starting over
+        assert!(!health.check(HEALTH_CHECKS - 1));
+        for beats in HEALTH_CHECKS..2 * HEALTH_CHECKS - 1 {
+            assert!(!health.check(beats));
+        }
+        assert!(health.check(2 * HEALTH_CHECKS - 1));
+    }
+}
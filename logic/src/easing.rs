@@ -0,0 +1,142 @@
+//! Easing curves, shaping a progress from `0.0` to `1.0`.
+//!
+//! The curves ease in and out: they start and end slowly, and are symmetric around the middle.
+//! They are used for animations, as well as for fading the brightness.
+
+use strum::{EnumString, IntoStaticStr};
+
+/// A keyframe, the value at a point in time, both from `0` to `255`.
+pub type Keyframe = (u8, u8);
+
+/// A curve defined by keyframes, in ascending order of time, from `0` to `255`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keyframes {
+    frames: &'static [Keyframe],
+}
+
+impl Keyframes {
+    pub const fn new(frames: &'static [Keyframe]) -> Self {
+        Self { frames }
+    }
+
+    /// Sample at a point in time, from `0.0` to `1.0`, going from one keyframe to the next
+    /// following an easing curve.
+    pub fn sample(&self, t: f32, easing: Easing) -> f32 {
+        let (first, last) = match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return 0.0,
+        };
+
+        let time = t.clamp(0.0, 1.0) * 255.0;
+        if time <= first.0 as f32 {
+            return first.1 as f32 / 255.0;
+        }
+
+        for window in self.frames.windows(2) {
+            let ((t0, v0), (t1, v1)) = (window[0], window[1]);
+            if time <= t1 as f32 {
+                let progress = (time - t0 as f32) / (t1 - t0).max(1) as f32;
+                let value = v0 as f32 + (v1 as f32 - v0 as f32) * easing.ease(progress);
+                return value / 255.0;
+            }
+        }
+
+        last.1 as f32 / 255.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    #[default]
+    Sine,
+    Quadratic,
+    Exponential,
+}
+
+impl Easing {
+    /// Ease a progress, from `0.0` to `1.0`. Values outside are clamped.
+    pub fn ease(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::Sine => (1.0 - libm::cosf(t * core::f32::consts::PI)) / 2.0,
+            Self::Quadratic if t < 0.5 => 2.0 * t * t,
+            Self::Quadratic => 1.0 - 2.0 * (1.0 - t) * (1.0 - t),
+            // the exponential curve never reaches zero, so the ends are fixed
+            Self::Exponential if t == 0.0 || t == 1.0 => t,
+            Self::Exponential if t < 0.5 => libm::exp2f(20.0 * t - 10.0) / 2.0,
+            Self::Exponential => 1.0 - libm::exp2f(10.0 - 20.0 * t) / 2.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STEPS: [Keyframe; 4] = [(0, 0), (64, 128), (192, 128), (255, 255)];
+    const CURVES: [Easing; 4] = [
+        Easing::Linear,
+        Easing::Sine,
+        Easing::Quadratic,
+        Easing::Exponential,
+    ];
+
+    #[test]
+    fn endpoints() {
+        for easing in CURVES {
+            assert_eq!(easing.ease(0.0), 0.0, "{:?}", easing);
+            assert_eq!(easing.ease(1.0), 1.0, "{:?}", easing);
+            // clamped
+            assert_eq!(easing.ease(-1.0), 0.0, "{:?}", easing);
+            assert_eq!(easing.ease(2.0), 1.0, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn monotonic() {
+        for easing in CURVES {
+            let mut last = 0.0;
+            for i in 1..=1000 {
+                let value = easing.ease(i as f32 / 1000.0);
+                assert!(value >= last, "{:?} {}: {} {}", easing, i, last, value);
+                last = value;
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric() {
+        for easing in CURVES {
+            assert!((easing.ease(0.5) - 0.5).abs() < 1e-6, "{:?}", easing);
+            for i in 0..=100 {
+                let t = i as f32 / 100.0;
+                let sum = easing.ease(t) + easing.ease(1.0 - t);
+                assert!((sum - 1.0).abs() < 1e-5, "{:?} {}: {}", easing, t, sum);
+            }
+        }
+    }
+
+    #[test]
+    fn keyframes() {
+        let close = |a: f32, b: u8| (a * 255.0 - b as f32).abs() < 0.01;
+        let at = |time: u8| time as f32 / 255.0;
+
+        let frames = Keyframes::new(&STEPS);
+        assert!(close(frames.sample(0.0, Easing::Linear), 0));
+        assert!(close(frames.sample(at(32), Easing::Linear), 64));
+        assert!(close(frames.sample(at(128), Easing::Linear), 128));
+        // eased between the keyframes
+        assert!(close(frames.sample(at(32), Easing::Sine), 64));
+        assert!(frames.sample(at(16), Easing::Sine) < at(32));
+        assert!(frames.sample(at(48), Easing::Sine) > at(96));
+
+        // not monotonic
+        let beat = Keyframes::new(&[(0, 0), (128, 255), (255, 0)]);
+        assert!(close(beat.sample(at(128), Easing::Sine), 255));
+        assert!(close(beat.sample(1.0, Easing::Sine), 0));
+        assert_eq!(Keyframes::new(&[]).sample(0.5, Easing::Sine), 0.0);
+    }
+}
@@ -0,0 +1,675 @@
+//! Decoding the codes of infrared remote controls, using the NEC or RC5 protocol, and the
+//! actions their keys are bound to.
+//!
+//! The decoder is fed the pulses timed from the edges of an IR receiver module, a mark while a
+//! carrier is received, and a space otherwise.
+
+use core::str::FromStr;
+use heapless::Vec;
+use neopixel_protocol::Color;
+
+/// A space this long ends a key press, so that the next code is a new press, in µs
+const IDLE_US: u32 = 150_000;
+
+const NEC_LEADER_MARK_US: u32 = 9000;
+const NEC_LEADER_SPACE_US: u32 = 4500;
+const NEC_REPEAT_SPACE_US: u32 = 2250;
+const NEC_BIT_MARK_US: u32 = 562;
+const NEC_ZERO_SPACE_US: u32 = 562;
+const NEC_ONE_SPACE_US: u32 = 1687;
+
+/// Half of an RC5 bit, in µs
+const RC5_HALF_BIT_US: u32 = 889;
+/// Number of half bits of an RC5 frame
+const RC5_HALF_BITS: u8 = 28;
+
+/// A mark or space, with its duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pulse {
+    pub mark: bool,
+    pub us: u32,
+}
+
+/// A key of a remote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    /// The address contains the inverted address byte for remotes using the standard protocol
+    Nec {
+        address: u16,
+        command: u8,
+    },
+    Rc5 {
+        address: u8,
+        command: u8,
+    },
+}
+
+/// A key press, or the repetition of a held key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub key: Key,
+    pub repeat: bool,
+}
+
+/// Check if a duration is within 25% of the nominal duration.
+fn matches(us: u32, nominal: u32) -> bool {
+    (nominal - nominal / 4..=nominal + nominal / 4).contains(&us)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NecCode {
+    Key(Key),
+    Repeat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NecState {
+    Idle,
+    Leader,
+    /// Receiving data bits, expecting a mark
+    Mark {
+        bits: u8,
+        data: u32,
+    },
+    /// Receiving data bits, expecting the space, which carries the value
+    Space {
+        bits: u8,
+        data: u32,
+    },
+    /// Received the repeat space, expecting the final mark
+    Repeat,
+}
+
+struct NecDecoder {
+    state: NecState,
+}
+
+impl NecDecoder {
+    const fn new() -> Self {
+        Self {
+            state: NecState::Idle,
+        }
+    }
+
+    fn push(&mut self, pulse: Pulse) -> Option<NecCode> {
+        let (next, code) = self.next(pulse);
+        self.state = match next {
+            Some(state) => state,
+            // the pulse might start the next frame
+            None if self.state != NecState::Idle => {
+                self.state = NecState::Idle;
+                self.next(pulse).0.unwrap_or(NecState::Idle)
+            }
+            None => NecState::Idle,
+        };
+        code
+    }
+
+    /// Get the next state, or `None` if the pulse isn't valid.
+    fn next(&self, Pulse { mark, us }: Pulse) -> (Option<NecState>, Option<NecCode>) {
+        match (self.state, mark) {
+            (NecState::Idle, true) if matches(us, NEC_LEADER_MARK_US) => {
+                (Some(NecState::Leader), None)
+            }
+            (NecState::Leader, false) if matches(us, NEC_LEADER_SPACE_US) => {
+                (Some(NecState::Mark { bits: 0, data: 0 }), None)
+            }
+            (NecState::Leader, false) if matches(us, NEC_REPEAT_SPACE_US) => {
+                (Some(NecState::Repeat), None)
+            }
+            (NecState::Mark { bits: 32, data }, true) if matches(us, NEC_BIT_MARK_US) => {
+                (Some(NecState::Idle), Self::decode(data).map(NecCode::Key))
+            }
+            (NecState::Mark { bits, data }, true) if matches(us, NEC_BIT_MARK_US) => {
+                (Some(NecState::Space { bits, data }), None)
+            }
+            (NecState::Space { bits, data }, false) => {
+                let bit = if matches(us, NEC_ZERO_SPACE_US) {
+                    0
+                } else if matches(us, NEC_ONE_SPACE_US) {
+                    1
+                } else {
+                    return (None, None);
+                };
+                // least significant bit first
+                let data = data | bit << bits;
+                (
+                    Some(NecState::Mark {
+                        bits: bits + 1,
+                        data,
+                    }),
+                    None,
+                )
+            }
+            (NecState::Repeat, true) if matches(us, NEC_BIT_MARK_US) => {
+                (Some(NecState::Idle), Some(NecCode::Repeat))
+            }
+            (NecState::Idle, _) => (Some(NecState::Idle), None),
+            _ => (None, None),
+        }
+    }
+
+    fn decode(data: u32) -> Option<Key> {
+        let [address_low, address_high, command, inverted] = data.to_le_bytes();
+        if command != !inverted {
+            return None;
+        }
+        Some(Key::Nec {
+            address: u16::from_le_bytes([address_low, address_high]),
+            command,
+        })
+    }
+}
+
+/// An RC5 frame, with the toggle bit, which changes with every key press.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rc5Code {
+    key: Key,
+    toggle: bool,
+}
+
+/// Decodes the Manchester encoded RC5 (and RC5X) frames.
+///
+/// A one is a space followed by a mark, a zero a mark followed by a space. As the first start
+/// bit is always a one, its space isn't visible, and neither is the last space of a frame ending
+/// with a zero.
+struct Rc5Decoder {
+    /// The half bits received so far, marks are ones
+    halves: u32,
+    len: u8,
+}
+
+impl Rc5Decoder {
+    const fn new() -> Self {
+        Self { halves: 0, len: 0 }
+    }
+
+    fn push(&mut self, Pulse { mark, us }: Pulse) -> Option<Rc5Code> {
+        let n = if matches(us, RC5_HALF_BIT_US) {
+            1
+        } else if matches(us, 2 * RC5_HALF_BIT_US) {
+            2
+        } else {
+            self.len = 0;
+            return None;
+        };
+
+        if self.len == 0 {
+            if !mark {
+                return None;
+            }
+            // the space of the first start bit
+            self.len = 1;
+            self.halves = 0;
+        }
+
+        for _ in 0..n {
+            self.halves = self.halves << 1 | mark as u32;
+            self.len += 1;
+        }
+
+        if mark && self.len >= RC5_HALF_BITS - 1 {
+            if self.len == RC5_HALF_BITS - 1 {
+                // the final space of a trailing zero
+                self.halves <<= 1;
+                self.len += 1;
+            }
+            let halves = self.halves;
+            let len = self.len;
+            self.len = 0;
+            if len == RC5_HALF_BITS {
+                return Self::decode(halves);
+            }
+        } else if self.len >= RC5_HALF_BITS {
+            self.len = 0;
+        }
+
+        None
+    }
+
+    fn decode(halves: u32) -> Option<Rc5Code> {
+        let mut bits = 0u16;
+        for i in (0..RC5_HALF_BITS).step_by(2).rev() {
+            bits <<= 1;
+            bits |= match (halves >> i) & 0b11 {
+                0b01 => 1,
+                0b10 => 0,
+                _ => return None,
+            };
+        }
+
+        // the second start bit is the inverted 7th bit of the command (RC5X)
+        let extended = if bits & 1 << 12 == 0 { 0x40 } else { 0 };
+        Some(Rc5Code {
+            key: Key::Rc5 {
+                address: ((bits >> 6) & 0x1F) as u8,
+                command: (bits & 0x3F) as u8 | extended,
+            },
+            toggle: bits & 1 << 11 != 0,
+        })
+    }
+}
+
+/// Decodes pulses into key presses, of either protocol.
+pub struct Decoder {
+    nec: NecDecoder,
+    rc5: Rc5Decoder,
+    /// The last key, and its RC5 toggle bit, until the remote is idle
+    last: Option<(Key, bool)>,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            nec: NecDecoder::new(),
+            rc5: Rc5Decoder::new(),
+            last: None,
+        }
+    }
+
+    pub fn push(&mut self, pulse: Pulse) -> Option<Event> {
+        if !pulse.mark && pulse.us >= IDLE_US {
+            self.last = None;
+        }
+
+        let event = match self.nec.push(pulse) {
+            Some(NecCode::Key(key)) => Some(Event { key, repeat: false }),
+            Some(NecCode::Repeat) => self.last.map(|(key, _)| Event { key, repeat: true }),
+            None => None,
+        };
+        if let Some(event) = event {
+            self.last = Some((event.key, false));
+            return Some(event);
+        }
+
+        let Rc5Code { key, toggle } = self.rc5.push(pulse)?;
+        // a held key is sent again, with the same toggle bit
+        let repeat = self.last == Some((key, toggle));
+        self.last = Some((key, toggle));
+        Some(Event { key, repeat })
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Something a key can do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    On,
+    Off,
+    Toggle,
+    Lighter,
+    Darker,
+    Faster,
+    Slower,
+    NextMode,
+    PrevMode,
+    Color(Color),
+}
+
+/// The actions, by name.
+const ACTIONS: [(&str, Action); 18] = [
+    ("on", Action::On),
+    ("off", Action::Off),
+    ("toggle", Action::Toggle),
+    ("lighter", Action::Lighter),
+    ("darker", Action::Darker),
+    ("faster", Action::Faster),
+    ("slower", Action::Slower),
+    ("next", Action::NextMode),
+    ("prev", Action::PrevMode),
+    ("red", color(0xFF, 0x00, 0x00)),
+    ("orange", color(0xFF, 0x80, 0x00)),
+    ("yellow", color(0xFF, 0xFF, 0x00)),
+    ("green", color(0x00, 0xFF, 0x00)),
+    ("cyan", color(0x00, 0xFF, 0xFF)),
+    ("blue", color(0x00, 0x00, 0xFF)),
+    ("purple", color(0x80, 0x00, 0xFF)),
+    ("pink", color(0xFF, 0x00, 0x80)),
+    ("white", Action::Color(Color::WHITE)),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        ACTIONS.iter().map(|(name, _)| *name)
+    }
+
+    /// If the action is repeated, while the key is held.
+    pub fn repeats(&self) -> bool {
+        matches!(
+            self,
+            Self::Lighter | Self::Darker | Self::Faster | Self::Slower
+        )
+    }
+}
+
+/// Parse an action from its name, for the console.
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, ()> {
+        Self::from_name(name).ok_or(())
+    }
+}
+
+/// Address of the common 24 key remotes, shipped with LED strips
+const NEC_24_KEY: u16 = 0xEF00;
+/// Address of TVs, for RC5 remotes
+const RC5_TV: u8 = 0x00;
+
+const fn nec(command: u8, action: Action) -> (Key, Action) {
+    (
+        Key::Nec {
+            address: NEC_24_KEY,
+            command,
+        },
+        action,
+    )
+}
+
+const fn rc5(command: u8, action: Action) -> (Key, Action) {
+    (
+        Key::Rc5 {
+            address: RC5_TV,
+            command,
+        },
+        action,
+    )
+}
+
+const fn color(red: u8, green: u8, blue: u8) -> Action {
+    Action::Color(Color::Rgb { red, green, blue })
+}
+
+/// The default bindings, for the 24 key remote and an RC5 TV remote.
+///
+/// The keys of the 24 key remote are numbered row by row, the first column holds shades of
+/// red, the second of green and the third of blue. The effect keys in the last column change
+/// the speed and mode.
+const DEFAULT_KEYMAP: [(Key, Action); 29] = [
+    nec(0x00, Action::Lighter),
+    nec(0x01, Action::Darker),
+    nec(0x02, Action::Off),
+    nec(0x03, Action::On),
+    nec(0x04, color(0xFF, 0x00, 0x00)),
+    nec(0x05, color(0x00, 0xFF, 0x00)),
+    nec(0x06, color(0x00, 0x00, 0xFF)),
+    nec(0x07, Action::Color(Color::WHITE)),
+    nec(0x08, color(0xFF, 0x40, 0x00)),
+    nec(0x09, color(0x00, 0xFF, 0x40)),
+    nec(0x0A, color(0x40, 0x00, 0xFF)),
+    // flash
+    nec(0x0B, Action::Faster),
+    nec(0x0C, color(0xFF, 0x80, 0x00)),
+    nec(0x0D, color(0x00, 0xFF, 0x80)),
+    nec(0x0E, color(0x80, 0x00, 0xFF)),
+    // strobe
+    nec(0x0F, Action::Slower),
+    nec(0x10, color(0xFF, 0xC0, 0x00)),
+    nec(0x11, color(0x00, 0xFF, 0xC0)),
+    nec(0x12, color(0xC0, 0x00, 0xFF)),
+    // fade
+    nec(0x13, Action::PrevMode),
+    nec(0x14, color(0xFF, 0xFF, 0x00)),
+    nec(0x15, color(0x00, 0xFF, 0xFF)),
+    nec(0x16, color(0xFF, 0x00, 0xFF)),
+    // smooth
+    nec(0x17, Action::NextMode),
+    // standby, volume and channel
+    rc5(12, Action::Toggle),
+    rc5(16, Action::Lighter),
+    rc5(17, Action::Darker),
+    rc5(32, Action::NextMode),
+    rc5(33, Action::PrevMode),
+];
+
+/// The bindings of keys to actions.
+pub struct Keymap {
+    bindings: Vec<(Key, Action), 48>,
+}
+
+impl Keymap {
+    /// The default bindings.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::from_slice(&DEFAULT_KEYMAP).unwrap_or_default(),
+        }
+    }
+
+    pub fn get(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// Bind a key, replacing its current binding. Returns `false` if there is no space left.
+    pub fn bind(&mut self, key: Key, action: Action) -> bool {
+        match self.bindings.iter_mut().find(|(k, _)| *k == key) {
+            Some(binding) => {
+                binding.1 = action;
+                true
+            }
+            None => self.bindings.push((key, action)).is_ok(),
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The "on" key of a 24 key remote, captured from a TSOP38238 (marks are stretched)
+    const NEC_ON: [u32; 67] = [
+        9060, 4451, 627, 481, 605, 518, 636, 516, //
+        625, 485, 605, 490, 615, 520, 607, 495, //
+        628, 518, 617, 1642, 637, 1620, 605, 1611, //
+        609, 1633, 642, 482, 639, 1644, 638, 1610, //
+        627, 1644, 616, 1645, 637, 1639, 620, 496, //
+        611, 488, 609, 486, 621, 487, 645, 511, //
+        608, 485, 638, 482, 614, 499, 608, 1612, //
+        647, 1643, 638, 1644, 641, 1634, 633, 1604, //
+        636, 1620, 651,
+    ];
+
+    /// The repeat code, sent while holding a key
+    const NEC_REPEAT: [u32; 3] = [9060, 2181, 639];
+
+    /// Volume up of a TV remote, toggle bit set
+    const RC5_VOLUME_UP: [u32; 23] = [
+        958, 826, 948, 834, 1868, 838, 973, 800, 944, 844, 965, 830, 962, 818, 950, 1692, 1846,
+        831, 967, 845, 936, 817, 955,
+    ];
+
+    /// RC5X frame, with address 5 and command 0x45, toggle bit clear
+    const RC5X: [u32; 19] = [
+        1828, 801, 950, 840, 960, 823, 931, 1696, 1822, 1690, 1853, 813, 979, 829, 950, 1694, 1840,
+        1700, 960,
+    ];
+
+    const IDLE: Pulse = Pulse {
+        mark: false,
+        us: 500_000,
+    };
+
+    /// Decode pulses, alternating between mark and space, starting with a mark.
+    fn decode(decoder: &mut Decoder, pulses: &[u32]) -> Vec<Event, 4> {
+        pulses
+            .iter()
+            .enumerate()
+            .filter_map(|(i, us)| {
+                decoder.push(Pulse {
+                    mark: i % 2 == 0,
+                    us: *us,
+                })
+            })
+            .collect()
+    }
+
+    const ON: Key = Key::Nec {
+        address: NEC_24_KEY,
+        command: 0x03,
+    };
+
+    const VOLUME_UP: Key = Key::Rc5 {
+        address: RC5_TV,
+        command: 16,
+    };
+
+    #[test]
+    fn nec_frame() {
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            decode(&mut decoder, &NEC_ON),
+            [Event {
+                key: ON,
+                repeat: false
+            }]
+        );
+    }
+
+    #[test]
+    fn nec_repeat() {
+        let mut decoder = Decoder::new();
+
+        // a repeat without a key is ignored
+        assert!(decode(&mut decoder, &NEC_REPEAT).is_empty());
+
+        decode(&mut decoder, &NEC_ON);
+        decoder.push(Pulse {
+            mark: false,
+            us: 40_000,
+        });
+        assert_eq!(
+            decode(&mut decoder, &NEC_REPEAT),
+            [Event {
+                key: ON,
+                repeat: true
+            }]
+        );
+
+        // after releasing the key, a repeat is ignored
+        decoder.push(IDLE);
+        assert!(decode(&mut decoder, &NEC_REPEAT).is_empty());
+    }
+
+    #[test]
+    fn nec_corrupted() {
+        let mut decoder = Decoder::new();
+
+        // a bit got lost
+        let mut pulses = NEC_ON;
+        pulses[20] = 2500;
+        assert!(decode(&mut decoder, &pulses).is_empty());
+
+        // the next frame is received
+        decoder.push(IDLE);
+        assert_eq!(decode(&mut decoder, &NEC_ON).len(), 1);
+
+        // the command check fails
+        let mut pulses = NEC_ON;
+        pulses[51] = 1650;
+        assert!(decode(&mut decoder, &pulses).is_empty());
+    }
+
+    #[test]
+    fn rc5_frame() {
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            decode(&mut decoder, &RC5_VOLUME_UP),
+            [Event {
+                key: VOLUME_UP,
+                repeat: false
+            }]
+        );
+        assert_eq!(
+            decode(&mut decoder, &RC5X),
+            [Event {
+                key: Key::Rc5 {
+                    address: 5,
+                    command: 0x45
+                },
+                repeat: false
+            }]
+        );
+    }
+
+    #[test]
+    fn rc5_repeat() {
+        let mut decoder = Decoder::new();
+        decode(&mut decoder, &RC5_VOLUME_UP);
+
+        // held, the same toggle bit
+        decoder.push(Pulse {
+            mark: false,
+            us: 89_000,
+        });
+        assert!(decode(&mut decoder, &RC5_VOLUME_UP)[0].repeat);
+
+        // pressed again, after releasing
+        decoder.push(IDLE);
+        assert!(!decode(&mut decoder, &RC5_VOLUME_UP)[0].repeat);
+    }
+
+    #[test]
+    fn rc5_rejects_noise() {
+        let mut decoder = Decoder::new();
+
+        let mut pulses = RC5_VOLUME_UP;
+        pulses[7] = 400;
+        assert!(decode(&mut decoder, &pulses).is_empty());
+
+        // a truncated frame is followed by a complete one
+        decoder.push(IDLE);
+        decode(&mut decoder, &RC5_VOLUME_UP[..9]);
+        decoder.push(IDLE);
+        assert_eq!(decode(&mut decoder, &RC5_VOLUME_UP).len(), 1);
+    }
+
+    #[test]
+    fn keymap() {
+        let mut keymap = Keymap::new();
+        assert_eq!(keymap.get(ON), Some(Action::On));
+        assert_eq!(keymap.get(VOLUME_UP), Some(Action::Lighter));
+
+        let key = Key::Nec {
+            address: 0xFF00,
+            command: 0x45,
+        };
+        assert_eq!(keymap.get(key), None);
+        assert!(keymap.bind(key, Action::Toggle));
+        assert_eq!(keymap.get(key), Some(Action::Toggle));
+
+        // rebind
+        assert!(keymap.bind(ON, Action::NextMode));
+        assert_eq!(keymap.get(ON), Some(Action::NextMode));
+    }
+
+    #[test]
+    fn action_names() {
+        assert_eq!(Action::from_name("toggle"), Some(Action::Toggle));
+        assert_eq!(
+            Action::from_name("white"),
+            Some(Action::Color(Color::WHITE))
+        );
+        assert_eq!(Action::from_name("dance"), None);
+        assert!(Action::names().all(|name| Action::from_name(name).is_some()));
+    }
+}
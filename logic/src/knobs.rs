@@ -0,0 +1,137 @@
+//! Potentiometer knobs, setting the brightness and the speed.
+//!
+//! The readings are smoothed, and only changes larger than a deadband are reported, so that noise
+//! doesn't turn into messages.
+
+/// Weight of a new reading in the average, as shift (1/4)
+const SMOOTHING: u32 = 2;
+/// Change of the reading, required before the position gets reported
+const DEADBAND: u16 = 24;
+/// Readings at the ends of the range, which potentiometers don't reach reliably
+const END_ZONE: u16 = 48;
+const FULL_SCALE: u16 = 4095;
+
+/// Tracks the position of a knob.
+pub struct Knob {
+    /// The smoothed reading, with additional bits of precision
+    average: Option<u32>,
+    /// The reading of the last reported position
+    reported: Option<u16>,
+}
+
+impl Knob {
+    pub const fn new() -> Self {
+        Self {
+            average: None,
+            reported: None,
+        }
+    }
+
+    /// Feed a new 12 bit reading, returning the position, if it changed.
+    pub fn update(&mut self, raw: i16) -> Option<u8> {
+        let raw = raw.clamp(0, FULL_SCALE as i16) as u32;
+        let average = match self.average {
+            Some(average) => average - (average >> SMOOTHING) + raw,
+            None => raw << SMOOTHING,
+        };
+        self.average = Some(average);
+
+        let reading = (average >> SMOOTHING) as u16;
+        match self.reported {
+            Some(reported) if reported.abs_diff(reading) < DEADBAND => None,
+            _ => {
+                // don't report the same position again, only the reading is different
+                let previous = self.reported.map(position);
+                self.reported = Some(reading);
+                Some(position(reading)).filter(|p| previous != Some(*p))
+            }
+        }
+    }
+}
+
+impl Default for Knob {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Map a reading to the position, from `0` to `255`.
+fn position(reading: u16) -> u8 {
+    let range = FULL_SCALE - 2 * END_ZONE;
+    let reading = reading.clamp(END_ZONE, FULL_SCALE - END_ZONE) - END_ZONE;
+    (reading as u32 * u8::MAX as u32 / range as u32) as u8
+}
+
+/// Map the position to the brightness, quadratically, giving finer control of dim settings.
+pub fn brightness(position: u8) -> u8 {
+    let position = position as u32;
+    (position * position / u8::MAX as u32) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settle(knob: &mut Knob, raw: i16) -> Option<u8> {
+        let mut last = None;
+        for _ in 0..20 {
+            last = knob.update(raw).or(last);
+        }
+        last
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(position(0), 0);
+        assert_eq!(position(END_ZONE), 0);
+        assert_eq!(position(2048), 127);
+        assert_eq!(position(FULL_SCALE - END_ZONE), 255);
+        assert_eq!(position(FULL_SCALE), 255);
+    }
+
+    #[test]
+    fn brightness_curve() {
+        assert_eq!(brightness(0), 0);
+        assert_eq!(brightness(16), 1);
+        assert_eq!(brightness(128), 64);
+        assert_eq!(brightness(255), 255);
+    }
+
+    #[test]
+    fn initial_position() {
+        let mut knob = Knob::new();
+        assert_eq!(knob.update(2048), Some(127));
+        assert_eq!(knob.update(2048), None);
+    }
+
+    #[test]
+    fn ignore_noise() {
+        let mut knob = Knob::new();
+        knob.update(2048);
+        // a noisy reading, jittering by a few LSB
+        let noise = [2050, 2041, 2060, 2039, 2055, 2048, 2030, 2066];
+        assert!(noise.iter().all(|raw| knob.update(*raw).is_none()));
+    }
+
+    #[test]
+    fn follow_turns() {
+        let mut knob = Knob::new();
+        knob.update(END_ZONE as i16);
+
+        // turning up slowly, the position follows monotonically
+        let mut positions = heapless::Vec::<u8, 256>::new();
+        for raw in (0..3000).step_by(8) {
+            if let Some(position) = knob.update(raw) {
+                positions.push(position).unwrap();
+            }
+        }
+        assert!(positions.windows(2).all(|w| w[1] > w[0]));
+
+        // and reaches the end, once turned up completely
+        assert_eq!(settle(&mut knob, FULL_SCALE as i16), Some(255));
+
+        // turned down quickly
+        assert_eq!(settle(&mut knob, 0), Some(0));
+        assert_eq!(knob.update(0), None);
+    }
+}
@@ -0,0 +1,21 @@
+#![no_std]
+#![feature(generic_associated_types)]
+
+//! The logic of the firmware which doesn't depend on the hardware: the state machines, the
+//! sensor signal processing and the light patterns.
+//!
+//! Like the protocol and console crates, this crate must stay free of hardware dependencies, so
+//! that its tests run on the host. The firmware wires it to the peripherals.
+
+pub mod accel;
+pub mod ambient;
+pub mod dfu;
+pub mod easing;
+pub mod ir;
+pub mod knobs;
+pub mod occupancy;
+pub mod pattern;
+pub mod rgb;
+pub mod rng;
+pub mod thermal;
+pub mod triggers;
@@ -0,0 +1,193 @@
+//! Switching the lights on and off, following the occupancy of the room.
+//!
+//! Motion switches the lights on, and they are switched off again after some time without
+//! motion. Controlling the lights manually overrides this for a while.
+
+use embassy_time::{Duration, Instant};
+
+/// Time without motion, after which the lights are switched off
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Time manual control overrides the motion control
+const OVERRIDE: Duration = Duration::from_secs(15 * 60);
+
+/// What to do with the lights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    FadeIn,
+    FadeOut,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// No motion seen yet, the lights are not controlled
+    Idle,
+    Occupied {
+        last_motion: Instant,
+    },
+    Vacant,
+    /// Manually controlled, ignoring motion
+    Override {
+        until: Instant,
+    },
+}
+
+pub struct Occupancy {
+    /// Time without motion, after which the lights are switched off. Disabled if `None`.
+    timeout: Option<Duration>,
+    state: State,
+}
+
+impl Occupancy {
+    pub const fn new(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            state: State::Idle,
+        }
+    }
+
+    /// Change the timeout, or disable the motion control.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+        if timeout.is_none() {
+            self.state = State::Idle;
+        }
+    }
+
+    /// Motion was detected.
+    pub fn motion(&mut self, now: Instant) -> Option<Action> {
+        self.timeout?;
+        match self.state {
+            State::Idle | State::Vacant => {
+                self.state = State::Occupied { last_motion: now };
+                Some(Action::FadeIn)
+            }
+            State::Occupied { .. } => {
+                self.state = State::Occupied { last_motion: now };
+                None
+            }
+            State::Override { .. } => None,
+        }
+    }
+
+    /// The lights were controlled manually.
+    pub fn manual(&mut self, now: Instant) {
+        if self.state != State::Idle {
+            self.state = State::Override {
+                until: now + OVERRIDE,
+            };
+        }
+    }
+
+    /// Check for timeouts.
+    pub fn poll(&mut self, now: Instant) -> Option<Action> {
+        let timeout = self.timeout?;
+        match self.state {
+            State::Occupied { last_motion } if now >= last_motion + timeout => {
+                self.state = State::Vacant;
+                Some(Action::FadeOut)
+            }
+            State::Override { until } if now >= until => {
+                // continue as if there was motion, so that the lights are switched off later
+                self.state = State::Occupied { last_motion: now };
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    enum Input {
+        Motion,
+        Manual,
+    }
+
+    /// Run a timeline of inputs (in seconds), polling every second, and collect the actions.
+    fn run(occupancy: &mut Occupancy, end: u64, inputs: &[(u64, Input)]) -> Vec<(u64, Action)> {
+        let mut actions = Vec::new();
+        for second in 0..=end {
+            let now = Instant::from_secs(second);
+            for (_, input) in inputs.iter().filter(|(t, _)| *t == second) {
+                let action = match input {
+                    Input::Motion => occupancy.motion(now),
+                    Input::Manual => {
+                        occupancy.manual(now);
+                        None
+                    }
+                };
+                actions.extend(action.map(|a| (second, a)));
+            }
+            actions.extend(occupancy.poll(now).map(|a| (second, a)));
+        }
+        actions
+    }
+
+    type Vec<T> = heapless::Vec<T, 16>;
+
+    #[test]
+    fn motion_timeout() {
+        let mut occupancy = Occupancy::new(Duration::from_secs(60));
+        let actions = run(
+            &mut occupancy,
+            300,
+            &[
+                (10, Input::Motion),
+                (40, Input::Motion),
+                (90, Input::Motion),
+                (200, Input::Motion),
+            ],
+        );
+
+        assert_eq!(
+            actions,
+            [
+                (10, Action::FadeIn),
+                (150, Action::FadeOut),
+                (200, Action::FadeIn),
+                (260, Action::FadeOut)
+            ]
+        );
+    }
+
+    #[test]
+    fn manual_override() {
+        let mut occupancy = Occupancy::new(Duration::from_secs(60));
+        let override_end = 30 + OVERRIDE.as_secs();
+        let actions = run(
+            &mut occupancy,
+            override_end + 120,
+            &[
+                (10, Input::Motion),
+                // switched off manually, motion is ignored
+                (30, Input::Manual),
+                (50, Input::Motion),
+                (100, Input::Motion),
+            ],
+        );
+
+        // after the override, the lights are switched off without motion
+        assert_eq!(
+            actions,
+            [(10, Action::FadeIn), (override_end + 60, Action::FadeOut)]
+        );
+    }
+
+    #[test]
+    fn manual_before_motion() {
+        // without motion, the lights are not controlled
+        let mut occupancy = Occupancy::new(Duration::from_secs(60));
+        let actions = run(&mut occupancy, 120, &[(10, Input::Manual)]);
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn disabled() {
+        let mut occupancy = Occupancy::new(Duration::from_secs(60));
+        occupancy.set_timeout(None);
+        let actions = run(&mut occupancy, 120, &[(10, Input::Motion)]);
+        assert!(actions.is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+//! One dimensional cellular automata.
+//!
+//! Cells are packed into bits, and a generation is computed a word at a time. The elementary rules
+//! use the Wolfram numbering, [`LIFE`] selects the one dimensional Game of Life (by Millen), which
+//! looks at two cells on each side.
+
+use crate::pattern::{Context, Steps};
+use crate::rgb::{Rgb8, BLACK};
+use crate::rng::Rng;
+
+const WORDS: usize = 8;
+/// The maximum number of cells, further pixels stay dark
+pub const MAX_CELLS: usize = WORDS * 32;
+/// The one dimensional Game of Life: cells are born with two or three live neighbours, and
+/// survive with two or four, out of four.
+pub const LIFE: u16 = 0x100;
+
+/// Generations per second, at the default speed one for each tick
+const RATE: f32 = 20.0;
+/// Step along the palette, for each generation a cell lives
+const AGE_STEP: u8 = 24;
+/// Step along the palette, for each generation of the history
+const HISTORY_STEP: u8 = 2;
+/// Seed of the first generation, for rules not starting from a single cell
+const SEED: u32 = 0x00C0_FFEE;
+
+/// A row of cells, alive or dead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cells {
+    /// bit `i % 32` of word `i / 32` is cell `i`, bits past the end are always clear
+    words: [u32; WORDS],
+    len: usize,
+}
+
+impl Cells {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: [0; WORDS],
+            len: len.min(MAX_CELLS),
+        }
+    }
+
+    pub fn random(len: usize, rng: &mut Rng) -> Self {
+        let mut cells = Self::new(len);
+        for word in &mut cells.words {
+            *word = rng.next_u32();
+        }
+        cells.mask();
+        cells
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len && self.words[i / 32] & 1 << (i % 32) != 0
+    }
+
+    pub fn set(&mut self, i: usize, alive: bool) {
+        if i < self.len {
+            if alive {
+                self.words[i / 32] |= 1 << (i % 32);
+            } else {
+                self.words[i / 32] &= !(1 << (i % 32));
+            }
+        }
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u32 {
+        self.words.iter().map(|w| w.count_ones()).sum()
+    }
+
+    /// Compute the next generation.
+    pub fn step(&self, rule: u16, wrap: bool) -> Self {
+        let mut next = Self::new(self.len);
+
+        if rule == LIFE {
+            let [a, b, c, d] = [-2, -1, 1, 2].map(|offset| self.neighbours(offset, wrap));
+            for (i, word) in next.words.iter_mut().enumerate() {
+                // add up the neighbours, each bit of the words counting for its cell
+                let (sum_ab, carry_ab) = (a[i] ^ b[i], a[i] & b[i]);
+                let (sum_cd, carry_cd) = (c[i] ^ d[i], c[i] & d[i]);
+                let ones = sum_ab ^ sum_cd;
+                let carry = sum_ab & sum_cd;
+                let twos = carry_ab ^ carry_cd ^ carry;
+                let fours = carry_ab & carry_cd;
+
+                let alive = self.words[i];
+                let born = !alive & twos & !fours;
+                let survive = alive & (twos & !ones | fours);
+                *word = born | survive;
+            }
+        } else {
+            let (left, right) = (self.neighbours(-1, wrap), self.neighbours(1, wrap));
+            for (i, word) in next.words.iter_mut().enumerate() {
+                let (l, c, r) = (left[i], self.words[i], right[i]);
+                for pattern in 0..8 {
+                    if rule & 1 << pattern != 0 {
+                        let select = |cells: u32, bit: u16| {
+                            if pattern & bit != 0 {
+                                cells
+                            } else {
+                                !cells
+                            }
+                        };
+                        *word |= select(l, 4) & select(c, 2) & select(r, 1);
+                    }
+                }
+            }
+        }
+
+        next.mask();
+        next
+    }
+
+    /// The neighbours at an offset: bit `i` is the cell at `i + offset`, for offsets up to `2`.
+    fn neighbours(&self, offset: isize, wrap: bool) -> [u32; WORDS] {
+        let shift = offset.unsigned_abs() as u32;
+        let mut result = [0; WORDS];
+        for (i, word) in result.iter_mut().enumerate() {
+            *word = if offset > 0 {
+                let next = self.words.get(i + 1).copied().unwrap_or(0);
+                self.words[i] >> shift | next << (32 - shift)
+            } else {
+                let prev = if i > 0 { self.words[i - 1] } else { 0 };
+                self.words[i] << shift | prev >> (32 - shift)
+            };
+        }
+
+        if wrap && self.len > 0 {
+            // the cells past one end are the ones at the other end
+            let len = self.len as isize;
+            let ends = if offset > 0 {
+                (len - offset).max(0)..len
+            } else {
+                0..(-offset).min(len)
+            };
+            for i in ends {
+                let source = (i + offset).rem_euclid(len) as usize;
+                let bit = 1 << (i % 32);
+                if self.get(source) {
+                    result[i as usize / 32] |= bit;
+                } else {
+                    result[i as usize / 32] &= !bit;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Clear the bits past the end.
+    fn mask(&mut self) {
+        for (i, word) in self.words.iter_mut().enumerate() {
+            *word &= match self.len.saturating_sub(i * 32) {
+                0 => 0,
+                n if n >= 32 => u32::MAX,
+                n => (1 << n) - 1,
+            };
+        }
+    }
+}
+
+/// The generations of an automaton, advancing with time.
+///
+/// `RULE` is the number of an elementary rule, or [`LIFE`]. With `WRAP`, the row is a ring,
+/// otherwise the cells past the ends are dead. With `RESEED`, the cells are randomized once they
+/// die out or stagnate, repeating the last or the last two generations.
+struct Generations<const RULE: u16, const WRAP: bool, const RESEED: bool> {
+    steps: Steps,
+    cells: Cells,
+    previous: Cells,
+}
+
+impl<const RULE: u16, const WRAP: bool, const RESEED: bool> Generations<RULE, WRAP, RESEED> {
+    fn new(len: usize) -> Self {
+        // the elementary rules look best growing from a single cell
+        let cells = if RULE == LIFE {
+            Cells::random(len, &mut Rng::new(SEED))
+        } else {
+            let mut cells = Cells::new(len);
+            cells.set(len.min(MAX_CELLS) / 2, true);
+            cells
+        };
+        Self {
+            steps: Steps::new(),
+            cells,
+            previous: cells,
+        }
+    }
+
+    /// Advance by a time step, calling `f` with each new generation.
+    fn advance(&mut self, dt: f32, rng: &mut Rng, mut f: impl FnMut(&Cells)) {
+        for _ in 0..self.steps.advance(dt, RATE) {
+            self.step(rng);
+            f(&self.cells);
+        }
+    }
+
+    fn step(&mut self, rng: &mut Rng) {
+        let next = self.cells.step(RULE, WRAP);
+        let stagnant = next == self.cells || next == self.previous || next.population() == 0;
+        self.previous = self.cells;
+        self.cells = if RESEED && stagnant {
+            Cells::random(self.cells.len(), rng)
+        } else {
+            next
+        };
+    }
+}
+
+/// The live cells of an automaton, colored from the palette by their age.
+pub struct Automaton<const N: usize, const RULE: u16, const WRAP: bool, const RESEED: bool> {
+    generations: Generations<RULE, WRAP, RESEED>,
+    /// the position in the palette of each cell, advancing while it lives
+    ages: [u8; N],
+}
+
+impl<const N: usize, const RULE: u16, const WRAP: bool, const RESEED: bool>
+    Automaton<N, RULE, WRAP, RESEED>
+{
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            generations: Generations::new(N),
+            ages: [0; N],
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let mut rng = ctx.rng;
+        self.advance(ctx.time_step(), &mut rng);
+
+        let cells = &self.generations.cells;
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = if cells.get(i) {
+                ctx.palette.sample(self.ages[i])
+            } else {
+                BLACK
+            };
+        }
+    }
+
+    fn advance(&mut self, dt: f32, rng: &mut Rng) {
+        let ages = &mut self.ages;
+        self.generations.advance(dt, rng, |cells| {
+            for (i, age) in ages.iter_mut().enumerate() {
+                *age = if cells.get(i) {
+                    age.saturating_add(AGE_STEP)
+                } else {
+                    0
+                };
+            }
+        });
+    }
+}
+
+/// The history of the middle cell of an automaton, scrolling along the strip, with the newest
+/// generation at the start.
+pub struct History<const N: usize, const RULE: u16, const WRAP: bool, const RESEED: bool> {
+    generations: Generations<RULE, WRAP, RESEED>,
+    /// the position in the palette, advancing with each generation
+    color: u8,
+}
+
+impl<const N: usize, const RULE: u16, const WRAP: bool, const RESEED: bool>
+    History<N, RULE, WRAP, RESEED>
+{
+    pub fn new(pixels: &mut [Rgb8; N]) -> Self {
+        pixels.fill(BLACK);
+        Self {
+            generations: Generations::new(N),
+            color: 0,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let mut rng = ctx.rng;
+        let gradient = ctx.palette.gradient();
+        let color = &mut self.color;
+        self.generations
+            .advance(ctx.time_step(), &mut rng, |cells| {
+                *color = color.wrapping_add(HISTORY_STEP);
+                let alive = cells.get(cells.len() / 2);
+                Self::scroll(
+                    pixels,
+                    if alive {
+                        gradient.sample(*color)
+                    } else {
+                        BLACK
+                    },
+                );
+            });
+    }
+
+    fn scroll(pixels: &mut [Rgb8; N], pixel: Rgb8) {
+        if N > 0 {
+            pixels.copy_within(0..N - 1, 1);
+            pixels[0] = pixel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Parse cells, `#` being alive.
+    fn cells(row: &str) -> Cells {
+        let mut cells = Cells::new(row.len());
+        for (i, c) in row.chars().enumerate() {
+            cells.set(i, c == '#');
+        }
+        cells
+    }
+
+    /// Step a cell at a time, as a reference.
+    fn reference(cells: &Cells, rule: u16, wrap: bool) -> Cells {
+        let len = cells.len() as isize;
+        let cell = |i: isize| {
+            if wrap {
+                cells.get(i.rem_euclid(len) as usize)
+            } else {
+                i >= 0 && cells.get(i as usize)
+            }
+        };
+
+        let mut next = Cells::new(cells.len());
+        for i in 0..len {
+            let alive = if rule == LIFE {
+                let neighbours = [-2, -1, 1, 2].iter().filter(|o| cell(i + *o)).count();
+                match cell(i) {
+                    true => neighbours == 2 || neighbours == 4,
+                    false => neighbours == 2 || neighbours == 3,
+                }
+            } else {
+                let pattern =
+                    (cell(i - 1) as u16) << 2 | (cell(i) as u16) << 1 | cell(i + 1) as u16;
+                rule & 1 << pattern != 0
+            };
+            next.set(i as usize, alive);
+        }
+        next
+    }
+
+    #[test]
+    fn rule_30() {
+        let rows = [
+            "......#......",
+            ".....###.....",
+            "....##..#....",
+            "...##.####...",
+            "..##..#...#..",
+            ".##.####.###.",
+        ];
+        for window in rows.windows(2) {
+            assert_eq!(cells(window[0]).step(30, false), cells(window[1]));
+        }
+    }
+
+    #[test]
+    fn rule_110() {
+        let rows = [
+            ".......#", "......##", ".....###", "....##.#", "...#####", "..##...#", ".###..##",
+            "##.#.###",
+        ];
+        for window in rows.windows(2) {
+            assert_eq!(cells(window[0]).step(110, false), cells(window[1]));
+        }
+    }
+
+    #[test]
+    fn wraparound() {
+        assert_eq!(cells("#......").step(30, true), cells("##....#"));
+        assert_eq!(cells("#......").step(30, false), cells("##....."));
+        assert_eq!(cells("......#").step(30, false), cells(".....##"));
+        assert_eq!(cells("......#").step(30, true), cells("#....##"));
+    }
+
+    #[test]
+    fn bit_packed() {
+        let mut rng = Rng::new(7);
+        for len in [1, 2, 5, 31, 32, 33, 64, 70, 100, MAX_CELLS] {
+            for rule in [30, 90, 110, 184, LIFE] {
+                for wrap in [false, true] {
+                    let mut cells = Cells::random(len, &mut rng);
+                    for _ in 0..20 {
+                        let next = cells.step(rule, wrap);
+                        assert_eq!(
+                            next,
+                            reference(&cells, rule, wrap),
+                            "{} {} {}",
+                            len,
+                            rule,
+                            wrap
+                        );
+                        cells = next;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn center_column() {
+        // the middle cell of rule 30, growing from a single cell
+        let mut generations = Generations::<30, true, false>::new(60);
+        let mut column = [false; 20];
+        let mut i = 0;
+        generations.advance(1.0, &mut Rng::new(1), |cells| {
+            column[i] = cells.get(30);
+            i += 1;
+        });
+        assert_eq!(i, 20);
+        assert_eq!(
+            column.map(|alive| alive as u8),
+            [1, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 1, 1, 0, 0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn reseed() {
+        let mut rng = Rng::new(3);
+
+        // rule 4 keeps single cells alive, without changing
+        let mut generations = Generations::<4, true, false>::new(40);
+        let start = generations.cells;
+        generations.step(&mut rng);
+        assert_eq!(generations.cells, start);
+
+        let mut generations = Generations::<4, true, true>::new(40);
+        generations.step(&mut rng);
+        assert_ne!(generations.cells, start);
+
+        // a blinker, repeating every second generation
+        let mut generations = Generations::<LIFE, true, true>::new(13);
+        generations.cells = cells("........##...");
+        generations.previous = generations.cells;
+        generations.step(&mut rng);
+        assert_eq!(generations.cells, cells(".......#..#.."));
+        generations.step(&mut rng);
+        assert_ne!(generations.cells, cells("........##..."));
+        assert_ne!(generations.cells, cells(".......#..#.."));
+
+        // deterministic, for the same random numbers
+        let run = |seed| {
+            let mut generations = Generations::<LIFE, true, true>::new(64);
+            let mut rng = Rng::new(seed);
+            for _ in 0..100 {
+                generations.step(&mut rng);
+            }
+            generations.cells
+        };
+        assert_eq!(run(5), run(5));
+    }
+
+    #[test]
+    fn ages() {
+        let mut automaton = Automaton::<8, 4, false, false>::new(&mut [BLACK; 8]);
+        let mut rng = Rng::new(1);
+        automaton.advance(0.16, &mut rng);
+        assert_eq!(automaton.ages, [0, 0, 0, 0, 3 * AGE_STEP, 0, 0, 0]);
+    }
+
+    #[test]
+    fn scroll() {
+        let mut pixels = [BLACK; 3];
+        let red = Rgb8::new(255, 0, 0);
+        History::<3, 30, true, true>::scroll(&mut pixels, red);
+        History::<3, 30, true, true>::scroll(&mut pixels, BLACK);
+        assert_eq!(pixels, [BLACK, red, BLACK]);
+    }
+}
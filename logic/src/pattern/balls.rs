@@ -0,0 +1,75 @@
+use crate::pattern::{
+    particles::{Particle, Particles},
+    Context,
+};
+use crate::rgb::Rgb8;
+
+const BALLS: usize = 3;
+/// Gravity, pulling towards the start of the strip, in pixels/s²
+const GRAVITY: f32 = 40.0;
+/// Velocity kept when bouncing, for each ball
+const BOUNCE: [f32; BALLS] = [0.9, 0.85, 0.8];
+/// Velocity, below which a ball is kicked up again, in pixels/s
+const MIN_VELOCITY: f32 = 5.0;
+/// Time for the trail to fade, in seconds
+const TRAIL: f32 = 0.05;
+
+/// Balls bouncing on the start of the strip, kicked up again once they come to rest.
+pub struct BouncingBalls<const N: usize> {
+    particles: Particles<N, BALLS>,
+}
+
+impl<const N: usize> BouncingBalls<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        let mut particles = Particles::new(TRAIL);
+        for bounce in BOUNCE {
+            particles.spawn(Particle {
+                acceleration: -GRAVITY,
+                bounce: Some(bounce),
+                ..Particle::new(0.0, Self::kick(), Rgb8::new(0, 0, 0))
+            });
+        }
+        Self { particles }
+    }
+
+    /// The velocity reaching the end of the strip.
+    fn kick() -> f32 {
+        libm::sqrtf(2.0 * GRAVITY * N as f32)
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let dt = ctx.time_step();
+        self.advance(dt, |i| ctx.palette.sample((i * 256 / BALLS) as u8));
+        self.particles.render(dt, pixels);
+    }
+
+    fn advance(&mut self, dt: f32, color: impl Fn(usize) -> Rgb8) {
+        self.particles.step(dt, |_| {});
+        for (i, ball) in self.particles.iter_mut().enumerate() {
+            ball.color = color(i);
+            if ball.position < 1.0 && libm::fabsf(ball.velocity) < MIN_VELOCITY {
+                ball.velocity = Self::kick();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keep_bouncing() {
+        let mut balls = BouncingBalls::<60>::new(&mut [Rgb8::new(0, 0, 0); 60]);
+        let mut highest = [0f32; BALLS];
+        for _ in 0..60 * 20 {
+            balls.advance(0.05, |_| Rgb8::new(0, 0, 0));
+            assert_eq!(balls.particles.len(), BALLS);
+            for (i, ball) in balls.particles.iter().enumerate() {
+                assert!((0.0..=60.0).contains(&ball.position));
+                highest[i] = highest[i].max(ball.position);
+            }
+        }
+        assert!(highest.iter().all(|h| *h > 30.0), "{:?}", highest);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::easing::{Easing, Keyframes};
+use crate::pattern::{scale, Context};
+use crate::rgb::Rgb8;
+
+/// The level of a heartbeat, with a strong and a weaker beat
+const HEARTBEAT: Keyframes =
+    Keyframes::new(&[(0, 0), (20, 255), (70, 0), (100, 160), (160, 0), (255, 0)]);
+
+/// The selected color, fading in and out following the easing curve.
+///
+/// `PERIOD_MS` is the time for a full breath, in and out.
+pub struct Breathing<const N: usize, const PERIOD_MS: u16> {
+    /// the phase of the breath, from `0.0` to `1.0`
+    phase: f32,
+}
+
+impl<const N: usize, const PERIOD_MS: u16> Breathing<N, PERIOD_MS> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self { phase: 0.0 }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        self.phase = libm::fmodf(
+            self.phase + ctx.time_step() * 1000.0 / PERIOD_MS as f32,
+            1.0,
+        );
+        pixels.fill(scale(ctx.color, Self::level(self.phase, ctx.easing)));
+    }
+
+    fn level(phase: f32, easing: Easing) -> f32 {
+        // in during the first half, and out again
+        let progress = 1.0 - libm::fabsf(2.0 * phase - 1.0);
+        easing.ease(progress)
+    }
+}
+
+/// The selected color, beating like a heart.
+///
+/// `BPM` is the number of heartbeats per minute. The easing curve shapes each beat.
+pub struct Pulse<const N: usize, const BPM: u8> {
+    /// the phase of the heartbeat, from `0.0` to `1.0`
+    phase: f32,
+}
+
+impl<const N: usize, const BPM: u8> Pulse<N, BPM> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self { phase: 0.0 }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        self.phase = libm::fmodf(self.phase + ctx.time_step() * BPM as f32 / 60.0, 1.0);
+        pixels.fill(scale(ctx.color, HEARTBEAT.sample(self.phase, ctx.easing)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn breathing() {
+        type Breath = Breathing<1, 4000>;
+        for easing in [Easing::Linear, Easing::Sine, Easing::Exponential] {
+            assert_eq!(Breath::level(0.0, easing), 0.0);
+            assert_eq!(Breath::level(0.5, easing), 1.0);
+            assert_eq!(Breath::level(1.0, easing), 0.0);
+            // symmetric
+            assert_eq!(Breath::level(0.2, easing), Breath::level(0.8, easing));
+        }
+        assert_eq!(Breath::level(0.125, Easing::Linear), 0.25);
+        assert!(Breath::level(0.125, Easing::Sine) < 0.25);
+    }
+
+    #[test]
+    fn heartbeat() {
+        // two beats, the second one weaker, and a pause
+        let levels: [f32; 101] =
+            core::array::from_fn(|i| HEARTBEAT.sample(i as f32 / 100.0, Easing::Sine));
+        let mut peaks = levels
+            .windows(3)
+            .filter(|w| w[1] > w[0] && w[1] >= w[2])
+            .map(|w| w[1]);
+        match [peaks.next(), peaks.next(), peaks.next()] {
+            [Some(first), Some(second), None] => assert!(first > second),
+            peaks => panic!("{:?}", peaks),
+        }
+        assert!(levels[70..].iter().all(|l| *l == 0.0));
+    }
+}
@@ -0,0 +1,236 @@
+//! The classic chase effects, moving in steps along the strip.
+//!
+//! All of them use [`Steps`], so that they follow the speed the same way.
+
+use crate::pattern::{scale, Context, Steps};
+use crate::rgb::{Rgb8, BLACK};
+
+/// Steps per second, at the default speed
+const RATE: f32 = 10.0;
+/// Steps per second of the scanner, which moves a pixel per step
+const SCAN_RATE: f32 = 40.0;
+/// Level of the tail of the scanner kept, for each step, out of `256`
+const TAIL: u16 = 192;
+/// Step along the palette, for each wipe
+const WIPE_STEP: u8 = 64;
+
+/// Every `SPACING`th pixel lit in the selected color, marching along the strip.
+pub struct TheaterChase<const N: usize, const SPACING: usize> {
+    steps: Steps,
+    offset: usize,
+}
+
+impl<const N: usize, const SPACING: usize> TheaterChase<N, SPACING> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            steps: Steps::new(),
+            offset: 0,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        self.advance(ctx.time_step());
+        self.render(pixels, ctx.color);
+    }
+
+    fn advance(&mut self, dt: f32) {
+        let steps = self.steps.advance(dt, RATE) as usize;
+        self.offset = (self.offset + steps) % SPACING;
+    }
+
+    fn render(&self, pixels: &mut [Rgb8; N], color: Rgb8) {
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = if i % SPACING == self.offset {
+                color
+            } else {
+                BLACK
+            };
+        }
+    }
+}
+
+/// A scanner moving back and forth in the selected color, with a fading tail.
+pub struct Larson<const N: usize> {
+    steps: Steps,
+    position: usize,
+    forward: bool,
+    /// the level of each pixel, fading after the scanner passed
+    levels: [u8; N],
+}
+
+impl<const N: usize> Larson<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        let mut levels = [0; N];
+        levels[0] = u8::MAX;
+        Self {
+            steps: Steps::new(),
+            position: 0,
+            forward: true,
+            levels,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        self.advance(ctx.time_step());
+        for (pixel, level) in pixels.iter_mut().zip(self.levels) {
+            *pixel = scale(ctx.color, level as f32 / 255.0);
+        }
+    }
+
+    fn advance(&mut self, dt: f32) {
+        for _ in 0..self.steps.advance(dt, SCAN_RATE) {
+            for level in &mut self.levels {
+                *level = (*level as u16 * TAIL / 256) as u8;
+            }
+
+            if N > 1 {
+                if self.forward && self.position == N - 1 {
+                    self.forward = false;
+                } else if !self.forward && self.position == 0 {
+                    self.forward = true;
+                }
+                if self.forward {
+                    self.position += 1;
+                } else {
+                    self.position -= 1;
+                }
+            }
+            self.levels[self.position] = u8::MAX;
+        }
+    }
+}
+
+/// Filling the strip pixel by pixel, with the next color of the palette each time.
+pub struct ColorWipe<const N: usize> {
+    steps: Steps,
+    /// the number of pixels filled with the new color
+    filled: usize,
+    /// the position of the new color in the palette
+    color: u8,
+}
+
+impl<const N: usize> ColorWipe<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            steps: Steps::new(),
+            filled: 0,
+            color: WIPE_STEP,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        self.advance(ctx.time_step());
+
+        let (new, old) = (
+            ctx.palette.sample(self.color),
+            ctx.palette.sample(self.color.wrapping_sub(WIPE_STEP)),
+        );
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = if i < self.filled { new } else { old };
+        }
+    }
+
+    fn advance(&mut self, dt: f32) {
+        // wiping the whole strip takes as long as ten steps of the other chases
+        for _ in 0..self.steps.advance(dt, RATE * N as f32 / 10.0) {
+            self.filled += 1;
+            if self.filled > N {
+                self.filled = 1;
+                self.color = self.color.wrapping_add(WIPE_STEP);
+            }
+        }
+    }
+}
+
+/// Smooth waves of the selected color, running along the strip.
+///
+/// `WAVES` is the number of waves on the strip at once.
+pub struct RunningLights<const N: usize, const WAVES: usize> {
+    /// the phase of the waves, from `0.0` to `1.0`
+    phase: f32,
+}
+
+impl<const N: usize, const WAVES: usize> RunningLights<N, WAVES> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self { phase: 0.0 }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        // moving by a pixel for each step of the other chases
+        let wave = N as f32 / WAVES as f32;
+        self.phase = libm::fmodf(self.phase + ctx.time_step() * RATE / wave, 1.0);
+
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = scale(ctx.color, Self::level(i, self.phase));
+        }
+    }
+
+    fn level(i: usize, phase: f32) -> f32 {
+        let angle = (i as f32 * WAVES as f32 / N as f32 - phase) * 2.0 * core::f32::consts::PI;
+        (libm::sinf(angle) + 1.0) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::RED;
+
+    #[test]
+    fn theater_chase() {
+        let mut chase = TheaterChase::<7, 3>::new(&mut [BLACK; 7]);
+        let mut pixels = [BLACK; 7];
+
+        chase.render(&mut pixels, RED);
+        assert_eq!(pixels, [RED, BLACK, BLACK, RED, BLACK, BLACK, RED]);
+
+        // less than a step
+        chase.advance(0.05);
+        chase.render(&mut pixels, RED);
+        assert_eq!(pixels, [RED, BLACK, BLACK, RED, BLACK, BLACK, RED]);
+
+        chase.advance(0.05);
+        chase.render(&mut pixels, RED);
+        assert_eq!(pixels, [BLACK, RED, BLACK, BLACK, RED, BLACK, BLACK]);
+
+        // two steps at once
+        chase.advance(0.2);
+        assert_eq!(chase.offset, 0);
+    }
+
+    #[test]
+    fn larson() {
+        let mut larson = Larson::<4>::new(&mut [BLACK; 4]);
+        let mut positions = [0; 8];
+        for position in &mut positions {
+            larson.advance(1.0 / SCAN_RATE);
+            *position = larson.position;
+        }
+        assert_eq!(positions, [1, 2, 3, 2, 1, 0, 1, 2]);
+        // with a tail, fading
+        assert_eq!(larson.levels, [143, 191, 255, 60]);
+    }
+
+    #[test]
+    fn color_wipe() {
+        let mut wipe = ColorWipe::<10>::new(&mut [BLACK; 10]);
+        wipe.advance(0.5);
+        assert_eq!((wipe.filled, wipe.color), (5, WIPE_STEP));
+        wipe.advance(0.5);
+        assert_eq!((wipe.filled, wipe.color), (10, WIPE_STEP));
+        wipe.advance(0.1);
+        assert_eq!((wipe.filled, wipe.color), (1, 2 * WIPE_STEP));
+    }
+
+    #[test]
+    fn running_lights() {
+        type Lights = RunningLights<8, 2>;
+        let levels = |phase| -> [f32; 8] {
+            core::array::from_fn(|i| libm::roundf(Lights::level(i, phase) * 10.0) / 10.0)
+        };
+
+        assert_eq!(levels(0.0), [0.5, 1.0, 0.5, 0.0, 0.5, 1.0, 0.5, 0.0]);
+        // moving along the strip
+        assert_eq!(levels(0.25), [0.0, 0.5, 1.0, 0.5, 0.0, 0.5, 1.0, 0.5]);
+    }
+}
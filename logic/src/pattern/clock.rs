@@ -0,0 +1,159 @@
+use crate::pattern::{add, Context};
+use crate::rgb::{Rgb8, BLACK};
+use embassy_time::Duration;
+
+pub const MILLIS_PER_DAY: u32 = 24 * 60 * 60 * 1000;
+const MILLIS_PER_MINUTE: u32 = 60 * 1000;
+const MILLIS_PER_HOUR: u32 = 60 * MILLIS_PER_MINUTE;
+
+const HOURS: Rgb8 = Rgb8::new(0xFF, 0x00, 0x00);
+const MINUTES: Rgb8 = Rgb8::new(0x00, 0xFF, 0x00);
+const SECONDS: Rgb8 = Rgb8::new(0x00, 0x00, 0xFF);
+/// The marks at twelve, three, six and nine o'clock
+const MARKS: Rgb8 = Rgb8::new(0x10, 0x10, 0x10);
+/// Time for switching on or off, blinking while the time is not set
+const BLINK_MS: u64 = 500;
+
+/// A clock on a ring, showing the hours, minutes and seconds of the wall clock.
+///
+/// `OFFSET` is the pixel at twelve o'clock. With `CLOCKWISE`, the hands move along the strip,
+/// otherwise backwards. Until the wall clock is set, it blinks at twelve, like an unset VCR.
+pub struct Clock<const N: usize, const OFFSET: usize, const CLOCKWISE: bool> {
+    /// the time running, for blinking
+    elapsed: Duration,
+}
+
+impl<const N: usize, const OFFSET: usize, const CLOCKWISE: bool> Clock<N, OFFSET, CLOCKWISE> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            elapsed: Duration::from_ticks(0),
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        // a clock follows the time, not the speed
+        self.elapsed += ctx.delta;
+        match ctx.time_of_day {
+            Some(millis) => Self::fill(pixels, millis),
+            None => Self::not_set(pixels, self.elapsed.as_millis()),
+        }
+    }
+
+    fn fill(pixels: &mut [Rgb8; N], millis: u32) {
+        pixels.fill(BLACK);
+        for quarter in 0..4 {
+            pixels[Self::pixel(quarter, 4)] = MARKS;
+        }
+
+        let hands = [
+            (millis % (MILLIS_PER_DAY / 2), MILLIS_PER_DAY / 2, HOURS),
+            (millis % MILLIS_PER_HOUR, MILLIS_PER_HOUR, MINUTES),
+            (millis % MILLIS_PER_MINUTE, MILLIS_PER_MINUTE, SECONDS),
+        ];
+        for (value, range, color) in hands {
+            // hands on the same pixel mix
+            let pixel = &mut pixels[Self::pixel(value, range)];
+            *pixel = add(*pixel, color);
+        }
+    }
+
+    fn not_set(pixels: &mut [Rgb8; N], now: u64) {
+        if (now / BLINK_MS) & 1 == 0 {
+            Self::fill(pixels, 0);
+        } else {
+            pixels.fill(BLACK);
+        }
+    }
+
+    /// The pixel showing `value` of `range`, a full turn around the ring.
+    fn pixel(value: u32, range: u32) -> usize {
+        let position = (value as u64 * N as u64 / range as u64) as usize % N;
+        if CLOCKWISE {
+            (OFFSET + position) % N
+        } else {
+            (OFFSET + N - position) % N
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn time(hours: u32, minutes: u32, seconds: u32) -> u32 {
+        ((hours * 60 + minutes) * 60 + seconds) * 1000
+    }
+
+    /// Check each pixel is reached exactly at its part of a turn.
+    fn positions<const N: usize, const OFFSET: usize, const CLOCKWISE: bool>() {
+        for range in [MILLIS_PER_MINUTE, MILLIS_PER_HOUR, MILLIS_PER_DAY / 2] {
+            for i in 0..N {
+                // the first time at or after the exact part of the turn, rounding up
+                let part = i as u64 * range as u64;
+                let start = match part {
+                    0 => 0,
+                    _ => ((part - 1) / N as u64 + 1) as u32,
+                };
+                let expected = match CLOCKWISE {
+                    true => (OFFSET + i) % N,
+                    false => (OFFSET + N - i) % N,
+                };
+                assert_eq!(Clock::<N, OFFSET, CLOCKWISE>::pixel(start, range), expected);
+                if start > 0 {
+                    let before = Clock::<N, OFFSET, CLOCKWISE>::pixel(start - 1, range);
+                    assert_ne!(before, expected, "{} {} {}", N, range, i);
+                }
+            }
+            // the full turn is back at twelve o'clock
+            assert_eq!(
+                Clock::<N, OFFSET, CLOCKWISE>::pixel(range, range),
+                OFFSET % N
+            );
+        }
+    }
+
+    #[test]
+    fn pixel() {
+        positions::<12, 0, true>();
+        positions::<16, 4, false>();
+        positions::<24, 23, true>();
+        positions::<60, 30, false>();
+        positions::<61, 0, true>();
+
+        type Ring = Clock<60, 0, true>;
+        assert_eq!(Ring::pixel(time(3, 0, 0), MILLIS_PER_DAY / 2), 15);
+        assert_eq!(Ring::pixel(time(0, 59, 59) + 999, MILLIS_PER_HOUR), 59);
+        assert_eq!(Ring::pixel(time(0, 0, 1), MILLIS_PER_MINUTE), 1);
+
+        // counting backwards, three o'clock comes before twelve
+        type Reversed = Clock<12, 6, false>;
+        assert_eq!(Reversed::pixel(1, 4), 3);
+        assert_eq!(Reversed::pixel(3, 4), 9);
+    }
+
+    #[test]
+    fn fill() {
+        let mut pixels = [BLACK; 12];
+        Clock::<12, 0, true>::fill(&mut pixels, time(15, 30, 45));
+        assert_eq!(pixels[3], add(MARKS, HOURS));
+        assert_eq!(pixels[6], add(MARKS, MINUTES));
+        assert_eq!(pixels[9], add(MARKS, SECONDS));
+        assert_eq!(pixels[0], MARKS);
+        assert_eq!(pixels.iter().filter(|p| **p != BLACK).count(), 4);
+
+        // all at twelve, mixing to white
+        Clock::<12, 0, true>::fill(&mut pixels, time(12, 0, 0));
+        assert_eq!(pixels[0], Rgb8::new(0xFF, 0xFF, 0xFF));
+    }
+
+    #[test]
+    fn not_set() {
+        let mut pixels = [BLACK; 12];
+        Clock::<12, 2, true>::not_set(&mut pixels, 0);
+        assert_eq!(pixels[2], Rgb8::new(0xFF, 0xFF, 0xFF));
+        Clock::<12, 2, true>::not_set(&mut pixels, BLINK_MS);
+        assert_eq!(pixels, [BLACK; 12]);
+        Clock::<12, 2, true>::not_set(&mut pixels, 2 * BLINK_MS + 1);
+        assert_eq!(pixels[2], Rgb8::new(0xFF, 0xFF, 0xFF));
+    }
+}
@@ -0,0 +1,39 @@
+use crate::pattern::{
+    particles::{Particle, Particles},
+    Context,
+};
+use crate::rgb::Rgb8;
+
+/// Velocity of the comet, in pixels/s
+const VELOCITY: f32 = 30.0;
+/// Time for the tail to fade, in seconds
+const TAIL: f32 = 0.3;
+/// Step along the palette, for each new comet
+const COLOR_STEP: u8 = 40;
+
+/// Comets shooting along the strip, with a fading tail, each in the next color of the palette.
+pub struct Comet<const N: usize> {
+    particles: Particles<N, 1>,
+    color: u8,
+}
+
+impl<const N: usize> Comet<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            particles: Particles::new(TAIL),
+            color: 0,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let dt = ctx.time_step();
+
+        if self.particles.is_empty() {
+            let color = ctx.palette.sample(self.color);
+            self.color = self.color.wrapping_add(COLOR_STEP);
+            self.particles.spawn(Particle::new(0.0, VELOCITY, color));
+        }
+        self.particles.step(dt, |_| {});
+        self.particles.render(dt, pixels);
+    }
+}
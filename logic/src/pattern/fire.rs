@@ -0,0 +1,155 @@
+use crate::pattern::Context;
+use crate::rgb::Rgb8;
+use crate::rng::Rng;
+use embassy_time::Duration;
+
+/// Number of pixels at the start of the strip, where sparks ignite
+const SPARK_ZONE: usize = 7;
+/// Maximum number of steps simulated in one tick, skipping longer pauses
+const MAX_STEPS: u32 = 8;
+
+/// A fire, simulating heat rising from the start of the strip.
+///
+/// Each step, all cells cool down a bit, heat drifts up and diffuses, and new sparks may ignite
+/// near the start. `COOLING` is how fast the flames cool down, `SPARKING` the chance (out of
+/// `255`) of a new spark. The heat is mapped through the palette, from cold to hot.
+pub struct Fire<const N: usize, const COOLING: u8 = 55, const SPARKING: u8 = 120> {
+    heat: [u8; N],
+    /// time not simulated yet
+    pending: Duration,
+}
+
+impl<const N: usize, const COOLING: u8, const SPARKING: u8> Fire<N, COOLING, SPARKING> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            heat: [0; N],
+            pending: Duration::from_ticks(0),
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let mut rng = ctx.rng;
+        self.advance(ctx.delta, ctx.speed, &mut rng);
+
+        let gradient = ctx.palette.gradient();
+        for (pixel, heat) in pixels.iter_mut().zip(self.heat) {
+            *pixel = gradient.sample(heat);
+        }
+    }
+
+    /// Simulate the time passed, in steps depending on the speed.
+    pub fn advance(&mut self, delta: Duration, speed: u8, rng: &mut Rng) {
+        let step = Self::step_len(speed);
+        self.pending += delta;
+
+        let mut steps = 0;
+        while self.pending >= step && steps < MAX_STEPS {
+            self.pending -= step;
+            self.step(rng);
+            steps += 1;
+        }
+        // skip the rest of a longer pause
+        self.pending = Duration::from_ticks(self.pending.as_ticks() % step.as_ticks());
+    }
+
+    /// The time of a step, from 60ms (slowest) to 20ms (fastest).
+    fn step_len(speed: u8) -> Duration {
+        Duration::from_millis(60 - 40 * speed as u64 / u8::MAX as u64)
+    }
+
+    fn step(&mut self, rng: &mut Rng) {
+        // cool down
+        let cooling = COOLING as u32 * 10 / N as u32 + 2;
+        for heat in &mut self.heat {
+            *heat = heat.saturating_sub(rng.below(cooling) as u8);
+        }
+
+        // drift up and diffuse
+        for i in (2..N).rev() {
+            self.heat[i] = ((self.heat[i - 1] as u16 + 2 * self.heat[i - 2] as u16) / 3) as u8;
+        }
+
+        // ignite
+        if rng.next_u8() < SPARKING {
+            let i = rng.below(SPARK_ZONE.min(N) as u32) as usize;
+            self.heat[i] = self.heat[i].saturating_add(160 + rng.below(96) as u8);
+        }
+    }
+
+    pub fn heat(&self) -> &[u8; N] {
+        &self.heat
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::BLACK;
+
+    /// a step, at the default speed
+    const STEP: Duration = Duration::from_millis(41);
+
+    #[test]
+    fn golden() {
+        assert_eq!(Fire::<16>::step_len(127), STEP);
+
+        let mut fire = Fire::<16>::new(&mut [BLACK; 16]);
+        let mut rng = Rng::new(1);
+        for _ in 0..20 {
+            fire.advance(STEP, 127, &mut rng);
+        }
+        assert_eq!(
+            fire.heat(),
+            &[26, 205, 85, 162, 108, 149, 115, 128, 120, 128, 70, 10, 22, 72, 116, 122]
+        );
+    }
+
+    #[test]
+    fn frame_rate_independent() {
+        let (mut a, mut rng_a) = (Fire::<16>::new(&mut [BLACK; 16]), Rng::new(7));
+        let (mut b, mut rng_b) = (Fire::<16>::new(&mut [BLACK; 16]), Rng::new(7));
+
+        // 48 steps, in ticks of 10ms and 100ms
+        for _ in 0..200 {
+            a.advance(Duration::from_millis(10), 127, &mut rng_a);
+        }
+        for _ in 0..20 {
+            b.advance(Duration::from_millis(100), 127, &mut rng_b);
+        }
+        assert_eq!(a.heat(), b.heat());
+        assert_eq!(a.pending, b.pending);
+    }
+
+    #[test]
+    fn parameters() {
+        // without sparks, the fire goes out
+        let mut fire = Fire::<16, 55, 0>::new(&mut [BLACK; 16]);
+        let mut rng = Rng::new(3);
+        fire.heat = [200; 16];
+        for _ in 0..200 {
+            fire.advance(STEP, 127, &mut rng);
+        }
+        assert_eq!(fire.heat(), &[0; 16]);
+
+        // with less cooling, flames get higher
+        let total = |heat: &[u8; 30]| heat.iter().map(|h| *h as u32).sum::<u32>();
+        let mut hot = Fire::<30, 20, 200>::new(&mut [BLACK; 30]);
+        let mut cold = Fire::<30, 100, 200>::new(&mut [BLACK; 30]);
+        let (mut rng_hot, mut rng_cold) = (Rng::new(5), Rng::new(5));
+        let (mut sum_hot, mut sum_cold) = (0, 0);
+        for _ in 0..500 {
+            hot.advance(STEP, 127, &mut rng_hot);
+            cold.advance(STEP, 127, &mut rng_cold);
+            sum_hot += total(hot.heat());
+            sum_cold += total(cold.heat());
+        }
+        assert!(sum_hot > sum_cold * 2, "{} {}", sum_hot, sum_cold);
+    }
+
+    #[test]
+    fn long_pause() {
+        let mut fire = Fire::<16>::new(&mut [BLACK; 16]);
+        fire.advance(Duration::from_secs(60), 127, &mut Rng::new(1));
+        assert!(fire.pending < STEP);
+    }
+}
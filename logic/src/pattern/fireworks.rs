@@ -0,0 +1,144 @@
+use crate::pattern::{
+    palettes::Palette,
+    particles::{Particle, Particles},
+    Context,
+};
+use crate::rgb::Rgb8;
+use crate::rng::Rng;
+use heapless::Vec;
+
+/// Gravity of the rocket, in pixels/s²
+const GRAVITY: f32 = 40.0;
+/// Number of sparks, when a rocket bursts
+const SPARKS: usize = 12;
+/// Maximum velocity of the sparks, in pixels/s
+const SPARK_VELOCITY: f32 = 20.0;
+/// Time for the trails to fade, in seconds
+const TRAIL: f32 = 0.15;
+/// Longest pause between rockets, in ms
+const MAX_PAUSE_MS: u32 = 1500;
+
+const SPARK: u8 = 0;
+const ROCKET: u8 = 1;
+
+/// Rockets rising from the start of the strip, bursting into sparks in colors of the palette.
+pub struct Fireworks<const N: usize> {
+    particles: Particles<N, { SPARKS + 1 }>,
+    /// Time until the next rocket, in seconds
+    pause: f32,
+}
+
+impl<const N: usize> Fireworks<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            particles: Particles::new(TRAIL),
+            pause: 0.0,
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let dt = ctx.time_step();
+        let mut rng = ctx.rng;
+        self.advance(dt, ctx.palette, &mut rng);
+        self.particles.render(dt, pixels);
+    }
+
+    fn advance(&mut self, dt: f32, palette: Palette, rng: &mut Rng) {
+        let mut bursts = Vec::<f32, 1>::new();
+        self.particles.step(dt, |p| {
+            if p.kind == ROCKET {
+                bursts.push(p.position).ok();
+            }
+        });
+        for position in bursts {
+            self.burst(position, palette, rng);
+        }
+
+        if self.particles.is_empty() {
+            self.pause -= dt;
+            if self.pause <= 0.0 {
+                self.launch(rng);
+            }
+        }
+    }
+
+    /// Launch a rocket, bursting at its highest point, in the upper half of the strip.
+    fn launch(&mut self, rng: &mut Rng) {
+        let height = N as f32 * (0.5 + rng.below(40) as f32 / 100.0);
+        let velocity = libm::sqrtf(2.0 * GRAVITY * height);
+        self.particles.spawn(Particle {
+            acceleration: -GRAVITY,
+            life: velocity / GRAVITY,
+            kind: ROCKET,
+            ..Particle::new(0.0, velocity, Rgb8::new(0x40, 0x20, 0x00))
+        });
+        self.pause = rng.below(MAX_PAUSE_MS) as f32 / 1000.0;
+    }
+
+    fn burst(&mut self, position: f32, palette: Palette, rng: &mut Rng) {
+        let color = rng.next_u8();
+        for _ in 0..SPARKS {
+            let velocity = (rng.below(201) as f32 / 100.0 - 1.0) * SPARK_VELOCITY;
+            self.particles.spawn(Particle {
+                acceleration: -GRAVITY / 4.0,
+                life: 0.5 + rng.below(100) as f32 / 100.0,
+                kind: SPARK,
+                ..Particle::new(
+                    position,
+                    velocity,
+                    palette.sample(color.wrapping_add(rng.below(32) as u8)),
+                )
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::BLACK;
+
+    fn run(seed: u32) -> [[Rgb8; 30]; 100] {
+        let mut fireworks = Fireworks::<30>::new(&mut [BLACK; 30]);
+        let mut rng = Rng::new(seed);
+        let mut frames = [[BLACK; 30]; 100];
+        for frame in &mut frames {
+            fireworks.advance(0.05, Palette::Party, &mut rng);
+            fireworks.particles.render(0.05, frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn burst() {
+        let mut fireworks = Fireworks::<30>::new(&mut [BLACK; 30]);
+        let mut rng = Rng::new(1);
+        fireworks.advance(0.05, Palette::Party, &mut rng);
+        assert_eq!(fireworks.particles.len(), 1);
+
+        // rising, until it bursts
+        let mut steps = 0;
+        while fireworks.particles.len() == 1 {
+            let rocket = *fireworks.particles.iter().next().unwrap();
+            fireworks.advance(0.05, Palette::Party, &mut rng);
+            assert_eq!(rocket.kind, ROCKET);
+            assert!(rocket.velocity > 0.0);
+            steps += 1;
+        }
+        assert!(steps > 10);
+        assert_eq!(fireworks.particles.len(), SPARKS);
+        assert!(fireworks.particles.iter().all(|p| p.kind == SPARK));
+
+        // sparks burn out, before the next rocket starts
+        for _ in 0..40 {
+            fireworks.advance(0.05, Palette::Party, &mut rng);
+        }
+        assert!(fireworks.particles.len() <= 1);
+    }
+
+    #[test]
+    fn deterministic() {
+        assert!(run(3) == run(3));
+        assert!(run(3) != run(4));
+    }
+}
@@ -0,0 +1,123 @@
+//! The light patterns, and what they share: the context of a frame, and the color math.
+
+pub mod automaton;
+pub mod balls;
+pub mod breathing;
+pub mod chase;
+pub mod clock;
+pub mod comet;
+pub mod fire;
+pub mod fireworks;
+pub mod noise;
+pub mod palettes;
+pub mod particles;
+pub mod plasma;
+pub mod solid;
+pub mod twinkle;
+
+use crate::{easing::Easing, rgb::Rgb8, rng::Rng};
+use embassy_time::Duration;
+use palettes::Palette;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Context {
+    /// the speed configuration
+    pub speed: u8,
+    /// the time since the last run (could be zero)
+    pub delta: Duration,
+    /// the selected color
+    pub color: Rgb8,
+    /// the second color, e.g. for the end of a gradient
+    pub second_color: Rgb8,
+    /// the tilt along the strip, from `-127` (first pixel down) to `127` (last pixel down)
+    pub tilt: i8,
+    /// the palette selected for the mode
+    pub palette: Palette,
+    /// the selected easing curve
+    pub easing: Easing,
+    /// random numbers, different for each run
+    pub rng: Rng,
+    /// the milliseconds since midnight, unless the wall clock was not set
+    pub time_of_day: Option<u32>,
+}
+
+impl Context {
+    /// The time since the last run, in seconds, scaled by the speed.
+    ///
+    /// The default speed runs in real time, each step of `64` doubles or halves it.
+    pub fn time_step(&self) -> f32 {
+        self.delta.as_micros() as f32 / 1_000_000.0
+            * libm::exp2f((self.speed as f32 - 127.0) / 64.0)
+    }
+}
+
+/// Whole steps of a stepped animation, at a rate following the speed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Steps {
+    /// the fraction of a step, carried over to the next tick
+    pending: f32,
+}
+
+impl Steps {
+    pub const fn new() -> Self {
+        Self { pending: 0.0 }
+    }
+
+    /// Advance by a time step, like [`Context::time_step`], returning the number of whole steps
+    /// at `rate` steps per second.
+    pub fn advance(&mut self, dt: f32, rate: f32) -> u32 {
+        self.pending += dt * rate;
+        let steps = self.pending as u32;
+        self.pending -= steps as f32;
+        steps
+    }
+}
+
+/// Scale a color by a level, from `0.0` (black) to `1.0`.
+pub fn scale(color: Rgb8, level: f32) -> Rgb8 {
+    let scale = |c: u8| (c as f32 * level) as u8;
+    Rgb8::new(scale(color.r), scale(color.g), scale(color.b))
+}
+
+/// Add two colors, saturating each channel.
+pub fn add(a: Rgb8, b: Rgb8) -> Rgb8 {
+    Rgb8::new(
+        a.r.saturating_add(b.r),
+        a.g.saturating_add(b.g),
+        a.b.saturating_add(b.b),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::WHITE;
+
+    #[test]
+    fn time_step() {
+        let ctx = |speed| Context {
+            speed,
+            delta: Duration::from_millis(50),
+            color: WHITE,
+            second_color: WHITE,
+            tilt: 0,
+            palette: Palette::Rainbow,
+            easing: Easing::Sine,
+            rng: Rng::new(1),
+            time_of_day: None,
+        };
+        assert_eq!(ctx(127).time_step(), 0.05);
+        assert_eq!(ctx(191).time_step(), 0.1);
+        assert_eq!(ctx(63).time_step(), 0.025);
+    }
+
+    #[test]
+    fn steps() {
+        let mut steps = Steps::new();
+        assert_eq!(steps.advance(0.05, 10.0), 0);
+        assert_eq!(steps.advance(0.05, 10.0), 1);
+        assert_eq!(steps.advance(0.25, 10.0), 2);
+        // the rest is carried over
+        assert_eq!(steps.advance(0.05, 10.0), 1);
+    }
+}
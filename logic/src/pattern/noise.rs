@@ -0,0 +1,103 @@
+//! Smooth value noise, in fixed point.
+//!
+//! Coordinates are 8.8 fixed point: the upper byte selects a cell of the lattice, the lower byte
+//! the position inside the cell. Each lattice point has a pseudo random value, which is smoothly
+//! interpolated in between. The noise repeats every 256 cells, so coordinates can simply wrap.
+
+/// The value of a lattice point.
+fn hash(x: u8, y: u8) -> u8 {
+    let mut h = (x as u32 | (y as u32) << 8 | 0x5A << 16).wrapping_mul(0x9E37_79B1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85EB_CA77);
+    h ^= h >> 13;
+    (h >> 24) as u8
+}
+
+/// Smoothstep, `3t² - 2t³`, flattening the noise at the lattice points.
+fn fade(t: u8) -> u8 {
+    let t = t as u32;
+    ((t * t * (3 * 256 - 2 * t)) >> 16) as u8
+}
+
+fn lerp(a: u8, b: u8, t: u8) -> u8 {
+    (a as i32 + (b as i32 - a as i32) * t as i32 / 256) as u8
+}
+
+/// One dimensional noise.
+pub fn noise1(x: u16) -> u8 {
+    noise2(x, 0)
+}
+
+/// Two dimensional noise.
+pub fn noise2(x: u16, y: u16) -> u8 {
+    let (xi, xf) = ((x >> 8) as u8, fade(x as u8));
+    let (yi, yf) = ((y >> 8) as u8, fade(y as u8));
+    let (xj, yj) = (xi.wrapping_add(1), yi.wrapping_add(1));
+
+    let a = lerp(hash(xi, yi), hash(xj, yi), xf);
+    let b = lerp(hash(xi, yj), hash(xj, yj), xf);
+    lerp(a, b, yf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reference() {
+        assert_eq!(
+            [0, 1, 2, 3, 255].map(|x| hash(x, 0)),
+            [242, 234, 171, 121, 140]
+        );
+        assert_eq!(
+            [0x0000, 0x0080, 0x0100, 0x1234, 0xFFFF].map(noise1),
+            [242, 238, 234, 52, 241]
+        );
+        assert_eq!(
+            [
+                (0x0000, 0x0000),
+                (0x0080, 0x0080),
+                (0x1234, 0x5678),
+                (0xFFFF, 0x8000)
+            ]
+            .map(|(x, y)| noise2(x, y)),
+            [242, 159, 82, 81]
+        );
+    }
+
+    #[test]
+    fn lattice() {
+        for x in 0..=255 {
+            for y in [0, 17, 255] {
+                assert_eq!(noise2((x as u16) << 8, y << 8), hash(x, y as u8));
+            }
+        }
+    }
+
+    #[test]
+    fn smooth() {
+        for y in [0u16, 0x1234, 0x8080] {
+            let mut last = noise2(0, y);
+            for x in 1..=u16::MAX {
+                let value = noise2(x, y);
+                assert!(value.abs_diff(last) <= 2, "{} {}: {} {}", x, y, last, value);
+                last = value;
+            }
+            // and wrapping around
+            assert!(noise2(0, y).abs_diff(last) <= 2);
+        }
+    }
+
+    #[test]
+    fn range() {
+        let (mut min, mut max, mut sum) = (u8::MAX, 0, 0u32);
+        for i in 0..4096u32 {
+            let value = noise2((i * 37) as u16, (i * 101) as u16);
+            min = min.min(value);
+            max = max.max(value);
+            sum += value as u32;
+        }
+        assert!(min < 32 && max > 224, "{} {}", min, max);
+        assert!((100..156).contains(&(sum / 4096)), "{}", sum / 4096);
+    }
+}
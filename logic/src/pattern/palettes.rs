@@ -0,0 +1,241 @@
+//! Color palettes, shared by the patterns.
+//!
+//! A palette is a gradient, defined by color stops and interpolated in between. Sampling is done
+//! with integer math, either directly from the stops, or from a lookup table of evenly spaced
+//! entries.
+
+use crate::rgb::Rgb8;
+use strum::{EnumCount, EnumIter, EnumString, IntoEnumIterator, IntoStaticStr};
+
+/// A color stop, at a position from `0` to `255`.
+pub type Stop = (u8, Rgb8);
+
+/// A gradient, with stops in ascending order, from `0` to `255`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gradient {
+    stops: &'static [Stop],
+}
+
+impl Gradient {
+    pub const fn new(stops: &'static [Stop]) -> Self {
+        Self { stops }
+    }
+
+    /// Sample the gradient at a position, interpolating between the stops.
+    pub fn sample(&self, index: u8) -> Rgb8 {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Rgb8::new(0, 0, 0),
+        };
+        if index <= first.0 {
+            return first.1;
+        }
+
+        for window in self.stops.windows(2) {
+            let ((p0, c0), (p1, c1)) = (window[0], window[1]);
+            if index <= p1 {
+                let t = (index - p0) as u32 * 255 / (p1 - p0).max(1) as u32;
+                return lerp(c0, c1, t as u8);
+            }
+        }
+
+        last.1
+    }
+
+    /// Create a lookup table of `M` entries.
+    pub fn lut<const M: usize>(&self) -> Lut<M> {
+        Lut::new(self)
+    }
+}
+
+/// A lookup table of `M` evenly spaced entries of a gradient, with `2 <= M <= 256`.
+///
+/// With 256 entries, sampling is a plain lookup. With fewer entries, neighboring entries are
+/// interpolated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lut<const M: usize> {
+    entries: [Rgb8; M],
+}
+
+impl<const M: usize> Lut<M> {
+    pub fn new(gradient: &Gradient) -> Self {
+        Self {
+            entries: core::array::from_fn(|i| gradient.sample((i * 255 / (M - 1)) as u8)),
+        }
+    }
+
+    pub fn sample(&self, index: u8) -> Rgb8 {
+        let position = index as usize * (M - 1);
+        let (i, t) = (position / 255, position % 255);
+        if t == 0 {
+            self.entries[i]
+        } else {
+            lerp(self.entries[i], self.entries[i + 1], t as u8)
+        }
+    }
+}
+
+/// Interpolate linearly between two colors, `t` going from `0` (`a`) to `255` (`b`).
+pub fn lerp(a: Rgb8, b: Rgb8, t: u8) -> Rgb8 {
+    let lerp = |a: u8, b: u8| (a as i32 + (b as i32 - a as i32) * t as i32 / 255) as u8;
+    Rgb8::new(lerp(a.r, b.r), lerp(a.g, b.g), lerp(a.b, b.b))
+}
+
+/// The catalog of palettes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumCount, EnumIter, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Palette {
+    Rainbow,
+    Ocean,
+    Lava,
+    Forest,
+    Party,
+    Heat,
+}
+
+const RAINBOW: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0xFF, 0x00, 0x00)),
+    (43, Rgb8::new(0xFF, 0xFF, 0x00)),
+    (85, Rgb8::new(0x00, 0xFF, 0x00)),
+    (128, Rgb8::new(0x00, 0xFF, 0xFF)),
+    (170, Rgb8::new(0x00, 0x00, 0xFF)),
+    (213, Rgb8::new(0xFF, 0x00, 0xFF)),
+    (255, Rgb8::new(0xFF, 0x00, 0x00)),
+]);
+
+const OCEAN: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x00, 0x00, 0x20)),
+    (96, Rgb8::new(0x00, 0x20, 0xA0)),
+    (160, Rgb8::new(0x00, 0x80, 0xC8)),
+    (224, Rgb8::new(0x40, 0xE0, 0xD0)),
+    (255, Rgb8::new(0xE0, 0xFF, 0xFF)),
+]);
+
+const LAVA: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x00, 0x00, 0x00)),
+    (64, Rgb8::new(0x80, 0x00, 0x00)),
+    (128, Rgb8::new(0xFF, 0x20, 0x00)),
+    (192, Rgb8::new(0xFF, 0x80, 0x00)),
+    (255, Rgb8::new(0xFF, 0xFF, 0x40)),
+]);
+
+const FOREST: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x00, 0x40, 0x00)),
+    (85, Rgb8::new(0x22, 0x8B, 0x22)),
+    (170, Rgb8::new(0x6B, 0x8E, 0x23)),
+    (255, Rgb8::new(0x9A, 0xCD, 0x32)),
+]);
+
+const PARTY: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x55, 0x00, 0xAB)),
+    (42, Rgb8::new(0x84, 0x00, 0x7C)),
+    (84, Rgb8::new(0xB5, 0x00, 0x4B)),
+    (128, Rgb8::new(0xE5, 0x00, 0x1B)),
+    (170, Rgb8::new(0xE8, 0x17, 0x00)),
+    (212, Rgb8::new(0xAB, 0x55, 0x00)),
+    (255, Rgb8::new(0x55, 0x00, 0xAB)),
+]);
+
+const HEAT: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x00, 0x00, 0x00)),
+    (85, Rgb8::new(0xFF, 0x00, 0x00)),
+    (170, Rgb8::new(0xFF, 0xFF, 0x00)),
+    (255, Rgb8::new(0xFF, 0xFF, 0xFF)),
+]);
+
+/// The colors of the flag of Ukraine, at evenly spaced stops.
+pub const UKRAINE: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x00, 0x00, 0xFF)),
+    (255, Rgb8::new(0xFF, 0xFF, 0x00)),
+]);
+
+/// The colors of the flag of Germany, at evenly spaced stops.
+pub const GERMANY: Gradient = Gradient::new(&[
+    (0, Rgb8::new(0x00, 0x00, 0x00)),
+    (127, Rgb8::new(0xFF, 0x00, 0x00)),
+    (255, Rgb8::new(0xFF, 0xFF, 0x00)),
+]);
+
+impl Palette {
+    pub fn gradient(&self) -> &'static Gradient {
+        match self {
+            Self::Rainbow => &RAINBOW,
+            Self::Ocean => &OCEAN,
+            Self::Lava => &LAVA,
+            Self::Forest => &FOREST,
+            Self::Party => &PARTY,
+            Self::Heat => &HEAT,
+        }
+    }
+
+    pub fn sample(&self, index: u8) -> Rgb8 {
+        self.gradient().sample(index)
+    }
+
+    pub fn next(&self) -> Self {
+        Self::iter()
+            .skip_while(|p| p != self)
+            .nth(1)
+            .unwrap_or(Self::Rainbow)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::str::FromStr;
+
+    #[test]
+    fn sample_stops() {
+        assert_eq!(HEAT.sample(0), Rgb8::new(0, 0, 0));
+        assert_eq!(HEAT.sample(85), Rgb8::new(0xFF, 0, 0));
+        assert_eq!(HEAT.sample(255), Rgb8::new(0xFF, 0xFF, 0xFF));
+        // half way between red and yellow
+        assert_eq!(HEAT.sample(127), Rgb8::new(0xFF, 126, 0));
+        assert_eq!(RAINBOW.sample(64), Rgb8::new(128, 0xFF, 0));
+
+        // beyond the stops
+        const PARTIAL: Gradient =
+            Gradient::new(&[(16, Rgb8::new(10, 0, 0)), (32, Rgb8::new(20, 0, 0))]);
+        assert_eq!(PARTIAL.sample(0), Rgb8::new(10, 0, 0));
+        assert_eq!(PARTIAL.sample(24), Rgb8::new(14, 0, 0));
+        assert_eq!(PARTIAL.sample(255), Rgb8::new(20, 0, 0));
+        assert_eq!(Gradient::new(&[]).sample(10), Rgb8::new(0, 0, 0));
+    }
+
+    #[test]
+    fn lookup_tables() {
+        // with 256 entries, the same as the gradient
+        let lut = HEAT.lut::<256>();
+        assert!((0..=255).all(|i| lut.sample(i) == HEAT.sample(i)));
+
+        // with 16 entries, close to the gradient
+        for palette in Palette::iter() {
+            let gradient = palette.gradient();
+            let lut = gradient.lut::<16>();
+            for i in 0..=255 {
+                let (a, b) = (lut.sample(i), gradient.sample(i));
+                let diff =
+                    a.r.abs_diff(b.r)
+                        .max(a.g.abs_diff(b.g))
+                        .max(a.b.abs_diff(b.b));
+                assert!(diff <= 40, "{:?} at {}: {:?} {:?}", palette, i, a, b);
+            }
+            assert_eq!(lut.sample(0), gradient.sample(0));
+            assert_eq!(lut.sample(255), gradient.sample(255));
+        }
+    }
+
+    #[test]
+    fn catalog() {
+        assert_eq!(Palette::from_str("ocean"), Ok(Palette::Ocean));
+        assert_eq!(Into::<&'static str>::into(Palette::Heat), "heat");
+
+        let mut palette = Palette::Rainbow;
+        for _ in 0..Palette::COUNT {
+            palette = palette.next();
+        }
+        assert_eq!(palette, Palette::Rainbow);
+        assert_eq!(Palette::Heat.next(), Palette::Rainbow);
+    }
+}
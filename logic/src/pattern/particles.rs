@@ -0,0 +1,233 @@
+//! A particle system, for effects of things moving along the strip.
+//!
+//! Particles move with their velocity and acceleration, until their lifetime ends or they leave
+//! the strip. They are blended additively into a trail, which fades over time.
+
+use crate::pattern::{add, scale};
+use crate::rgb::{Rgb8, BLACK};
+use heapless::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    /// Position, in pixels from the start of the strip
+    pub position: f32,
+    /// Velocity, in pixels/s
+    pub velocity: f32,
+    /// Acceleration, in pixels/s²
+    pub acceleration: f32,
+    /// Remaining lifetime, in seconds
+    pub life: f32,
+    pub color: Rgb8,
+    /// Velocity kept when bouncing off the ends, `None` to leave the strip
+    pub bounce: Option<f32>,
+    /// Free for the effect, to tell different kinds of particles apart
+    pub kind: u8,
+}
+
+impl Particle {
+    /// A particle living forever, moving with a constant velocity.
+    pub const fn new(position: f32, velocity: f32, color: Rgb8) -> Self {
+        Self {
+            position,
+            velocity,
+            acceleration: 0.0,
+            life: f32::INFINITY,
+            color,
+            bounce: None,
+            kind: 0,
+        }
+    }
+
+    /// Move the particle, returning `false` once its lifetime ended.
+    fn step(&mut self, dt: f32, length: f32) -> bool {
+        self.life -= dt;
+        self.velocity += self.acceleration * dt;
+        self.position += self.velocity * dt;
+
+        if let Some(bounce) = self.bounce {
+            if self.position < 0.0 {
+                self.position = -self.position;
+                self.velocity = -self.velocity * bounce;
+            } else if self.position > length {
+                self.position = 2.0 * length - self.position;
+                self.velocity = -self.velocity * bounce;
+            }
+        }
+
+        self.life > 0.0
+    }
+}
+
+pub struct Particles<const N: usize, const M: usize> {
+    particles: Vec<Particle, M>,
+    trail: [Rgb8; N],
+    /// Time for the trail to fade to about a third, in seconds
+    fade: f32,
+}
+
+impl<const N: usize, const M: usize> Particles<N, M> {
+    pub const fn new(fade: f32) -> Self {
+        Self {
+            particles: Vec::new(),
+            trail: [BLACK; N],
+            fade,
+        }
+    }
+
+    /// Add a particle, returning `false` if there is no space left.
+    pub fn spawn(&mut self, particle: Particle) -> bool {
+        self.particles.push(particle).is_ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Particle> {
+        self.particles.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Move all particles by a time step, in seconds.
+    ///
+    /// Particles leaving the strip are removed. Particles whose lifetime ended are removed too,
+    /// after being passed to `expired`.
+    pub fn step(&mut self, dt: f32, mut expired: impl FnMut(&Particle)) {
+        let length = N as f32;
+        let mut i = 0;
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            let alive = particle.step(dt, length);
+            if !alive {
+                expired(particle);
+            }
+            if alive && (0.0..=length).contains(&particle.position) {
+                i += 1;
+            } else {
+                self.particles.swap_remove(i);
+            }
+        }
+    }
+
+    /// Fade the trail by a time step, in seconds, and draw the particles on top.
+    pub fn render(&mut self, dt: f32, pixels: &mut [Rgb8; N]) {
+        let keep = if self.fade > 0.0 {
+            libm::expf(-dt / self.fade)
+        } else {
+            0.0
+        };
+        for pixel in &mut self.trail {
+            *pixel = scale(*pixel, keep);
+        }
+
+        for particle in &self.particles {
+            // spread over the two closest pixels
+            let first = libm::floorf(particle.position - 0.5);
+            for i in [first, first + 1.0] {
+                let cover = 1.0 - libm::fabsf(i + 0.5 - particle.position);
+                if (0.0..N as f32).contains(&i) && cover > 0.0 {
+                    let pixel = &mut self.trail[i as usize];
+                    *pixel = add(*pixel, scale(particle.color, cover));
+                }
+            }
+        }
+
+        pixels.copy_from_slice(&self.trail);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::{BLUE, RED};
+
+    #[test]
+    fn motion() {
+        let mut particles = Particles::<10, 2>::new(0.0);
+        particles.spawn(Particle {
+            acceleration: 4.0,
+            ..Particle::new(1.0, 2.0, RED)
+        });
+
+        particles.step(0.5, |_| {});
+        let particle = particles.iter().next().unwrap();
+        assert_eq!(particle.velocity, 4.0);
+        assert_eq!(particle.position, 3.0);
+
+        // leaving the strip
+        particles.step(1.0, |_| panic!("not expired"));
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn lifetime() {
+        let mut particles = Particles::<10, 2>::new(0.0);
+        assert!(particles.spawn(Particle {
+            life: 1.0,
+            kind: 1,
+            ..Particle::new(1.0, 0.0, RED)
+        }));
+        assert!(particles.spawn(Particle::new(2.0, 0.0, RED)));
+        assert!(!particles.spawn(Particle::new(3.0, 0.0, RED)));
+
+        let mut expired = 0;
+        for _ in 0..4 {
+            particles.step(0.3, |p| expired += p.kind);
+        }
+        assert_eq!(expired, 1);
+        assert_eq!(particles.len(), 1);
+    }
+
+    #[test]
+    fn bounce() {
+        let mut particles = Particles::<10, 1>::new(0.0);
+        particles.spawn(Particle {
+            bounce: Some(0.5),
+            ..Particle::new(1.0, -4.0, RED)
+        });
+
+        particles.step(0.5, |_| {});
+        let particle = particles.iter().next().unwrap();
+        assert_eq!(particle.position, 1.0);
+        assert_eq!(particle.velocity, 2.0);
+
+        particles.step(5.0, |_| {});
+        let particle = particles.iter().next().unwrap();
+        assert_eq!(particle.position, 9.0);
+        assert_eq!(particle.velocity, -1.0);
+    }
+
+    #[test]
+    fn render() {
+        let mut pixels = [BLACK; 4];
+        let mut particles = Particles::<4, 2>::new(1.0);
+        particles.spawn(Particle::new(1.5, 0.0, RED));
+        particles.spawn(Particle::new(3.0, 0.0, Rgb8::new(0xFF, 0, 0x80)));
+
+        particles.render(0.0, &mut pixels);
+        assert_eq!(
+            pixels,
+            [BLACK, RED, Rgb8::new(127, 0, 64), Rgb8::new(127, 0, 64)]
+        );
+
+        // the trail fades, and particles add up
+        particles.iter_mut().for_each(|p| p.color = BLUE);
+        particles.render(1.0, &mut pixels);
+        assert_eq!(
+            pixels,
+            [
+                BLACK,
+                Rgb8::new(93, 0, 0xFF),
+                Rgb8::new(46, 0, 150),
+                Rgb8::new(46, 0, 150)
+            ]
+        );
+    }
+}
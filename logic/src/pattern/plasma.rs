@@ -0,0 +1,147 @@
+use crate::pattern::{noise::noise2, palettes::Gradient, scale, Context};
+use crate::rgb::Rgb8;
+
+/// The position in the noise, drifting over time.
+///
+/// `DRIFT` is in 1/256 cells per second.
+struct Drift<const DRIFT: u16> {
+    offset: f32,
+}
+
+impl<const DRIFT: u16> Drift<DRIFT> {
+    const fn new() -> Self {
+        Self { offset: 0.0 }
+    }
+
+    fn advance(&mut self, dt: f32) -> u16 {
+        self.offset = libm::fmodf(self.offset + DRIFT as f32 * dt, 65536.0);
+        self.offset as u16
+    }
+}
+
+/// The position of a pixel in the noise, `SCALE` being the distance between pixels, in 1/256
+/// cells.
+fn position<const SCALE: u16>(i: usize) -> u16 {
+    (i as u16).wrapping_mul(SCALE)
+}
+
+/// Two layers of noise, added up and wrapping around the palette.
+pub struct Plasma<const N: usize, const SCALE: u16, const DRIFT: u16> {
+    drift: Drift<DRIFT>,
+}
+
+impl<const N: usize, const SCALE: u16, const DRIFT: u16> Plasma<N, SCALE, DRIFT> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            drift: Drift::new(),
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let t = self.drift.advance(ctx.time_step());
+        let gradient = ctx.palette.gradient();
+
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let x = position::<SCALE>(i);
+            let a = noise2(x, t);
+            let b = noise2(t.wrapping_mul(2), x.wrapping_mul(2));
+            *pixel = gradient.sample(a.wrapping_add(b));
+        }
+    }
+}
+
+/// Large, slowly moving blobs of noise, with hard edges.
+pub struct LavaLamp<const N: usize, const SCALE: u16, const DRIFT: u16> {
+    drift: Drift<DRIFT>,
+}
+
+impl<const N: usize, const SCALE: u16, const DRIFT: u16> LavaLamp<N, SCALE, DRIFT> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            drift: Drift::new(),
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let t = self.drift.advance(ctx.time_step());
+        render_blobs::<N, SCALE>(pixels, t, ctx.palette.gradient());
+    }
+}
+
+fn render_blobs<const N: usize, const SCALE: u16>(
+    pixels: &mut [Rgb8; N],
+    t: u16,
+    gradient: &Gradient,
+) {
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = gradient.sample(contrast(noise2(position::<SCALE>(i), t)));
+    }
+}
+
+/// Stretch the middle of the noise to the full range, for sharper edges.
+fn contrast(value: u8) -> u8 {
+    ((value as i32 - 64) * 2).clamp(0, 255) as u8
+}
+
+/// Curtains of light, slowly changing their colors of the palette.
+pub struct Aurora<const N: usize, const SCALE: u16, const DRIFT: u16> {
+    drift: Drift<DRIFT>,
+}
+
+impl<const N: usize, const SCALE: u16, const DRIFT: u16> Aurora<N, SCALE, DRIFT> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            drift: Drift::new(),
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let t = self.drift.advance(ctx.time_step());
+        let gradient = ctx.palette.gradient();
+
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let x = position::<SCALE>(i);
+            // colors change slowly, along the strip and over time
+            let color = gradient.sample(noise2(x / 4, t / 4));
+            // the curtains are sharper, and move faster
+            let curtain = noise2(x, t.wrapping_add(0x8000)) as f32 / 255.0;
+            *pixel = scale(color, curtain * curtain);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pattern::palettes::Palette;
+    use crate::rgb::BLACK;
+
+    #[test]
+    fn drift() {
+        let mut drift = Drift::<512>::new();
+        assert_eq!(drift.advance(0.5), 256);
+        assert_eq!(drift.advance(0.25), 384);
+        // wrapping around
+        assert_eq!(drift.advance(127.25), 0);
+    }
+
+    #[test]
+    fn blobs() {
+        assert_eq!(contrast(0), 0);
+        assert_eq!(contrast(128), 128);
+        assert_eq!(contrast(200), 255);
+
+        // changing slowly, along the strip and over time
+        let gradient = Palette::Lava.gradient();
+        let mut last = [BLACK; 30];
+        render_blobs::<30, 64>(&mut last, 0, gradient);
+        for t in 1..200 {
+            let mut pixels = [BLACK; 30];
+            render_blobs::<30, 64>(&mut pixels, t * 4, gradient);
+            for (a, b) in pixels.iter().zip(last) {
+                assert!(a.r.abs_diff(b.r) <= 32, "{:?} {:?}", a, b);
+            }
+            last = pixels;
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::pattern::{palettes::lerp, Context};
+use crate::rgb::Rgb8;
+
+/// All pixels showing the selected color.
+pub struct Solid<const N: usize>;
+
+impl<const N: usize> Solid<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        pixels.fill(ctx.color);
+    }
+}
+
+/// A gradient along the strip, from the selected color to the second color.
+pub struct Gradient<const N: usize>;
+
+impl<const N: usize> Gradient<N> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        Self::fill(pixels, ctx.color, ctx.second_color);
+    }
+
+    fn fill(pixels: &mut [Rgb8; N], first: Rgb8, second: Rgb8) {
+        let last = N.saturating_sub(1).max(1);
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = lerp(first, second, (i * 255 / last) as u8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::{BLACK, BLUE, RED};
+
+    #[test]
+    fn gradient() {
+        let mut pixels = [BLACK; 5];
+        Gradient::fill(&mut pixels, RED, BLUE);
+        assert_eq!(pixels[0], RED);
+        assert_eq!(pixels[2], Rgb8::new(128, 0, 127));
+        assert_eq!(pixels[4], BLUE);
+
+        let mut pixel = [BLACK; 1];
+        Gradient::fill(&mut pixel, RED, BLUE);
+        assert_eq!(pixel, [RED]);
+    }
+}
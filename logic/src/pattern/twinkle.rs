@@ -0,0 +1,233 @@
+use crate::pattern::{
+    add,
+    palettes::{lerp, Palette},
+    scale, Context,
+};
+use crate::rgb::{Rgb8, BLACK, WHITE};
+use crate::rng::Rng;
+
+/// Brightness of the background, behind the twinkles
+const BACKGROUND: f32 = 1.0 / 16.0;
+/// Drift along the palette, in steps per second
+const DRIFT: f32 = 20.0;
+
+/// Random pixels fading in and out, in colors of the palette, over a dim background in the
+/// selected color.
+///
+/// `DENSITY` is the number of twinkles starting per second, `FADE_MS` the time to fade in, and
+/// out again.
+pub struct Twinkle<const N: usize, const DENSITY: u8, const FADE_MS: u16> {
+    /// the phase of each pixel: `0.0` when idle, rising up to `1.0` and falling until `2.0`
+    phases: [f32; N],
+    colors: [u8; N],
+}
+
+impl<const N: usize, const DENSITY: u8, const FADE_MS: u16> Twinkle<N, DENSITY, FADE_MS> {
+    pub fn new(_: &mut [Rgb8; N]) -> Self {
+        Self {
+            phases: [0.0; N],
+            colors: [0; N],
+        }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let mut rng = ctx.rng;
+        self.advance(ctx.time_step(), &mut rng);
+        self.render(pixels, ctx.palette, scale(ctx.color, BACKGROUND));
+    }
+
+    fn advance(&mut self, dt: f32, rng: &mut Rng) {
+        let step = dt * 1000.0 / FADE_MS as f32;
+        for phase in &mut self.phases {
+            if *phase > 0.0 {
+                *phase += step;
+                if *phase >= 2.0 {
+                    *phase = 0.0;
+                }
+            }
+        }
+
+        for _ in 0..rng.round(DENSITY as f32 * dt) {
+            let i = rng.below(N as u32) as usize;
+            if self.phases[i] == 0.0 {
+                self.phases[i] = f32::MIN_POSITIVE;
+                self.colors[i] = rng.next_u8();
+            }
+        }
+    }
+
+    fn render(&self, pixels: &mut [Rgb8; N], palette: Palette, background: Rgb8) {
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let phase = self.phases[i];
+            let level = if phase <= 1.0 { phase } else { 2.0 - phase };
+            let color = palette.sample(self.colors[i]);
+            *pixel = lerp(background, color, (level * 255.0) as u8);
+        }
+    }
+}
+
+/// Glitter, sparkling on top of the current mode.
+///
+/// `DENSITY` is the number of sparkles per second, `FADE_MS` the time for them to fade to about a
+/// third.
+pub struct Glitter<const N: usize, const DENSITY: u8, const FADE_MS: u16> {
+    /// the intensity of the sparkle of each pixel
+    sparkles: [u8; N],
+}
+
+impl<const N: usize, const DENSITY: u8, const FADE_MS: u16> Glitter<N, DENSITY, FADE_MS> {
+    pub const fn new() -> Self {
+        Self { sparkles: [0; N] }
+    }
+
+    /// Let the sparkles fade, and new ones appear.
+    pub fn advance(&mut self, dt: f32, rng: &mut Rng) {
+        let keep = libm::expf(-dt * 1000.0 / FADE_MS as f32);
+        for sparkle in &mut self.sparkles {
+            *sparkle = (*sparkle as f32 * keep) as u8;
+        }
+
+        for _ in 0..rng.round(DENSITY as f32 * dt) {
+            self.sparkles[rng.below(N as u32) as usize] = u8::MAX;
+        }
+    }
+
+    /// Render the sparkles on top of a frame of the current mode.
+    pub fn render(&self, pixels: &mut [Rgb8; N]) {
+        for (pixel, sparkle) in pixels.iter_mut().zip(&self.sparkles) {
+            *pixel = lerp(*pixel, WHITE, *sparkle);
+        }
+    }
+}
+
+impl<const N: usize, const DENSITY: u8, const FADE_MS: u16> Default
+    for Glitter<N, DENSITY, FADE_MS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Random pixels lighting up in colors of the palette, and fading out.
+///
+/// `DENSITY` is the number of pixels lighting up per second, `FADE_MS` the time for them to fade
+/// to about a third.
+pub struct Confetti<const N: usize, const DENSITY: u8, const FADE_MS: u16> {
+    /// the offset into the palette, drifting slowly
+    offset: f32,
+}
+
+impl<const N: usize, const DENSITY: u8, const FADE_MS: u16> Confetti<N, DENSITY, FADE_MS> {
+    pub fn new(pixels: &mut [Rgb8; N]) -> Self {
+        pixels.fill(BLACK);
+        Self { offset: 0.0 }
+    }
+
+    pub fn tick(&mut self, pixels: &mut [Rgb8; N], ctx: Context) {
+        let mut rng = ctx.rng;
+        self.advance(pixels, ctx.time_step(), ctx.palette, &mut rng);
+    }
+
+    fn advance(&mut self, pixels: &mut [Rgb8; N], dt: f32, palette: Palette, rng: &mut Rng) {
+        let keep = libm::expf(-dt * 1000.0 / FADE_MS as f32);
+        for pixel in pixels.iter_mut() {
+            *pixel = scale(*pixel, keep);
+        }
+
+        self.offset = libm::fmodf(self.offset + DRIFT * dt, 256.0);
+        for _ in 0..rng.round(DENSITY as f32 * dt) {
+            let color = (self.offset as u8).wrapping_add(rng.below(64) as u8);
+            let pixel = &mut pixels[rng.below(N as u32) as usize];
+            *pixel = add(*pixel, palette.sample(color));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn twinkle() {
+        let mut twinkle = Twinkle::<20, 10, 500>::new(&mut [BLACK; 20]);
+        let mut rng = Rng::new(1);
+        let mut pixels = [BLACK; 20];
+        let background = Rgb8::new(1, 2, 3);
+
+        // without time, nothing twinkles
+        twinkle.advance(0.0, &mut rng);
+        twinkle.render(&mut pixels, Palette::Heat, background);
+        assert_eq!(pixels, [background; 20]);
+
+        // about 10 twinkles per second, each lasting a second
+        let mut active = 0;
+        for _ in 0..200 {
+            twinkle.advance(0.05, &mut rng);
+            active += twinkle.phases.iter().filter(|p| **p > 0.0).count();
+        }
+        assert!((1400..2600).contains(&active), "{}", active);
+
+        // fading in, and out again
+        let mut twinkle = Twinkle::<1, 0, 500>::new(&mut [BLACK; 1]);
+        twinkle.phases[0] = f32::MIN_POSITIVE;
+        twinkle.colors[0] = 255;
+        let mut levels = [0; 10];
+        for level in &mut levels {
+            let mut pixel = [BLACK; 1];
+            twinkle.advance(0.1, &mut rng);
+            twinkle.render(&mut pixel, Palette::Heat, BLACK);
+            *level = pixel[0].r;
+        }
+        assert_eq!(levels, [51, 102, 153, 204, 255, 203, 152, 101, 50, 0]);
+    }
+
+    #[test]
+    fn glitter() {
+        let mut glitter = Glitter::<20, 10, 200>::new();
+        let mut rng = Rng::new(3);
+        let background = Rgb8::new(10, 20, 30);
+
+        // the frame is kept, without sparkles
+        let mut pixels = [background; 20];
+        glitter.render(&mut pixels);
+        assert_eq!(pixels, [background; 20]);
+
+        // sparkles light up pixels of the frame
+        for _ in 0..10 {
+            glitter.advance(0.05, &mut rng);
+        }
+        glitter.render(&mut pixels);
+        let lit = pixels.iter().filter(|p| p.b > background.b).count();
+        assert!((1..20).contains(&lit), "{}", lit);
+
+        // and fade back to it
+        let mut glitter = Glitter::<20, 0, 200>::new();
+        glitter.sparkles[0] = u8::MAX;
+        for _ in 0..40 {
+            glitter.advance(0.05, &mut rng);
+        }
+        let mut pixels = [background; 20];
+        glitter.render(&mut pixels);
+        assert_eq!(pixels, [background; 20]);
+    }
+
+    #[test]
+    fn confetti() {
+        let mut pixels = [BLACK; 30];
+        let mut confetti = Confetti::<30, 40, 200>::new(&mut pixels);
+        let mut rng = Rng::new(2);
+
+        for _ in 0..20 {
+            confetti.advance(&mut pixels, 0.05, Palette::Rainbow, &mut rng);
+        }
+        let lit = pixels.iter().filter(|p| **p != BLACK).count();
+        assert!((5..=30).contains(&lit), "{}", lit);
+
+        // fading out, without new confetti
+        let mut confetti = Confetti::<30, 0, 200>::new(&mut [BLACK; 30]);
+        for _ in 0..40 {
+            confetti.advance(&mut pixels, 0.05, Palette::Rainbow, &mut rng);
+        }
+        assert_eq!(pixels, [BLACK; 30]);
+    }
+}
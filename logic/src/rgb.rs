@@ -0,0 +1,23 @@
+//! Colors of the pixels.
+//!
+//! The same as the colors of the NeoPixel driver, which the firmware converts them to when writing
+//! the strip.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rgb8 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb8 {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+pub const BLACK: Rgb8 = Rgb8::new(0x00, 0x00, 0x00);
+pub const WHITE: Rgb8 = Rgb8::new(0xFF, 0xFF, 0xFF);
+pub const RED: Rgb8 = Rgb8::new(0xFF, 0x00, 0x00);
+pub const GREEN: Rgb8 = Rgb8::new(0x00, 0xFF, 0x00);
+pub const BLUE: Rgb8 = Rgb8::new(0x00, 0x00, 0xFF);
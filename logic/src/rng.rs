@@ -0,0 +1,101 @@
+//! A small pseudo random number generator (xorshift32), good enough for effects.
+//!
+//! On the device, it is seeded from the hardware random number generator. Tests use fixed seeds,
+//! to get the same sequence each run.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng(u32);
+
+impl Rng {
+    /// Create from a seed. Xorshift gets stuck at zero, so zero is replaced.
+    pub const fn new(seed: u32) -> Self {
+        if seed == 0 {
+            Self(0x9E37_79B9)
+        } else {
+            Self(seed)
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u32() >> 24) as u8
+    }
+
+    /// A value in `0..n`.
+    pub fn below(&mut self, n: u32) -> u32 {
+        ((self.next_u32() as u64 * n as u64) >> 32) as u32
+    }
+
+    /// Round a value randomly, rounding up with a chance of its fraction.
+    ///
+    /// On average, this gives the value, e.g. for the number of events expected in a time step.
+    pub fn round(&mut self, value: f32) -> u32 {
+        let whole = value as u32;
+        let fraction = ((value - whole as f32) * 65536.0) as u32;
+        whole + (self.below(65536) < fraction) as u32
+    }
+
+    /// A new generator, seeded from this one.
+    pub fn fork(&mut self) -> Self {
+        Self::new(self.next_u32())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sequence() {
+        let mut rng = Rng::new(1);
+        assert_eq!(rng.next_u32(), 270369);
+        assert_eq!(rng.next_u32(), 67634689);
+
+        // same seed, same sequence
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        assert!((0..100).all(|_| a.next_u32() == b.next_u32()));
+
+        // not stuck at zero
+        assert_ne!(Rng::new(0).next_u32(), 0);
+    }
+
+    #[test]
+    fn below() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            let value = rng.below(10);
+            assert!(value < 10);
+            seen[value as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+        assert_eq!(rng.below(0), 0);
+    }
+
+    #[test]
+    fn round() {
+        let mut rng = Rng::new(3);
+        assert_eq!(rng.round(2.0), 2);
+        assert_eq!(rng.round(0.0), 0);
+
+        let sum: u32 = (0..1000).map(|_| rng.round(0.25)).sum();
+        assert!((200..300).contains(&sum), "{}", sum);
+        assert!((0..1000).all(|_| (1..=2).contains(&rng.round(1.5))));
+    }
+
+    #[test]
+    fn fork() {
+        let mut rng = Rng::new(5);
+        let (mut a, mut b) = (rng.fork(), rng.fork());
+        assert_ne!(a, b);
+        assert_ne!(a.next_u32(), b.next_u32());
+    }
+}
@@ -0,0 +1,180 @@
+//! Thermal protection, derating the brightness when getting hot.
+//!
+//! Above a threshold, the brightness is reduced progressively, and only raised again once the
+//! temperature dropped by some margin.
+
+/// Temperature, in quarter degrees Celsius (the resolution of the TEMP sensor)
+pub type Temperature = i32;
+
+pub const fn celsius(value: i32) -> Temperature {
+    value * 4
+}
+
+/// Thresholds of the derating.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// Temperature, above which the brightness is reduced
+    pub start: Temperature,
+    /// Temperature, at which the brightness is reduced to `min_level`
+    pub full: Temperature,
+    /// Level the brightness is reduced to, until reaching `critical`
+    pub min_level: u8,
+    /// Temperature, at which the lights are switched off
+    pub critical: Temperature,
+    /// Drop of the temperature, required before the brightness is raised again
+    pub hysteresis: Temperature,
+}
+
+impl Limits {
+    /// The level of the brightness for a temperature, without hysteresis.
+    fn level(&self, temperature: Temperature) -> u8 {
+        if temperature >= self.critical {
+            0
+        } else if temperature <= self.start {
+            u8::MAX
+        } else if temperature >= self.full {
+            self.min_level
+        } else {
+            let range = (self.full - self.start).max(1);
+            let drop = (u8::MAX - self.min_level) as i32 * (temperature - self.start) / range;
+            (u8::MAX as i32 - drop) as u8
+        }
+    }
+}
+
+/// Derives the level of the brightness from the temperature.
+pub struct Derating {
+    limits: Limits,
+    level: u8,
+}
+
+impl Derating {
+    pub const fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            level: u8::MAX,
+        }
+    }
+
+    /// Feed a new temperature, returning the level, if it changed.
+    pub fn update(&mut self, temperature: Temperature) -> Option<u8> {
+        let mut level = self.limits.level(temperature);
+        if level > self.level {
+            // only raise the level again, once cooled down by the hysteresis
+            level = self
+                .limits
+                .level(temperature + self.limits.hysteresis)
+                .max(self.level);
+        }
+
+        if level != self.level {
+            self.level = level;
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+}
+
+/// Series resistor of the NTC divider, in Ω
+const NTC_SERIES: f32 = 10_000.0;
+/// Resistance of the NTC at 25°C, in Ω
+const NTC_R25: f32 = 10_000.0;
+/// Beta coefficient of the NTC
+const NTC_BETA: f32 = 3950.0;
+
+/// Convert a 12 bit SAADC reading of the NTC into a temperature.
+///
+/// The NTC is connected between the input and ground, with the series resistor to VDD, and the
+/// SAADC uses VDD as reference. Readings at the ends of the range (open or shorted) are rejected.
+pub fn ntc_temperature(raw: i16) -> Option<Temperature> {
+    if raw <= 0 || raw >= 4095 {
+        return None;
+    }
+    let raw = raw as f32;
+    let resistance = NTC_SERIES * raw / (4096.0 - raw);
+    let kelvin = 1.0 / (1.0 / 298.15 + libm::logf(resistance / NTC_R25) / NTC_BETA);
+    Some(libm::roundf((kelvin - 273.15) * 4.0) as Temperature)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        start: celsius(50),
+        full: celsius(65),
+        min_level: 64,
+        critical: celsius(75),
+        hysteresis: celsius(3),
+    };
+
+    /// Temperature (°C, every minute) of a strip in an enclosed diffuser: heating up at full
+    /// brightness, settling with the derated brightness, and cooling down after switching off.
+    const ENCLOSED: [i32; 24] = [
+        25, 32, 38, 43, 47, 50, 52, 54, 55, 56, 56, 55, 56, 55, 56, // heating up, settling
+        52, 48, 47, 46, 44, 40, 35, 30, 27, // switched off
+    ];
+
+    fn run(trace: &[i32]) -> heapless::Vec<u8, 32> {
+        let mut derating = Derating::new(LIMITS);
+        trace
+            .iter()
+            .map(|t| {
+                derating.update(celsius(*t));
+                derating.level()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(LIMITS.level(celsius(20)), u8::MAX);
+        assert_eq!(LIMITS.level(celsius(50)), u8::MAX);
+        assert_eq!(LIMITS.level(celsius(57)) as i32, 255 - 191 * 7 / 15);
+        assert_eq!(LIMITS.level(celsius(65)), 64);
+        assert_eq!(LIMITS.level(celsius(74)), 64);
+        assert_eq!(LIMITS.level(celsius(75)), 0);
+    }
+
+    #[test]
+    fn enclosed_diffuser() {
+        let levels = run(&ENCLOSED);
+
+        // not derated below the threshold
+        assert!(levels[..6].iter().all(|l| *l == u8::MAX));
+        // derated progressively while heating up
+        assert!(levels[6..10].windows(2).all(|w| w[1] < w[0]));
+        // keeping the level, while fluctuating around the peak
+        assert!(levels[9..15].iter().all(|l| *l == levels[9]));
+        // raised again while cooling down, after dropping by the hysteresis
+        assert_eq!(levels[15], LIMITS.level(celsius(55)));
+        assert!(levels[16] > levels[15]);
+        assert_eq!(*levels.last().unwrap(), u8::MAX);
+    }
+
+    #[test]
+    fn critical() {
+        let levels = run(&[60, 70, 76, 73, 72, 71, 68]);
+        assert_eq!(levels[1], 64);
+        assert_eq!(levels[2], 0);
+        // stays off, until cooled down by the hysteresis
+        assert_eq!(levels[3], 0);
+        assert_eq!(levels[4], 0);
+        assert_eq!(levels[5], 64);
+    }
+
+    #[test]
+    fn ntc() {
+        assert_eq!(ntc_temperature(2048), Some(celsius(25)));
+        // hotter, lower resistance
+        assert!(ntc_temperature(1000).unwrap() > celsius(50));
+        assert!(ntc_temperature(3000).unwrap() < celsius(10));
+        assert_eq!(ntc_temperature(0), None);
+        assert_eq!(ntc_temperature(4095), None);
+    }
+}
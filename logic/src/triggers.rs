@@ -0,0 +1,181 @@
+//! Trigger inputs, wiring doorbells, door contacts and relay outputs of other systems to actions.
+//!
+//! The inputs are debounced. Edge triggers fire on a change of the input, level triggers fire when
+//! the input becomes active, and when it is active on startup, and may have an action for when it
+//! becomes inactive again.
+
+use embassy_time::{Duration, Instant};
+
+/// Time the input must be stable, before a change is accepted
+const DEBOUNCE: Duration = Duration::from_millis(30);
+
+/// When a trigger fires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sense {
+    /// When the input goes high
+    Rising,
+    /// When the input goes low
+    Falling,
+    /// On any change of the input
+    Both,
+    /// While the input is high
+    High,
+    /// While the input is low
+    Low,
+}
+
+/// Configuration of a trigger input, as defined by the board.
+#[derive(Clone, Copy, Debug)]
+pub struct Config<A> {
+    pub sense: Sense,
+    pub action: A,
+    /// Action when the input becomes inactive again, only used by level triggers
+    pub release: Option<A>,
+}
+
+/// Evaluates the samples of a trigger input.
+pub struct Evaluator<A> {
+    config: Config<A>,
+    /// The debounced level, unknown until stable for the first time
+    level: Option<bool>,
+    /// The most recently sampled level, and since when
+    pending: Option<(bool, Instant)>,
+}
+
+impl<A: Copy> Evaluator<A> {
+    pub const fn new(config: Config<A>) -> Self {
+        Self {
+            config,
+            level: None,
+            pending: None,
+        }
+    }
+
+    /// Feed a sample of the input, returning the action to fire.
+    pub fn update(&mut self, high: bool, now: Instant) -> Option<A> {
+        let since = match self.pending {
+            Some((level, since)) if level == high => since,
+            _ => {
+                self.pending = Some((high, now));
+                now
+            }
+        };
+        if now - since < DEBOUNCE || self.level == Some(high) {
+            return None;
+        }
+
+        let initial = self.level.is_none();
+        self.level = Some(high);
+
+        let Config {
+            sense,
+            action,
+            release,
+        } = self.config;
+        match (sense, high) {
+            (Sense::High, true) | (Sense::Low, false) => Some(action),
+            // the initial level is no change
+            _ if initial => None,
+            (Sense::Rising, true) | (Sense::Falling, false) | (Sense::Both, _) => Some(action),
+            (Sense::High, false) | (Sense::Low, true) => release,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Action {
+        Notify,
+        On,
+        Off,
+        Toggle,
+    }
+
+    const DOORBELL: Config<Action> = Config {
+        sense: Sense::Falling,
+        action: Action::Notify,
+        release: None,
+    };
+
+    const DOOR: Config<Action> = Config {
+        sense: Sense::High,
+        action: Action::On,
+        release: Some(Action::Off),
+    };
+
+    /// Sample the input every 10ms, from edges (time in ms, level), and collect the actions.
+    fn simulate(
+        config: Config<Action>,
+        initial: bool,
+        edges: &[(u64, bool)],
+        end: u64,
+    ) -> heapless::Vec<(u64, Action), 8> {
+        let mut evaluator = Evaluator::new(config);
+        let mut actions = heapless::Vec::new();
+        let mut level = initial;
+        for t in (0..=end).step_by(10) {
+            if let Some((_, high)) = edges.iter().rev().find(|(at, _)| *at <= t) {
+                level = *high;
+            }
+            if let Some(action) = evaluator.update(level, Instant::from_millis(t)) {
+                actions.push((t, action)).unwrap();
+            }
+        }
+        actions
+    }
+
+    #[test]
+    fn doorbell() {
+        // pressed twice, bouncing on press and release
+        let edges = [
+            (100, false),
+            (110, true),
+            (120, false),
+            (400, true),
+            (410, false),
+            (420, true),
+            (1000, false),
+            (1200, true),
+        ];
+        let actions = simulate(DOORBELL, true, &edges, 1500);
+        assert_eq!(actions, [(150, Action::Notify), (1030, Action::Notify)]);
+    }
+
+    #[test]
+    fn ignore_glitches() {
+        let edges = [(100, false), (110, true), (500, false), (520, true)];
+        let actions = simulate(DOORBELL, true, &edges, 1000);
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn door_contact() {
+        // already open on startup, closed, and opened again
+        let edges = [(200, false), (800, true)];
+        let actions = simulate(DOOR, true, &edges, 1000);
+        assert_eq!(
+            actions,
+            [(30, Action::On), (230, Action::Off), (830, Action::On)]
+        );
+
+        // closed on startup
+        let actions = simulate(DOOR, false, &[], 100);
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn both_edges() {
+        let config = Config {
+            sense: Sense::Both,
+            action: Action::Toggle,
+            release: None,
+        };
+        let edges = [(100, true), (300, false)];
+        let actions = simulate(config, false, &edges, 500);
+        assert_eq!(actions, [(130, Action::Toggle), (330, Action::Toggle)]);
+    }
+}
@@ -0,0 +1,199 @@
+/// Company identifier of the manufacturer specific data.
+///
+/// This is the identifier reserved for testing, until we have one assigned.
+pub const COMPANY_ID: u16 = 0xFFFF;
+
+/// Version of the encoding, increased on incompatible changes.
+pub const VERSION: u8 = 1;
+
+/// AD type of manufacturer specific data.
+const AD_MANUFACTURER_DATA: u8 = 0xFF;
+
+const FLAG_ON: u8 = 0x01;
+const FLAG_SLEEPING: u8 = 0x02;
+
+/// The state of a light, as advertised in the manufacturer specific data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LightState {
+    /// Identifier of the mode, see [`ModeId`](crate::ModeId)
+    pub mode: u8,
+    pub brightness: u8,
+    pub speed: u8,
+    pub on: bool,
+    pub sleeping: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data is too short
+    Length,
+    /// Manufacturer data of a different company
+    CompanyId,
+    /// Unsupported version of the encoding
+    Version(u8),
+    /// Unknown kind of color
+    Kind(u8),
+    /// A value out of range
+    Range,
+}
+
+impl LightState {
+    /// Length of the encoded manufacturer data, including the company ID.
+    pub const ENCODED_LEN: usize = 7;
+
+    pub const fn new() -> Self {
+        Self {
+            mode: 0,
+            brightness: 0,
+            speed: 0,
+            on: false,
+            sleeping: false,
+        }
+    }
+
+    /// Encode as manufacturer specific data (without the AD length and type).
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        let company = COMPANY_ID.to_le_bytes();
+        let mut flags = 0;
+        if self.on {
+            flags |= FLAG_ON;
+        }
+        if self.sleeping {
+            flags |= FLAG_SLEEPING;
+        }
+        [
+            company[0],
+            company[1],
+            VERSION,
+            self.mode,
+            self.brightness,
+            self.speed,
+            flags,
+        ]
+    }
+
+    /// Decode from manufacturer specific data (without the AD length and type).
+    ///
+    /// Additional data, added by later versions, is ignored.
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        match data {
+            [c0, c1, version, rest @ ..] => {
+                if u16::from_le_bytes([*c0, *c1]) != COMPANY_ID {
+                    return Err(DecodeError::CompanyId);
+                }
+                if *version != VERSION {
+                    return Err(DecodeError::Version(*version));
+                }
+                match rest {
+                    [mode, brightness, speed, flags, ..] => Ok(Self {
+                        mode: *mode,
+                        brightness: *brightness,
+                        speed: *speed,
+                        on: flags & FLAG_ON != 0,
+                        sleeping: flags & FLAG_SLEEPING != 0,
+                    }),
+                    _ => Err(DecodeError::Length),
+                }
+            }
+            _ => Err(DecodeError::Length),
+        }
+    }
+
+    /// Encode as a complete AD structure, which can be appended to the advertising data.
+    pub fn encode_ad(&self) -> [u8; Self::ENCODED_LEN + 2] {
+        let mut result = [0; Self::ENCODED_LEN + 2];
+        result[0] = (Self::ENCODED_LEN + 1) as u8;
+        result[1] = AD_MANUFACTURER_DATA;
+        result[2..].copy_from_slice(&self.encode());
+        result
+    }
+
+    /// Find and decode the state in raw advertising data.
+    pub fn from_advertisement(data: &[u8]) -> Option<Self> {
+        let mut data = data;
+        while let [len, rest @ ..] = data {
+            let len = *len as usize;
+            if len == 0 || len > rest.len() {
+                break;
+            }
+            let (ad, remainder) = rest.split_at(len);
+            if ad[0] == AD_MANUFACTURER_DATA {
+                if let Ok(state) = Self::decode(&ad[1..]) {
+                    return Some(state);
+                }
+            }
+            data = remainder;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STATE: LightState = LightState {
+        mode: 2,
+        brightness: 16,
+        speed: 127,
+        on: true,
+        sleeping: false,
+    };
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(LightState::decode(&STATE.encode()), Ok(STATE));
+
+        let state = LightState {
+            on: false,
+            sleeping: true,
+            ..STATE
+        };
+        assert_eq!(LightState::decode(&state.encode()), Ok(state));
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(STATE.encode(), [0xFF, 0xFF, 0x01, 2, 16, 127, 0x01]);
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(LightState::decode(&[0xFF]), Err(DecodeError::Length));
+        assert_eq!(
+            LightState::decode(&[0xFF, 0xFF, 0x01, 2]),
+            Err(DecodeError::Length)
+        );
+        assert_eq!(
+            LightState::decode(&[0x59, 0x00, 0x01, 2, 16, 127, 0x01]),
+            Err(DecodeError::CompanyId)
+        );
+        assert_eq!(
+            LightState::decode(&[0xFF, 0xFF, 0x02, 2, 16, 127, 0x01]),
+            Err(DecodeError::Version(2))
+        );
+    }
+
+    #[test]
+    fn ignore_trailing_data() {
+        let mut data = [0u8; LightState::ENCODED_LEN + 2];
+        data[..LightState::ENCODED_LEN].copy_from_slice(&STATE.encode());
+        assert_eq!(LightState::decode(&data), Ok(STATE));
+    }
+
+    #[test]
+    fn from_advertisement() {
+        let mut adv = [0u8; 31];
+        let prefix = [0x02, 0x01, 0x06, 0x03, 0x03, 0x60, 0x18];
+        adv[..prefix.len()].copy_from_slice(&prefix);
+        adv[prefix.len()..prefix.len() + 9].copy_from_slice(&STATE.encode_ad());
+
+        assert_eq!(LightState::from_advertisement(&adv), Some(STATE));
+        assert_eq!(LightState::from_advertisement(&prefix), None);
+        // truncated structure
+        assert_eq!(
+            LightState::from_advertisement(&adv[..prefix.len() + 4]),
+            None
+        );
+    }
+}
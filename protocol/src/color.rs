@@ -0,0 +1,222 @@
+//! Colors, as set over BLE or the console.
+//!
+//! A color keeps the form it was set in, so that it is shown exactly as requested. HSV uses a hue
+//! in degrees, and saturation and value from `0` to `255`.
+
+use crate::DecodeError;
+
+const KIND_RGB: u8 = 0x00;
+const KIND_HSV: u8 = 0x01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Rgb { red: u8, green: u8, blue: u8 },
+    Hsv { hue: u16, saturation: u8, value: u8 },
+}
+
+impl Color {
+    /// Length of the encoded color.
+    pub const ENCODED_LEN: usize = 5;
+
+    pub const WHITE: Self = Self::Rgb {
+        red: 0xFF,
+        green: 0xFF,
+        blue: 0xFF,
+    };
+
+    /// The color as red, green and blue.
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Self::Rgb { red, green, blue } => (red, green, blue),
+            Self::Hsv {
+                hue,
+                saturation,
+                value,
+            } => hsv_to_rgb(hue, saturation, value),
+        }
+    }
+
+    /// The color as hue, saturation and value.
+    pub fn hsv(&self) -> (u16, u8, u8) {
+        match *self {
+            Self::Rgb { red, green, blue } => rgb_to_hsv(red, green, blue),
+            Self::Hsv {
+                hue,
+                saturation,
+                value,
+            } => (hue % 360, saturation, value),
+        }
+    }
+
+    /// Encode as a kind, followed by the components. Hues are little endian.
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        match *self {
+            Self::Rgb { red, green, blue } => [KIND_RGB, red, green, blue, 0],
+            Self::Hsv {
+                hue,
+                saturation,
+                value,
+            } => {
+                let hue = hue.to_le_bytes();
+                [KIND_HSV, hue[0], hue[1], saturation, value]
+            }
+        }
+    }
+
+    /// Decode a color. RGB colors may leave out the padding.
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        match data {
+            [KIND_RGB, red, green, blue, ..] => Ok(Self::Rgb {
+                red: *red,
+                green: *green,
+                blue: *blue,
+            }),
+            [KIND_HSV, h0, h1, saturation, value, ..] => Ok(Self::Hsv {
+                hue: u16::from_le_bytes([*h0, *h1]) % 360,
+                saturation: *saturation,
+                value: *value,
+            }),
+            [KIND_RGB | KIND_HSV, ..] | [] => Err(DecodeError::Length),
+            [kind, ..] => Err(DecodeError::Kind(*kind)),
+        }
+    }
+}
+
+fn hsv_to_rgb(hue: u16, saturation: u8, value: u8) -> (u8, u8, u8) {
+    let hue = (hue % 360) as u32;
+    let (s, v) = (saturation as u32, value as u32);
+    // the position inside the sector of 60°, from 0 to 255
+    let f = (hue % 60) * 255 / 60;
+
+    let p = (v * (255 - s) / 255) as u8;
+    let q = (v * (255 * 255 - s * f) / (255 * 255)) as u8;
+    let t = (v * (255 * 255 - s * (255 - f)) / (255 * 255)) as u8;
+    let v = v as u8;
+
+    match hue / 60 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    }
+}
+
+fn rgb_to_hsv(red: u8, green: u8, blue: u8) -> (u16, u8, u8) {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let delta = (max - min) as i32;
+    if delta == 0 {
+        return (0, 0, max);
+    }
+
+    let saturation = (delta * 255 / max as i32) as u8;
+    let (r, g, b) = (red as i32, green as i32, blue as i32);
+    let hue = if max == red {
+        60 * (g - b) / delta
+    } else if max == green {
+        120 + 60 * (b - r) / delta
+    } else {
+        240 + 60 * (r - g) / delta
+    };
+
+    (hue.rem_euclid(360) as u16, saturation, max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ORANGE: Color = Color::Rgb {
+        red: 0xFF,
+        green: 0x80,
+        blue: 0x00,
+    };
+
+    #[test]
+    fn conversions() {
+        let primaries = [
+            (0, (255, 0, 0)),
+            (60, (255, 255, 0)),
+            (120, (0, 255, 0)),
+            (180, (0, 255, 255)),
+            (240, (0, 0, 255)),
+            (300, (255, 0, 255)),
+        ];
+        for (hue, rgb) in primaries {
+            let color = Color::Hsv {
+                hue,
+                saturation: 255,
+                value: 255,
+            };
+            assert_eq!(color.rgb(), rgb);
+            assert_eq!(color.hsv(), (hue, 255, 255));
+            assert_eq!(
+                Color::Rgb {
+                    red: rgb.0,
+                    green: rgb.1,
+                    blue: rgb.2
+                }
+                .hsv(),
+                (hue, 255, 255)
+            );
+        }
+
+        assert_eq!(ORANGE.rgb(), (255, 128, 0));
+        assert_eq!(ORANGE.hsv(), (30, 255, 255));
+        assert_eq!(Color::WHITE.hsv(), (0, 0, 255));
+        let pale = Color::Hsv {
+            hue: 200,
+            saturation: 0,
+            value: 128,
+        };
+        assert_eq!(pale.rgb(), (128, 128, 128));
+    }
+
+    #[test]
+    fn round_trip() {
+        for hue in 0..360 {
+            let (r, g, b) = Color::Hsv {
+                hue,
+                saturation: 255,
+                value: 255,
+            }
+            .rgb();
+            let (h, s, v) = Color::Rgb {
+                red: r,
+                green: g,
+                blue: b,
+            }
+            .hsv();
+            assert!(h.abs_diff(hue) <= 1, "{} {}", hue, h);
+            assert_eq!((s, v), (255, 255));
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(ORANGE.encode(), [0x00, 0xFF, 0x80, 0x00, 0x00]);
+        let color = Color::Hsv {
+            hue: 300,
+            saturation: 128,
+            value: 255,
+        };
+        assert_eq!(color.encode(), [0x01, 0x2C, 0x01, 0x80, 0xFF]);
+
+        assert_eq!(Color::decode(&ORANGE.encode()), Ok(ORANGE));
+        assert_eq!(Color::decode(&[0x00, 0xFF, 0x80, 0x00]), Ok(ORANGE));
+        assert_eq!(Color::decode(&color.encode()), Ok(color));
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(Color::decode(&[]), Err(DecodeError::Length));
+        assert_eq!(Color::decode(&[0x00, 0xFF]), Err(DecodeError::Length));
+        assert_eq!(
+            Color::decode(&[0x01, 0x2C, 0x01, 0x80]),
+            Err(DecodeError::Length)
+        );
+        assert_eq!(Color::decode(&[0x02, 0, 0, 0]), Err(DecodeError::Kind(2)));
+    }
+}
@@ -0,0 +1,16 @@
+#![no_std]
+
+//! Data shared between the firmware and host side tools.
+//!
+//! This crate must stay `no_std` and free of hardware dependencies, so that it can be used on the
+//! device as well as on the host.
+
+pub mod advertisement;
+pub mod color;
+pub mod mode;
+pub mod time;
+
+pub use advertisement::*;
+pub use color::Color;
+pub use mode::ModeId;
+pub use time::TimeOfDay;
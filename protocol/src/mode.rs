@@ -0,0 +1,88 @@
+//! Identifiers of the modes, shared by the advertisement and the remote controls.
+
+/// The identifier of a mode.
+///
+/// Identifiers don't change: new modes get the next free identifier, and identifiers of removed
+/// modes are not reused. Depending on its features, a firmware may not support all modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ModeId {
+    Off = 0,
+    Fire = 1,
+    Rainbow = 2,
+    RainbowPart = 3,
+    Solid = 4,
+    Liquid = 5,
+    Comet = 6,
+    Fireworks = 7,
+    BouncingBalls = 8,
+    Twinkle = 9,
+    Confetti = 10,
+    Plasma = 11,
+    LavaLamp = 12,
+    Aurora = 13,
+    TheaterChase = 14,
+    Larson = 15,
+    RunningLights = 16,
+    ColorWipe = 17,
+    Breathing = 18,
+    Pulse = 19,
+    Gradient = 20,
+    Rule30 = 21,
+    Rule110 = 22,
+    Life = 23,
+    Clock = 24,
+}
+
+impl ModeId {
+    /// All identifiers, in order.
+    pub const ALL: [Self; 25] = [
+        Self::Off,
+        Self::Fire,
+        Self::Rainbow,
+        Self::RainbowPart,
+        Self::Solid,
+        Self::Liquid,
+        Self::Comet,
+        Self::Fireworks,
+        Self::BouncingBalls,
+        Self::Twinkle,
+        Self::Confetti,
+        Self::Plasma,
+        Self::LavaLamp,
+        Self::Aurora,
+        Self::TheaterChase,
+        Self::Larson,
+        Self::RunningLights,
+        Self::ColorWipe,
+        Self::Breathing,
+        Self::Pulse,
+        Self::Gradient,
+        Self::Rule30,
+        Self::Rule110,
+        Self::Life,
+        Self::Clock,
+    ];
+
+    /// The mode of an identifier, if known.
+    pub fn from_u8(id: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| *mode as u8 == id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(ModeId::from_u8(0), Some(ModeId::Off));
+        assert_eq!(ModeId::from_u8(1), Some(ModeId::Fire));
+        assert_eq!(ModeId::from_u8(24), Some(ModeId::Clock));
+        assert_eq!(ModeId::from_u8(25), None);
+        assert_eq!(ModeId::from_u8(u8::MAX), None);
+        for (i, mode) in ModeId::ALL.iter().enumerate() {
+            assert_eq!(*mode as usize, i);
+        }
+    }
+}
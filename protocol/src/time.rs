@@ -0,0 +1,92 @@
+//! Time of day, for setting the wall clock over BLE or the console.
+
+use crate::DecodeError;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeOfDay {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+impl TimeOfDay {
+    /// Length of the encoded time.
+    pub const ENCODED_LEN: usize = 3;
+
+    pub const MIDNIGHT: Self = Self {
+        hours: 0,
+        minutes: 0,
+        seconds: 0,
+    };
+
+    /// Create a time, if the values are in range.
+    pub fn new(hours: u8, minutes: u8, seconds: u8) -> Option<Self> {
+        if hours < 24 && minutes < 60 && seconds < 60 {
+            Some(Self {
+                hours,
+                minutes,
+                seconds,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The time from the seconds since midnight, wrapping around at the end of the day.
+    pub fn from_seconds(seconds: u32) -> Self {
+        let seconds = seconds % SECONDS_PER_DAY;
+        Self {
+            hours: (seconds / 3600) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+        }
+    }
+
+    /// The seconds since midnight.
+    pub fn seconds(&self) -> u32 {
+        self.hours as u32 * 3600 + self.minutes as u32 * 60 + self.seconds as u32
+    }
+
+    /// Encode as hours, minutes and seconds.
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        [self.hours, self.minutes, self.seconds]
+    }
+
+    /// Decode a time, from exactly hours, minutes and seconds.
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        match data {
+            [hours, minutes, seconds] => {
+                Self::new(*hours, *minutes, *seconds).ok_or(DecodeError::Range)
+            }
+            _ => Err(DecodeError::Length),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seconds() {
+        let time = TimeOfDay::new(13, 45, 30).unwrap();
+        assert_eq!(time.seconds(), 49530);
+        assert_eq!(TimeOfDay::from_seconds(49530), time);
+        assert_eq!(TimeOfDay::from_seconds(SECONDS_PER_DAY + 1).seconds(), 1);
+        assert_eq!(TimeOfDay::new(24, 0, 0), None);
+        assert_eq!(TimeOfDay::new(23, 60, 0), None);
+    }
+
+    #[test]
+    fn encoding() {
+        let time = TimeOfDay::new(7, 5, 9).unwrap();
+        assert_eq!(time.encode(), [7, 5, 9]);
+        assert_eq!(TimeOfDay::decode(&time.encode()), Ok(time));
+        assert_eq!(TimeOfDay::decode(&[7, 5]), Err(DecodeError::Length));
+        assert_eq!(TimeOfDay::decode(&[7, 5, 9, 0]), Err(DecodeError::Length));
+        assert_eq!(TimeOfDay::decode(&[7]), Err(DecodeError::Length));
+        assert_eq!(TimeOfDay::decode(&[7, 61, 0]), Err(DecodeError::Range));
+    }
+}